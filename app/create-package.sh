@@ -76,6 +76,11 @@ O servidor web permite consultar CNPJs via API REST:
 - GET /api/progress - Progresso de operações
 - POST /api/download/start - Iniciar download
 - POST /api/process/start - Iniciar processamento
+- POST /api/download/cancel, /api/process/cancel - Cancelar operação em andamento
+- POST /api/admin/reload - Trocar o banco sem reiniciar (ou use: server --watch)
+- GET /metrics - Métricas no formato do Prometheus
+- GET /openapi.json - Especificação OpenAPI 3
+- GET /docs/ - Documentação interativa (Swagger UI)
 
 Iniciar servidor:
   ./cnpj-sqlite server --port 8080
@@ -0,0 +1,557 @@
+//! Geração de uma amostra sintética no formato dos arquivos da Receita Federal, para
+//! testes e demonstrações sem baixar os 15 GB dos dados reais.
+//!
+//! Os ZIPs têm os mesmos nomes dos publicados pela Receita (`Empresas0.zip`,
+//! `Estabelecimentos0.zip`, ..., `Simples.zip`, `Cnaes.zip`, ...), com uma entrada cada
+//! no padrão `K3241.K03200Y0.D40511.EMPRECSV`. O conteúdo segue o formato original:
+//! campos entre aspas separados por `;`, sem cabeçalho e em Latin-1. Os CNPJs têm
+//! dígitos verificadores válidos e os códigos usados existem nas tabelas de código.
+//!
+//! A mesma semente gera sempre os mesmos registros.
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Days, NaiveDate};
+use indicatif::ProgressStyle;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+use crate::cnpj;
+use crate::referencia;
+use crate::ui;
+
+/// Parâmetros do subcomando `gerar-amostra`
+#[derive(Debug, Clone)]
+pub struct OpcoesAmostra {
+    /// Pasta onde os ZIPs são gravados (criada se não existir)
+    pub output_dir: String,
+    /// Quantidade de empresas; cada uma tem a matriz e, às vezes, filiais
+    pub empresas: usize,
+    /// Quantidade de ZIPs de empresas, estabelecimentos e sócios (a Receita publica 10)
+    pub partes: usize,
+    /// Mês de referência (AAAA-MM), usado nos nomes das entradas e como data limite
+    pub referencia: String,
+    pub semente: u64,
+}
+
+/// Quantidade de registros gerados de cada tipo
+#[derive(Debug, Clone, Default)]
+pub struct ResumoAmostra {
+    pub empresas: u64,
+    pub estabelecimentos: u64,
+    pub socios: u64,
+    pub simples: u64,
+    /// ZIPs gravados, na ordem em que foram criados
+    pub arquivos: Vec<PathBuf>,
+}
+
+const CNAES: &[(&str, &str)] = &[
+    ("0111301", "Cultivo de arroz"),
+    ("1091101", "Fabricação de produtos de panificação industrial"),
+    ("4120400", "Construção de edifícios"),
+    ("4399103", "Obras de alvenaria"),
+    ("4520001", "Serviços de manutenção e reparação mecânica de veículos automotores"),
+    ("4711302", "Comércio varejista de mercadorias em geral, com predominância de produtos alimentícios - supermercados"),
+    ("4744099", "Comércio varejista de materiais de construção em geral"),
+    ("4771701", "Comércio varejista de produtos farmacêuticos, sem manipulação de fórmulas"),
+    ("4781400", "Comércio varejista de artigos do vestuário e acessórios"),
+    ("4930202", "Transporte rodoviário de carga, exceto produtos perigosos e mudanças, intermunicipal, interestadual e internacional"),
+    ("5611201", "Restaurantes e similares"),
+    ("5620104", "Fornecimento de alimentos preparados preponderantemente para consumo domiciliar"),
+    ("6201501", "Desenvolvimento de programas de computador sob encomenda"),
+    ("6911701", "Serviços advocatícios"),
+    ("6920601", "Atividades de contabilidade"),
+    ("7319002", "Promoção de vendas"),
+    ("8211300", "Serviços combinados de escritório e apoio administrativo"),
+    ("8599604", "Treinamento em desenvolvimento profissional e gerencial"),
+    ("8610101", "Atividades de atendimento hospitalar, exceto pronto-socorro e unidades para atendimento a urgências"),
+    ("8630503", "Atividade médica ambulatorial restrita a consultas"),
+    ("8650004", "Atividades de fisioterapia"),
+    ("9602501", "Cabeleireiros, manicure e pedicure"),
+];
+
+/// Código, nome, UF, DDD e prefixo do CEP
+const MUNICIPIOS: &[(&str, &str, &str, &str, &str)] = &[
+    ("0255", "MANAUS", "AM", "92", "690"),
+    ("1389", "FORTALEZA", "CE", "85", "600"),
+    ("2531", "RECIFE", "PE", "81", "500"),
+    ("3849", "SALVADOR", "BA", "71", "400"),
+    ("4123", "BELO HORIZONTE", "MG", "31", "301"),
+    ("6001", "RIO DE JANEIRO", "RJ", "21", "200"),
+    ("6291", "CAMPINAS", "SP", "19", "130"),
+    ("7107", "SAO PAULO", "SP", "11", "010"),
+    ("7535", "CURITIBA", "PR", "41", "800"),
+    ("8047", "BLUMENAU", "SC", "47", "890"),
+    ("8105", "FLORIANOPOLIS", "SC", "48", "880"),
+    ("8179", "JOINVILLE", "SC", "47", "892"),
+    ("8801", "PORTO ALEGRE", "RS", "51", "900"),
+    ("9373", "GOIANIA", "GO", "62", "740"),
+    ("9701", "BRASILIA", "DF", "61", "700"),
+];
+
+const NATUREZAS: &[(&str, &str)] = &[
+    ("2054", "Sociedade Anônima Fechada"),
+    ("2062", "Sociedade Empresária Limitada"),
+    ("2135", "Empresário (Individual)"),
+    ("2240", "Sociedade Simples Limitada"),
+    ("3999", "Associação Privada"),
+];
+
+const QUALIFICACOES: &[(&str, &str)] = &[
+    ("00", "Não informada"),
+    ("05", "Administrador"),
+    ("10", "Diretor"),
+    ("16", "Presidente"),
+    ("22", "Sócio"),
+    ("49", "Sócio-Administrador"),
+    ("50", "Empresário"),
+];
+
+const MOTIVOS: &[(&str, &str)] = &[
+    ("00", "SEM MOTIVO"),
+    ("01", "EXTINCAO POR ENCERRAMENTO LIQUIDACAO VOLUNTARIA"),
+    ("63", "OMISSAO DE DECLARACOES"),
+    ("73", "OMISSAO CONTUMAZ"),
+];
+
+const PAISES: &[(&str, &str)] = &[
+    ("023", "ALEMANHA"),
+    ("063", "ARGENTINA"),
+    ("105", "BRASIL"),
+    ("249", "ESTADOS UNIDOS"),
+    ("607", "PORTUGAL"),
+];
+
+const PRENOMES: &[&str] = &[
+    "JOÃO", "MARIA", "JOSÉ", "ANA", "ANTÔNIO", "FRANCISCA", "CARLOS", "PAULA", "LUÍS",
+    "MÁRCIA", "PEDRO", "JÚLIA", "SÉRGIO", "CONCEIÇÃO", "FÁBIO", "LETÍCIA",
+];
+
+const SOBRENOMES: &[&str] = &[
+    "SILVA", "SANTOS", "OLIVEIRA", "SOUZA", "LIMA", "PEREIRA", "FERREIRA", "COSTA",
+    "RODRIGUES", "ALMEIDA", "NASCIMENTO", "ARAÚJO", "MACHADO", "CONCEIÇÃO", "GONÇALVES",
+];
+
+const PALAVRAS: &[&str] = &[
+    "ALFA", "AURORA", "ATLÂNTICO", "BOA VISTA", "CENTRAL", "ESTRELA", "HORIZONTE", "IPÊ",
+    "LITORAL", "NORDESTE", "PANTANAL", "PIONEIRA", "SÃO JORGE", "SERRA AZUL", "UNIÃO",
+];
+
+const RAMOS: &[&str] = &[
+    "COMÉRCIO", "SERVIÇOS", "CONSTRUÇÕES", "TRANSPORTES", "ALIMENTOS", "TECNOLOGIA",
+    "DISTRIBUIÇÃO", "SAÚDE", "EDUCAÇÃO", "CONFECÇÕES",
+];
+
+const TIPOS_LOGRADOURO: &[&str] = &["RUA", "AVENIDA", "TRAVESSA", "ALAMEDA", "RODOVIA"];
+
+const LOGRADOUROS: &[&str] = &[
+    "DAS FLORES", "SETE DE SETEMBRO", "GETÚLIO VARGAS", "QUINZE DE NOVEMBRO", "DOM PEDRO II",
+    "SÃO JOÃO", "DAS PALMEIRAS", "BEIRA-MAR", "JOSÉ BONIFÁCIO", "DO COMÉRCIO",
+];
+
+const BAIRROS: &[&str] = &["CENTRO", "JARDIM AMÉRICA", "VILA NOVA", "SÃO JOSÉ", "SANTA MÔNICA", "INDUSTRIAL"];
+
+/// Gerador pseudoaleatório SplitMix64: simples e estável entre versões, para que a mesma
+/// semente gere sempre a mesma amostra
+struct Sorteio(u64);
+
+impl Sorteio {
+    fn proximo(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Número entre 0 e `limite - 1`
+    fn ate(&mut self, limite: u64) -> u64 {
+        self.proximo() % limite.max(1)
+    }
+
+    /// Verdadeiro com a probabilidade informada, em porcentagem
+    fn chance(&mut self, porcentagem: u64) -> bool {
+        self.ate(100) < porcentagem
+    }
+
+    fn escolher<'a, T>(&mut self, opcoes: &'a [T]) -> &'a T {
+        &opcoes[self.ate(opcoes.len() as u64) as usize]
+    }
+
+    fn digitos(&mut self, quantidade: usize) -> String {
+        (0..quantidade).map(|_| char::from(b'0' + self.ate(10) as u8)).collect()
+    }
+
+    /// Data entre `inicio` e `fim`, inclusive
+    fn data(&mut self, inicio: NaiveDate, fim: NaiveDate) -> NaiveDate {
+        let dias = (fim - inicio).num_days().max(0) as u64;
+        inicio + Days::new(self.ate(dias + 1))
+    }
+
+    fn nome_pessoa(&mut self) -> String {
+        format!("{} {} {}", self.escolher(PRENOMES), self.escolher(SOBRENOMES), self.escolher(SOBRENOMES))
+    }
+}
+
+/// Datas no formato dos arquivos (AAAAMMDD)
+fn data_receita(data: NaiveDate) -> String {
+    data.format("%Y%m%d").to_string()
+}
+
+/// ZIP com uma única entrada, escrita registro a registro em Latin-1
+struct ArquivoZip {
+    caminho: PathBuf,
+    csv: csv::Writer<ZipWriter<File>>,
+}
+
+impl ArquivoZip {
+    fn criar(pasta: &Path, nome_zip: &str, entrada: &str, data: NaiveDate) -> Result<Self> {
+        let caminho = pasta.join(nome_zip);
+        let arquivo = File::create(&caminho).with_context(|| format!("Falha ao criar {:?}", caminho))?;
+        let mut zip = ZipWriter::new(arquivo);
+        // A data da entrada resolve a década do trecho D40511 ao detectar a referência
+        let modificado = zip::DateTime::from_date_and_time(data.year() as u16, data.month() as u8, data.day() as u8, 0, 0, 0)
+            .map_err(|_| anyhow::anyhow!("Data fora do intervalo aceito pelo ZIP: {}", data))?;
+        let opcoes = FileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .last_modified_time(modificado);
+        zip.start_file(entrada, opcoes)?;
+        let csv = csv::WriterBuilder::new()
+            .delimiter(b';')
+            .quote_style(csv::QuoteStyle::Always)
+            .from_writer(zip);
+        Ok(Self { caminho, csv })
+    }
+
+    fn gravar(&mut self, campos: &[String]) -> Result<()> {
+        // Todos os textos da amostra cabem em Latin-1, a codificação dos arquivos da Receita
+        let campos = campos.iter().map(|campo| encoding_rs::WINDOWS_1252.encode(campo).0);
+        self.csv.write_record(campos)?;
+        Ok(())
+    }
+
+    fn concluir(self) -> Result<PathBuf> {
+        let mut zip = self.csv.into_inner().map_err(|e| anyhow::anyhow!("{}", e.error()))?;
+        zip.flush()?;
+        zip.finish()?;
+        Ok(self.caminho)
+    }
+}
+
+/// Gera a amostra completa: tabelas de código, empresas, estabelecimentos, sócios e Simples
+pub fn gerar(opcoes: &OpcoesAmostra) -> Result<ResumoAmostra> {
+    let mes = referencia::validar_iso(&opcoes.referencia)
+        .with_context(|| format!("Mês de referência inválido: {} (use AAAA-MM)", opcoes.referencia))?;
+    anyhow::ensure!(opcoes.empresas > 0, "A amostra precisa de pelo menos uma empresa");
+    anyhow::ensure!((1..=10).contains(&opcoes.partes), "A quantidade de partes deve ser de 1 a 10");
+    let hoje = NaiveDate::parse_from_str(&format!("{}-11", mes), "%Y-%m-%d")?;
+    // Trecho do nome das entradas: último dígito do ano, mês e dia (D40511)
+    let data_nome = format!("D{}{:02}11", hoje.year() % 10, hoje.month());
+
+    let pasta = Path::new(&opcoes.output_dir);
+    fs::create_dir_all(pasta).with_context(|| format!("Falha ao criar diretório: {}", opcoes.output_dir))?;
+
+    ui::print_header("🧪 Geração de Amostra Sintética");
+    ui::print_info(&format!("Diretório de saída: {}", opcoes.output_dir));
+    ui::print_info(&format!(
+        "Empresas: {} | Partes: {} | Referência: {} | Semente: {}",
+        opcoes.empresas, opcoes.partes, mes, opcoes.semente
+    ));
+
+    let mut resumo = ResumoAmostra::default();
+
+    let tabelas_codigo = [
+        ("Cnaes.zip", "CNAECSV", CNAES.to_vec()),
+        ("Motivos.zip", "MOTICSV", MOTIVOS.to_vec()),
+        ("Municipios.zip", "MUNICCSV", MUNICIPIOS.iter().map(|m| (m.0, m.1)).collect()),
+        ("Naturezas.zip", "NATJUCSV", NATUREZAS.to_vec()),
+        ("Paises.zip", "PAISCSV", PAISES.to_vec()),
+        ("Qualificacoes.zip", "QUALSCSV", QUALIFICACOES.to_vec()),
+    ];
+    for (nome_zip, sufixo, linhas) in tabelas_codigo {
+        let entrada = format!("F.K03200$Z.{}.{}", data_nome, sufixo);
+        let mut arquivo = ArquivoZip::criar(pasta, nome_zip, &entrada, hoje)?;
+        for (codigo, descricao) in linhas {
+            arquivo.gravar(&[codigo.to_string(), descricao.to_string()])?;
+        }
+        resumo.arquivos.push(arquivo.concluir()?);
+    }
+
+    let mut partes = Vec::with_capacity(opcoes.partes);
+    for parte in 0..opcoes.partes {
+        let entrada = |sufixo: &str| format!("K3241.K03200Y{}.{}.{}", parte, data_nome, sufixo);
+        partes.push((
+            ArquivoZip::criar(pasta, &format!("Empresas{}.zip", parte), &entrada("EMPRECSV"), hoje)?,
+            ArquivoZip::criar(pasta, &format!("Estabelecimentos{}.zip", parte), &entrada("ESTABELE"), hoje)?,
+            ArquivoZip::criar(pasta, &format!("Socios{}.zip", parte), &entrada("SOCIOCSV"), hoje)?,
+        ));
+    }
+    let mut simples = ArquivoZip::criar(pasta, "Simples.zip", &format!("F.K03200$W.SIMPLES.CSV.{}", data_nome), hoje)?;
+
+    let mut sorteio = Sorteio(opcoes.semente);
+    let mut basicos = HashSet::with_capacity(opcoes.empresas);
+    let pb = ui::progress_bar(opcoes.empresas as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("  Empresas: [{bar:40.cyan/blue}] {pos}/{len} ({percent}%) | ETA: {eta}")?
+            .progress_chars("#>-"),
+    );
+    for indice in 0..opcoes.empresas {
+        let basico = loop {
+            let candidato = sorteio.digitos(8);
+            if basicos.insert(candidato.clone()) {
+                break candidato;
+            }
+        };
+        let (empresas, estabelecimentos, socios) = &mut partes[indice % opcoes.partes];
+        let empresa = gerar_empresa(&mut sorteio, &basico, hoje);
+
+        empresas.gravar(&empresa.campos)?;
+        resumo.empresas += 1;
+        for estabelecimento in gerar_estabelecimentos(&mut sorteio, &basico, &empresa, hoje) {
+            estabelecimentos.gravar(&estabelecimento)?;
+            resumo.estabelecimentos += 1;
+        }
+        for socio in gerar_socios(&mut sorteio, &basico, &empresa, hoje) {
+            socios.gravar(&socio)?;
+            resumo.socios += 1;
+        }
+        if let Some(linha) = gerar_simples(&mut sorteio, &basico, &empresa, hoje) {
+            simples.gravar(&linha)?;
+            resumo.simples += 1;
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    for (empresas, estabelecimentos, socios) in partes {
+        resumo.arquivos.push(empresas.concluir()?);
+        resumo.arquivos.push(estabelecimentos.concluir()?);
+        resumo.arquivos.push(socios.concluir()?);
+    }
+    resumo.arquivos.push(simples.concluir()?);
+
+    ui::print_success(&format!("{} arquivos ZIP gerados", resumo.arquivos.len()));
+    ui::print_statistics(&[
+        ("Empresas", resumo.empresas),
+        ("Estabelecimentos", resumo.estabelecimentos),
+        ("Sócios", resumo.socios),
+        ("Simples", resumo.simples),
+    ]);
+    ui::print_verbose(&format!(
+        "Para processar: cnpj-sqlite process -i {} --referencia {}",
+        opcoes.output_dir, mes
+    ));
+    Ok(resumo)
+}
+
+/// Empresa gerada e o que os outros arquivos precisam saber dela
+struct EmpresaGerada {
+    campos: Vec<String>,
+    individual: bool,
+    porte: &'static str,
+    inicio: NaiveDate,
+}
+
+fn gerar_empresa(sorteio: &mut Sorteio, basico: &str, hoje: NaiveDate) -> EmpresaGerada {
+    let (natureza, _) = *sorteio.escolher(NATUREZAS);
+    let individual = natureza == "2135";
+    let razao_social = match natureza {
+        // Empresário individual: nome do titular seguido do CPF, como na base real
+        "2135" => format!("{} {}", sorteio.nome_pessoa(), sorteio.digitos(11)),
+        "2054" => format!("{} {} S.A.", sorteio.escolher(PALAVRAS), sorteio.escolher(RAMOS)),
+        "3999" => format!("ASSOCIAÇÃO {} DE {}", sorteio.escolher(PALAVRAS), sorteio.escolher(RAMOS)),
+        _ => format!("{} {} {} LTDA", sorteio.escolher(SOBRENOMES), sorteio.escolher(PALAVRAS), sorteio.escolher(RAMOS)),
+    };
+    let qualificacao = match natureza {
+        "2135" => "50",
+        "2054" => "10",
+        "3999" => "16",
+        _ => "49",
+    };
+    let porte = if individual { "01" } else { *sorteio.escolher(&["00", "01", "01", "03", "05"]) };
+    let capital = match porte {
+        "01" => 1_000 + sorteio.ate(80_000),
+        "03" => 100_000 + sorteio.ate(900_000),
+        _ => 10_000 + sorteio.ate(50_000_000),
+    };
+    let centavos = if sorteio.chance(20) { sorteio.ate(100) } else { 0 };
+    let inicio = sorteio.data(NaiveDate::from_ymd_opt(1975, 1, 1).unwrap_or(hoje), hoje);
+    EmpresaGerada {
+        campos: vec![
+            basico.to_string(),
+            razao_social,
+            natureza.to_string(),
+            qualificacao.to_string(),
+            format!("{},{:02}", capital, centavos),
+            porte.to_string(),
+            String::new(),
+        ],
+        individual,
+        porte,
+        inicio,
+    }
+}
+
+fn gerar_estabelecimentos(
+    sorteio: &mut Sorteio,
+    basico: &str,
+    empresa: &EmpresaGerada,
+    hoje: NaiveDate,
+) -> Vec<Vec<String>> {
+    let filiais = if empresa.individual || !sorteio.chance(15) { 0 } else { 1 + sorteio.ate(3) };
+    (0..=filiais)
+        .map(|ordem| {
+            let ordem = format!("{:04}", ordem + 1);
+            let dv = cnpj::calcular_dv(&format!("{}{}", basico, ordem)).unwrap_or_default();
+            let inicio = if ordem == "0001" { empresa.inicio } else { sorteio.data(empresa.inicio, hoje) };
+            let (situacao, motivo) = match sorteio.ate(100) {
+                0..=64 => ("02", "00"),
+                65..=84 => ("08", "01"),
+                85..=94 => ("04", *sorteio.escolher(&["63", "73"])),
+                95..=97 => ("03", "00"),
+                _ => ("01", "00"),
+            };
+            let data_situacao = if situacao == "02" { inicio } else { sorteio.data(inicio, hoje) };
+            let (codigo_municipio, _, uf, ddd, prefixo_cep) = *sorteio.escolher(MUNICIPIOS);
+            let (cnae, _) = *sorteio.escolher(CNAES);
+            let mut secundarias: Vec<&str> = Vec::new();
+            for _ in 0..sorteio.ate(4) {
+                let (codigo, _) = *sorteio.escolher(CNAES);
+                if codigo != cnae && !secundarias.contains(&codigo) {
+                    secundarias.push(codigo);
+                }
+            }
+            let numero = if sorteio.chance(10) { "S/N".to_string() } else { (1 + sorteio.ate(3000)).to_string() };
+            let complemento = match sorteio.ate(4) {
+                0 => format!("SALA {}", 101 + sorteio.ate(20)),
+                1 => format!("LOJA {}", 1 + sorteio.ate(9)),
+                _ => String::new(),
+            };
+            let nome_fantasia = if sorteio.chance(60) {
+                format!("{} {}", sorteio.escolher(PALAVRAS), sorteio.escolher(RAMOS))
+            } else {
+                String::new()
+            };
+            let telefone = format!("{}{}", 3 + sorteio.ate(6), sorteio.digitos(7));
+            let email = if sorteio.chance(70) {
+                format!("contato{}@exemplo.com.br", &basico[..4])
+            } else {
+                String::new()
+            };
+            vec![
+                basico.to_string(),
+                ordem.clone(),
+                dv,
+                if ordem == "0001" { "1" } else { "2" }.to_string(),
+                nome_fantasia,
+                situacao.to_string(),
+                data_receita(data_situacao),
+                motivo.to_string(),
+                String::new(),
+                String::new(),
+                data_receita(inicio),
+                cnae.to_string(),
+                secundarias.join(","),
+                sorteio.escolher(TIPOS_LOGRADOURO).to_string(),
+                sorteio.escolher(LOGRADOUROS).to_string(),
+                numero,
+                complemento,
+                sorteio.escolher(BAIRROS).to_string(),
+                format!("{}{}", prefixo_cep, sorteio.digitos(5)),
+                uf.to_string(),
+                codigo_municipio.to_string(),
+                ddd.to_string(),
+                telefone,
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                email,
+                String::new(),
+                String::new(),
+            ]
+        })
+        .collect()
+}
+
+fn gerar_socios(sorteio: &mut Sorteio, basico: &str, empresa: &EmpresaGerada, hoje: NaiveDate) -> Vec<Vec<String>> {
+    // Empresários individuais não têm quadro societário
+    if empresa.individual {
+        return Vec::new();
+    }
+    (0..1 + sorteio.ate(3))
+        .map(|i| {
+            let entrada = if i == 0 { empresa.inicio } else { sorteio.data(empresa.inicio, hoje) };
+            let qualificacao = if i == 0 { "49" } else { *sorteio.escolher(&["05", "22", "49"]) };
+            let (identificador, nome, documento, pais, faixa) = match sorteio.ate(20) {
+                // Pessoa jurídica: CNPJ completo, com dígitos verificadores válidos
+                0 => {
+                    let base = sorteio.digitos(8) + "0001";
+                    let dv = cnpj::calcular_dv(&base).unwrap_or_default();
+                    let nome = format!("{} {} PARTICIPAÇÕES LTDA", sorteio.escolher(PALAVRAS), sorteio.escolher(RAMOS));
+                    ("1", nome, base + &dv, "", "0")
+                }
+                // Estrangeiro: sem CPF, com o país
+                1 => {
+                    let (pais, _) = *sorteio.escolher(PAISES);
+                    let pais = if pais == "105" { "607" } else { pais };
+                    ("3", sorteio.nome_pessoa(), "***999999**".to_string(), pais, "0")
+                }
+                // Pessoa física: CPF mascarado como na base pública
+                _ => {
+                    let faixa = ["2", "3", "4", "5", "6", "7", "8"][sorteio.ate(7) as usize];
+                    ("2", sorteio.nome_pessoa(), format!("***{}**", sorteio.digitos(6)), "", faixa)
+                }
+            };
+            vec![
+                basico.to_string(),
+                identificador.to_string(),
+                nome,
+                documento,
+                qualificacao.to_string(),
+                data_receita(entrada),
+                pais.to_string(),
+                "***000000**".to_string(),
+                String::new(),
+                "00".to_string(),
+                faixa.to_string(),
+            ]
+        })
+        .collect()
+}
+
+fn gerar_simples(sorteio: &mut Sorteio, basico: &str, empresa: &EmpresaGerada, hoje: NaiveDate) -> Option<Vec<String>> {
+    const SEM_DATA: &str = "00000000";
+    let pequena = matches!(empresa.porte, "01" | "03");
+    if !pequena && !sorteio.chance(10) {
+        return None;
+    }
+    let mei = empresa.individual && sorteio.chance(80);
+    // Data do início do Simples Nacional; empresas mais antigas optam a partir dela
+    let inicio_simples = NaiveDate::from_ymd_opt(2007, 7, 1).unwrap_or(empresa.inicio).max(empresa.inicio);
+    let opcao = sorteio.data(inicio_simples, hoje);
+    let excluida = sorteio.chance(25);
+    let exclusao = if excluida { data_receita(sorteio.data(opcao, hoje)) } else { SEM_DATA.to_string() };
+    let (opcao_simples, data_opcao_simples, data_exclusao_simples) = if pequena || mei {
+        (if excluida { "N" } else { "S" }, data_receita(opcao), exclusao.clone())
+    } else {
+        ("N", SEM_DATA.to_string(), SEM_DATA.to_string())
+    };
+    let (opcao_mei, data_opcao_mei, data_exclusao_mei) = if mei {
+        (if excluida { "N" } else { "S" }, data_receita(opcao), exclusao)
+    } else {
+        ("N", SEM_DATA.to_string(), SEM_DATA.to_string())
+    };
+    Some(vec![
+        basico.to_string(),
+        opcao_simples.to_string(),
+        data_opcao_simples,
+        data_exclusao_simples,
+        opcao_mei.to_string(),
+        data_opcao_mei,
+        data_exclusao_mei,
+    ])
+}
@@ -1,352 +1,486 @@
-use actix_web::{web, HttpResponse, Result as ActixResult};
-use rusqlite::{Connection, params};
+use actix_web::dev::Service;
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
-use anyhow::Context;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use utoipa::ToSchema;
+use utoipa_swagger_ui::SwaggerUi;
+use futures::future::{self, Either};
+use crate::atualizacao::BancoTemporario;
+use crate::chaves::ControleAcesso;
+use crate::cnpj;
+use crate::config::Config;
+use crate::cache::{self, RespostaCacheada};
+use crate::consultas::{self, ArquivoOrigem, CnpjResponse};
+use crate::database;
+use crate::download;
+use crate::erros::{self, ErroApi, Problema};
+use crate::jobs::{EstadoJob, GerenciadorJobs, TipoJob};
+use crate::metricas;
+use crate::openapi;
+use crate::process::{self, OpcoesProcessamento};
+use crate::recarga::{self, Recarga};
+use crate::referencia;
 use crate::ui;
 
-#[derive(Serialize, Deserialize)]
-pub struct CnpjResponse {
-    pub cnpj: String,
-    pub estabelecimento: Option<EstabelecimentoCompleto>,
-    pub empresa: Option<EmpresaCompleta>,
-    pub socios: Vec<SocioCompleto>,
-    pub simples: Option<SimplesDados>,
+/// Resposta de `/api/database/status`, montada a partir de `_referencia`,
+/// `_arquivos_origem` e `_schema_version`
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct StatusBanco {
+    pub banco: String,
+    /// Quando o servidor abriu este banco (na inicialização ou na última recarga)
+    pub carregado_em: String,
+    pub tamanho_bytes: Option<u64>,
+    pub referencia: Option<String>,
+    pub url_origem: Option<String>,
+    pub gerado_em: Option<String>,
+    pub versao_ferramenta: Option<String>,
+    pub versao_esquema: i64,
+    pub duracao_processamento_segundos: Option<f64>,
+    pub tabelas: BTreeMap<String, i64>,
+    pub arquivos_origem: Vec<ArquivoOrigem>,
+    /// Demais chaves de `_referencia` (ex: `cnpj_qtde`, `socios_orfaos`)
+    pub outros: BTreeMap<String, String>,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct EstabelecimentoCompleto {
-    // Dados do estabelecimento
-    pub cnpj: String,
-    pub cnpj_basico: String,
-    pub cnpj_ordem: String,
-    pub cnpj_dv: String,
-    pub matriz_filial: String,
-    pub nome_fantasia: String,
-    pub situacao_cadastral: String,
-    pub data_situacao_cadastral: String,
-    pub motivo_situacao_cadastral: String,
-    pub motivo_situacao_cadastral_desc: Option<String>,
-    pub nome_cidade_exterior: String,
-    pub pais: String,
-    pub pais_desc: Option<String>,
-    pub data_inicio_atividades: String,
-    pub cnae_fiscal: String,
-    pub cnae_fiscal_desc: Option<String>,
-    pub cnae_fiscal_secundaria: String,
-    pub tipo_logradouro: String,
-    pub logradouro: String,
-    pub numero: String,
-    pub complemento: String,
-    pub bairro: String,
-    pub cep: String,
-    pub uf: String,
-    pub municipio: String,
-    pub municipio_desc: Option<String>,
-    pub ddd1: String,
-    pub telefone1: String,
-    pub ddd2: String,
-    pub telefone2: String,
-    pub ddd_fax: String,
-    pub fax: String,
-    pub correio_eletronico: String,
-    pub situacao_especial: String,
-    pub data_situacao_especial: String,
+pub struct AppState {
+    /// Banco consultado, trocado por `/api/admin/reload` ou pela vigilância do arquivo
+    pub banco: Arc<Recarga>,
+    pub jobs: GerenciadorJobs,
+    /// Chaves de acesso; `None` quando o servidor roda sem autenticação
+    pub acesso: Option<ControleAcesso>,
+    /// Configuração efetiva, usada nos campos omitidos dos pedidos de download e processamento
+    pub padroes: Config,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct EmpresaCompleta {
-    pub cnpj_basico: String,
-    pub razao_social: String,
-    pub natureza_juridica: String,
-    pub natureza_juridica_desc: Option<String>,
-    pub qualificacao_responsavel: String,
-    pub qualificacao_responsavel_desc: Option<String>,
-    pub capital_social: Option<f64>,
-    pub porte_empresa: String,
-    pub ente_federativo_responsavel: String,
+/// Rotas de monitoramento e documentação, liberadas mesmo com chaves de acesso
+const ROTAS_SEM_CHAVE: &[&str] = &["/health", "/metrics", "/openapi.json", "/docs"];
+
+fn rota_sem_chave(caminho: &str) -> bool {
+    ROTAS_SEM_CHAVE.contains(&caminho) || caminho.starts_with("/docs/")
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct SocioCompleto {
-    pub cnpj: String,
-    pub cnpj_basico: String,
-    pub identificador_de_socio: String,
-    pub nome_socio: String,
-    pub cnpj_cpf_socio: String,
-    pub qualificacao_socio: String,
-    pub qualificacao_socio_desc: Option<String>,
-    pub data_entrada_sociedade: String,
-    pub pais: String,
-    pub pais_desc: Option<String>,
-    pub representante_legal: String,
-    pub nome_representante: String,
-    pub qualificacao_representante_legal: String,
-    pub qualificacao_representante_legal_desc: Option<String>,
-    pub faixa_etaria: String,
+/// Rotas que gravam em disco ou trocam o banco; exigem uma chave de administração
+const ROTAS_ADMIN: &[&str] = &[
+    "/api/download/start",
+    "/api/download/cancel",
+    "/api/process/start",
+    "/api/process/cancel",
+    "/api/admin/reload",
+];
+
+/// Chave enviada em `Authorization: Bearer <chave>` ou em `X-API-Key`
+fn chave_da_requisicao(req: &actix_web::dev::ServiceRequest) -> Option<&str> {
+    let cabecalhos = req.headers();
+    cabecalhos
+        .get("authorization")
+        .and_then(|valor| valor.to_str().ok())
+        .and_then(|valor| valor.strip_prefix("Bearer "))
+        .or_else(|| cabecalhos.get("x-api-key").and_then(|valor| valor.to_str().ok()))
+        .map(str::trim)
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct SimplesDados {
-    pub cnpj_basico: String,
-    pub opcao_simples: String,
-    pub data_opcao_simples: String,
-    pub data_exclusao_simples: String,
-    pub opcao_mei: String,
-    pub data_opcao_mei: String,
-    pub data_exclusao_mei: String,
+/// Confere a chave de acesso; `None` libera a requisição
+fn verificar_acesso(req: &actix_web::dev::ServiceRequest) -> Option<ErroApi> {
+    let state = req.app_data::<web::Data<AppState>>()?;
+    let acesso = state.acesso.as_ref()?;
+    if rota_sem_chave(req.path()) {
+        return None;
+    }
+    let exige_admin = ROTAS_ADMIN.contains(&req.path());
+    acesso.autorizar(chave_da_requisicao(req), exige_admin).err().map(ErroApi::from)
 }
 
-pub struct AppState {
-    pub db: Arc<Mutex<Connection>>,
+/// Corpo de `POST /api/process/start`, com os mesmos padrões do subcomando `process`
+/// (seção `[process]` da configuração do servidor). As pastas e o banco de destino não
+/// podem ser escolhidos pelo cliente: vêm sempre da configuração
+#[derive(Deserialize, Default, ToSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct PedidoProcessamento {
+    pub referencia: Option<String>,
+    pub cleanup: Option<bool>,
+    pub vacuum: Option<bool>,
+}
+
+/// Resposta de `/health`
+#[derive(Serialize, ToSchema)]
+pub struct Saude {
+    /// `ok` ou `erro`
+    pub status: String,
+    pub mensagem: String,
+    /// `ok` se o banco respondeu a uma consulta de teste
+    pub banco: String,
+}
+
+/// Banco substituído por `/api/admin/reload`
+#[derive(Serialize, ToSchema)]
+pub struct BancoAnterior {
+    pub banco: String,
+    pub referencia: String,
 }
 
+/// Resposta de `/api/admin/reload` quando o banco novo foi aceito
+#[derive(Serialize, ToSchema)]
+pub struct RespostaRecarga {
+    pub status: String,
+    pub banco: String,
+    pub referencia: String,
+    pub carregado_em: String,
+    pub anterior: BancoAnterior,
+}
+
+/// Resposta de `/api/progress`: o último trabalho de cada tipo (`null` se nenhum foi iniciado)
+#[derive(Serialize, ToSchema)]
+pub struct ProgressoTrabalhos {
+    pub download: Option<EstadoJob>,
+    pub process: Option<EstadoJob>,
+}
+
+/// Dados completos de um CNPJ: estabelecimento, empresa, sócios e Simples
+#[utoipa::path(
+    get,
+    path = "/cnpj/{cnpj}",
+    tag = "consulta",
+    params(("cnpj" = String, Path, description = "CNPJ com 14 dígitos, com ou sem pontuação", example = "00000000000191")),
+    responses(
+        (status = 200, description = "Dados do CNPJ; `empresa` e `simples` são `null` quando não há registro", body = CnpjResponse,
+            headers(
+                ("ETag" = String, description = "Mês de referência e hash do conteúdo"),
+                ("Last-Modified" = String, description = "Data de geração do banco"),
+            )),
+        (status = 304, description = "O cliente já tem esta versão (`If-None-Match` ou `If-Modified-Since`)"),
+        (status = 400, description = "CNPJ sem 14 dígitos", body = Problema, content_type = "application/problem+json"),
+        (status = 404, description = "CNPJ não existe no banco em uso", body = Problema, content_type = "application/problem+json"),
+        (status = 401, description = "Chave de acesso ausente ou inválida", body = Problema, content_type = "application/problem+json"),
+        (status = 429, description = "Limite ou cota da chave excedidos; veja o cabeçalho `Retry-After`", body = Problema, content_type = "application/problem+json"),
+    )
+)]
 pub async fn consultar_cnpj(
+    req: HttpRequest,
     cnpj: web::Path<String>,
     state: web::Data<AppState>,
-) -> ActixResult<HttpResponse> {
-    let cnpj_limpo = cnpj.replace(".", "").replace("/", "").replace("-", "");
-    
-    if cnpj_limpo.len() != 14 {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "erro": "CNPJ deve ter 14 dígitos",
-            "cnpj_recebido": cnpj_limpo
-        })));
-    }
+) -> Result<HttpResponse, ErroApi> {
+    let cnpj_limpo = cnpj::limpar(&cnpj)
+        .ok_or_else(|| ErroApi::CnpjInvalido { recebido: cnpj.into_inner() })?;
 
-    let db = state.db.lock().unwrap();
-    
-    // Busca dados do estabelecimento e empresa (query principal)
-    let estabelecimento = buscar_estabelecimento(&db, &cnpj_limpo)?;
-    
-    let empresa = if let Some(ref est) = estabelecimento {
-        buscar_empresa(&db, &est.cnpj_basico)?
-    } else {
-        None
+    // A consulta inteira usa o mesmo banco, mesmo que ele seja trocado no meio
+    let banco = state.banco.atual();
+    let resposta = match banco.cache.obter(&cnpj_limpo) {
+        Some(resposta) => resposta,
+        None => {
+            let dados = consultas::buscar_cnpj(&**banco.conexao(), &cnpj_limpo)
+                .map_err(ErroApi::interno("Erro ao consultar CNPJ"))?
+                .ok_or_else(|| ErroApi::CnpjNaoEncontrado { cnpj: cnpj_limpo.clone() })?;
+            let corpo = serde_json::to_vec(&dados)
+                .map_err(|e| ErroApi::interno("Erro ao serializar a resposta")(e.into()))?;
+            let resposta = Arc::new(RespostaCacheada::new(&banco.referencia, corpo.into()));
+            banco.cache.guardar(cnpj_limpo, Arc::clone(&resposta));
+            resposta
+        }
     };
-    
-    // Busca sócios
-    let socios = buscar_socios(&db, &cnpj_limpo)?;
-    
-    // Busca dados do Simples
-    let simples = if let Some(ref est) = estabelecimento {
-        buscar_simples(&db, &est.cnpj_basico)?
-    } else {
-        None
+
+    let nao_modificado = cache::nao_modificado(&req, &resposta.etag, banco.modificado_em);
+    let mut http = if nao_modificado { HttpResponse::NotModified() } else { HttpResponse::Ok() };
+    http.insert_header((header::ETAG, resposta.etag.clone()))
+        .insert_header((header::LAST_MODIFIED, cache::data_http(banco.modificado_em)))
+        // Sem isso, os navegadores estimam a validade pelo Last-Modified e podem
+        // continuar usando dados de um banco já trocado
+        .insert_header((header::CACHE_CONTROL, "no-cache"));
+    if nao_modificado {
+        return Ok(http.finish());
+    }
+    Ok(http.content_type("application/json").body(resposta.corpo.clone()))
+}
+
+/// Metadados do banco em uso: referência, versões, contagens e arquivos de origem
+#[utoipa::path(
+    get,
+    path = "/api/database/status",
+    tag = "banco",
+    responses((status = 200, description = "Metadados do banco", body = StatusBanco))
+)]
+pub async fn database_status(state: web::Data<AppState>) -> Result<HttpResponse, ErroApi> {
+    let banco = state.banco.atual();
+    let (pares, arquivos_origem, versao_esquema) = {
+        let db = banco.conexao();
+        (
+            db.ler_referencia().map_err(ErroApi::interno("Erro ao ler _referencia"))?,
+            db.ler_arquivos_origem().map_err(ErroApi::interno("Erro ao ler _arquivos_origem"))?,
+            db.versao_esquema().map_err(ErroApi::interno("Erro ao ler a versão do esquema"))?,
+        )
     };
-    
-    let response = CnpjResponse {
-        cnpj: cnpj_limpo,
-        estabelecimento,
-        empresa,
-        socios,
-        simples,
+
+    let mut status = StatusBanco {
+        banco: banco.caminho.clone(),
+        carregado_em: banco.carregado_em.clone(),
+        tamanho_bytes: std::fs::metadata(banco.arquivo()).ok().map(|m| m.len()),
+        referencia: None,
+        url_origem: None,
+        gerado_em: None,
+        versao_ferramenta: None,
+        versao_esquema,
+        duracao_processamento_segundos: None,
+        tabelas: BTreeMap::new(),
+        arquivos_origem,
+        outros: BTreeMap::new(),
     };
-    
-    Ok(HttpResponse::Ok().json(response))
+    for (chave, valor) in pares {
+        match chave.as_str() {
+            "CNPJ" => status.referencia = Some(valor),
+            referencia::CHAVE_URL_ORIGEM => status.url_origem = Some(valor),
+            referencia::CHAVE_GERADO_EM => status.gerado_em = Some(valor),
+            referencia::CHAVE_VERSAO_FERRAMENTA => status.versao_ferramenta = Some(valor),
+            referencia::CHAVE_DURACAO => status.duracao_processamento_segundos = valor.parse().ok(),
+            // A versão vem de _schema_version, que acompanha as migrações
+            referencia::CHAVE_VERSAO_ESQUEMA => {}
+            _ => match chave.strip_prefix(referencia::PREFIXO_LINHAS) {
+                Some(tabela) => {
+                    status.tabelas.insert(tabela.to_string(), valor.parse().unwrap_or(0));
+                }
+                None => {
+                    status.outros.insert(chave, valor);
+                }
+            },
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(status))
 }
 
-fn buscar_estabelecimento(db: &Connection, cnpj: &str) -> ActixResult<Option<EstabelecimentoCompleto>> {
-    let mut stmt = db.prepare(
-        r#"
-        SELECT 
-            t.cnpj, t.cnpj_basico, t.cnpj_ordem, t.cnpj_dv, t.matriz_filial,
-            t.nome_fantasia, t.situacao_cadastral, t.data_situacao_cadastral,
-            t.motivo_situacao_cadastral, t.nome_cidade_exterior, t.pais,
-            t.data_inicio_atividades, t.cnae_fiscal, t.cnae_fiscal_secundaria,
-            t.tipo_logradouro, t.logradouro, t.numero, t.complemento,
-            t.bairro, t.cep, t.uf, t.municipio,
-            t.ddd1, t.telefone1, t.ddd2, t.telefone2,
-            t.ddd_fax, t.fax, t.correio_eletronico,
-            t.situacao_especial, t.data_situacao_especial,
-            tmot.descricao as motivo_situacao_cadastral_desc,
-            tmun.descricao as municipio_desc,
-            tc.descricao as cnae_fiscal_desc,
-            tpa.descricao as pais_desc
-        FROM estabelecimento t
-        LEFT JOIN motivo tmot ON tmot.codigo = t.motivo_situacao_cadastral
-        LEFT JOIN municipio tmun ON tmun.codigo = t.municipio
-        LEFT JOIN cnae tc ON tc.codigo = t.cnae_fiscal
-        LEFT JOIN pais tpa ON tpa.codigo = t.pais
-        WHERE t.cnpj = ?1
-        "#
-    ).map_err(|e| actix_web::error::ErrorInternalServerError(format!("Erro SQL: {}", e)))?;
-    
-    let row_result = stmt.query_row(params![cnpj], |row| {
-        Ok(EstabelecimentoCompleto {
-            cnpj: row.get(0)?,
-            cnpj_basico: row.get(1)?,
-            cnpj_ordem: row.get(2)?,
-            cnpj_dv: row.get(3)?,
-            matriz_filial: row.get(4)?,
-            nome_fantasia: row.get(5)?,
-            situacao_cadastral: row.get(6)?,
-            data_situacao_cadastral: row.get(7)?,
-            motivo_situacao_cadastral: row.get(8)?,
-            motivo_situacao_cadastral_desc: row.get(28)?,
-            nome_cidade_exterior: row.get(9)?,
-            pais: row.get(10)?,
-            pais_desc: row.get(31)?,
-            data_inicio_atividades: row.get(11)?,
-            cnae_fiscal: row.get(12)?,
-            cnae_fiscal_desc: row.get(30)?,
-            cnae_fiscal_secundaria: row.get(13)?,
-            tipo_logradouro: row.get(14)?,
-            logradouro: row.get(15)?,
-            numero: row.get(16)?,
-            complemento: row.get(17)?,
-            bairro: row.get(18)?,
-            cep: row.get(19)?,
-            uf: row.get(20)?,
-            municipio: row.get(21)?,
-            municipio_desc: row.get(29)?,
-            ddd1: row.get(22)?,
-            telefone1: row.get(23)?,
-            ddd2: row.get(24)?,
-            telefone2: row.get(25)?,
-            ddd_fax: row.get(26)?,
-            fax: row.get(27)?,
-            correio_eletronico: row.get::<_, Option<String>>(28)?.unwrap_or_default(),
-            situacao_especial: row.get::<_, Option<String>>(29)?.unwrap_or_default(),
-            data_situacao_especial: row.get::<_, Option<String>>(30)?.unwrap_or_default(),
-        })
-    });
-    
-    match row_result {
-        Ok(est) => Ok(Some(est)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(actix_web::error::ErrorInternalServerError(format!("Erro ao buscar estabelecimento: {}", e))),
+/// Reabre o arquivo do banco configurado, depois que ele foi substituído. Se o banco novo
+/// não passar na validação, o atual continua ativo e a resposta é `422` com o motivo
+#[utoipa::path(
+    post,
+    path = "/api/admin/reload",
+    tag = "banco",
+    responses(
+        (status = 200, description = "Banco trocado", body = RespostaRecarga),
+        (status = 422, description = "O banco novo não passou na validação; o atual continua ativo", body = Problema, content_type = "application/problem+json"),
+        (status = 403, description = "Com `--chaves`, a rota exige uma chave de administração", body = Problema, content_type = "application/problem+json"),
+    )
+)]
+pub async fn recarregar_banco(state: web::Data<AppState>) -> Result<HttpResponse, ErroApi> {
+    let banco = Arc::clone(&state.banco);
+    // Abrir e validar o banco novo é trabalho bloqueante
+    let resultado = web::block(move || banco.recarregar())
+        .await
+        .map_err(|e| ErroApi::Interno { contexto: "Erro ao recarregar o banco", erro: anyhow::anyhow!("{}", e) })?;
+    match resultado {
+        Ok((anterior, novo)) => Ok(HttpResponse::Ok().json(RespostaRecarga {
+            status: "ok".to_string(),
+            banco: novo.caminho.clone(),
+            referencia: novo.referencia.clone(),
+            carregado_em: novo.carregado_em.clone(),
+            anterior: BancoAnterior {
+                banco: anterior.caminho.clone(),
+                referencia: anterior.referencia.clone(),
+            },
+        })),
+        Err(e) => {
+            ui::print_error(&format!("Recarga recusada, o banco atual continua ativo: {:#}", e));
+            // As mensagens do driver do banco ficam só no log
+            Err(ErroApi::RecargaRecusada { motivo: erros::mensagem_sem_sql(&e) })
+        }
     }
 }
 
-fn buscar_empresa(db: &Connection, cnpj_basico: &str) -> ActixResult<Option<EmpresaCompleta>> {
-    let mut stmt = db.prepare(
-        r#"
-        SELECT 
-            te.cnpj_basico, te.razao_social, te.natureza_juridica,
-            te.qualificacao_responsavel, te.capital_social, te.porte_empresa,
-            te.ente_federativo_responsavel,
-            tnat.descricao as natureza_juridica_desc,
-            tq.descricao as qualificacao_responsavel_desc
-        FROM empresas te
-        LEFT JOIN natureza_juridica tnat ON tnat.codigo = te.natureza_juridica
-        LEFT JOIN qualificacao_socio tq ON tq.codigo = te.qualificacao_responsavel
-        WHERE te.cnpj_basico = ?1
-        "#
-    ).map_err(|e| actix_web::error::ErrorInternalServerError(format!("Erro SQL: {}", e)))?;
-    
-    let row_result = stmt.query_row(params![cnpj_basico], |row| {
-        Ok(EmpresaCompleta {
-            cnpj_basico: row.get(0)?,
-            razao_social: row.get(1)?,
-            natureza_juridica: row.get(2)?,
-            natureza_juridica_desc: row.get(7)?,
-            qualificacao_responsavel: row.get(3)?,
-            qualificacao_responsavel_desc: row.get(8)?,
-            capital_social: row.get(4)?,
-            porte_empresa: row.get(5)?,
-            ente_federativo_responsavel: row.get(6)?,
-        })
-    });
-    
-    match row_result {
-        Ok(emp) => Ok(Some(emp)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(actix_web::error::ErrorInternalServerError(format!("Erro ao buscar empresa: {}", e))),
-    }
+/// Métricas no formato texto do Prometheus
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "monitoramento",
+    security(()),
+    responses((status = 200, description = "Métricas com o prefixo `cnpj_`", body = String, content_type = "text/plain"))
+)]
+pub async fn metrics() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(metricas::CONTENT_TYPE)
+        .body(metricas::exportar())
 }
 
-fn buscar_socios(db: &Connection, cnpj: &str) -> ActixResult<Vec<SocioCompleto>> {
-    let mut stmt = db.prepare(
-        r#"
-        SELECT 
-            ts.cnpj, ts.cnpj_basico, ts.identificador_de_socio, ts.nome_socio,
-            ts.cnpj_cpf_socio, ts.qualificacao_socio, ts.data_entrada_sociedade,
-            ts.pais, ts.representante_legal, ts.nome_representante,
-            ts.qualificacao_representante_legal, ts.faixa_etaria,
-            tq.descricao as qualificacao_socio_desc,
-            tq2.descricao as qualificacao_representante_legal_desc,
-            tpa.descricao as pais_desc
-        FROM socios ts
-        LEFT JOIN qualificacao_socio tq ON tq.codigo = ts.qualificacao_socio
-        LEFT JOIN qualificacao_socio tq2 ON tq2.codigo = ts.qualificacao_representante_legal
-        LEFT JOIN pais tpa ON tpa.codigo = ts.pais
-        WHERE ts.cnpj = ?1
-        "#
-    ).map_err(|e| actix_web::error::ErrorInternalServerError(format!("Erro SQL: {}", e)))?;
-    
-    let rows = stmt.query_map(params![cnpj], |row| {
-        Ok(SocioCompleto {
-            cnpj: row.get(0)?,
-            cnpj_basico: row.get(1)?,
-            identificador_de_socio: row.get(2)?,
-            nome_socio: row.get(3)?,
-            cnpj_cpf_socio: row.get(4)?,
-            qualificacao_socio: row.get(5)?,
-            qualificacao_socio_desc: row.get(12)?,
-            data_entrada_sociedade: row.get(6)?,
-            pais: row.get(7)?,
-            pais_desc: row.get(14)?,
-            representante_legal: row.get(8)?,
-            nome_representante: row.get(9)?,
-            qualificacao_representante_legal: row.get(10)?,
-            qualificacao_representante_legal_desc: row.get(13)?,
-            faixa_etaria: row.get(11)?,
-        })
-    }).map_err(|e| actix_web::error::ErrorInternalServerError(format!("Erro ao buscar sócios: {}", e)))?;
-    
-    let mut socios = Vec::new();
-    for row in rows {
-        socios.push(row.map_err(|e| actix_web::error::ErrorInternalServerError(format!("Erro ao processar sócio: {}", e)))?);
-    }
-    
-    Ok(socios)
+async fn rota_nao_encontrada() -> Result<HttpResponse, ErroApi> {
+    Err(ErroApi::RotaNaoEncontrada)
 }
 
-fn buscar_simples(db: &Connection, cnpj_basico: &str) -> ActixResult<Option<SimplesDados>> {
-    let mut stmt = db.prepare(
-        r#"
-        SELECT 
-            cnpj_basico, opcao_simples, data_opcao_simples,
-            data_exclusao_simples, opcao_mei, data_opcao_mei,
-            data_exclusao_mei
-        FROM simples
-        WHERE cnpj_basico = ?1
-        "#
-    ).map_err(|e| actix_web::error::ErrorInternalServerError(format!("Erro SQL: {}", e)))?;
-    
-    let row_result = stmt.query_row(params![cnpj_basico], |row| {
-        Ok(SimplesDados {
-            cnpj_basico: row.get(0)?,
-            opcao_simples: row.get(1)?,
-            data_opcao_simples: row.get(2)?,
-            data_exclusao_simples: row.get(3)?,
-            opcao_mei: row.get(4)?,
-            data_opcao_mei: row.get(5)?,
-            data_exclusao_mei: row.get(6)?,
-        })
-    });
-    
-    match row_result {
-        Ok(simples) => Ok(Some(simples)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(actix_web::error::ErrorInternalServerError(format!("Erro ao buscar Simples: {}", e))),
+/// A interface do Swagger usa caminhos relativos, então precisa da barra final
+async fn redirecionar_docs() -> HttpResponse {
+    HttpResponse::PermanentRedirect().insert_header(("Location", "/docs/")).finish()
+}
+
+/// Lê o corpo JSON de um pedido; corpo vazio usa os valores padrão
+fn ler_pedido<T: DeserializeOwned + Default>(corpo: &[u8]) -> Result<T, ErroApi> {
+    if corpo.iter().all(u8::is_ascii_whitespace) {
+        return Ok(T::default());
     }
+    serde_json::from_slice(corpo).map_err(|e| ErroApi::JsonInvalido(e.to_string()))
 }
 
-pub async fn start_server(db_path: &str, host: &str, port: u16) -> anyhow::Result<()> {
-    let conn = Connection::open(db_path)
-        .with_context(|| format!("Falha ao abrir banco de dados: {}", db_path))?;
-    
-    // Configura SQLite para melhor performance em multi-thread
-    // PRAGMA journal_mode retorna um valor, então usamos query_row
-    let _: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
-    conn.execute("PRAGMA synchronous = NORMAL", [])?;
-    conn.execute("PRAGMA cache_size = -64000", [])?;
-    conn.execute("PRAGMA foreign_keys = ON", [])?;
+/// Resposta comum aos dois endpoints de início de trabalho
+fn resposta_inicio<F>(state: &AppState, tipo: TipoJob, trabalho: F) -> Result<HttpResponse, ErroApi>
+where
+    F: FnOnce(crate::progresso::Progresso) -> anyhow::Result<()> + Send + 'static,
+{
+    let estado = state
+        .jobs
+        .iniciar(tipo, trabalho)
+        .map_err(|job| ErroApi::TrabalhoEmAndamento { tipo, job })?;
+    ui::print_info(&format!("Trabalho de {} #{} iniciado pela API", tipo.descricao(), estado.id));
+    Ok(HttpResponse::Accepted().json(estado))
+}
+
+/// Inicia o download dos arquivos da Receita em segundo plano, na pasta `download.output`
+/// da configuração do servidor
+#[utoipa::path(
+    post,
+    path = "/api/download/start",
+    tag = "trabalhos",
+    responses(
+        (status = 202, description = "Trabalho iniciado", body = EstadoJob),
+        (status = 409, description = "Já existe um download em execução (o campo `job` traz o estado dele)", body = Problema, content_type = "application/problem+json"),
+        (status = 403, description = "Com `--chaves`, a rota exige uma chave de administração", body = Problema, content_type = "application/problem+json"),
+    )
+)]
+pub async fn iniciar_download(state: web::Data<AppState>) -> Result<HttpResponse, ErroApi> {
+    let output = state.padroes.download.output.clone();
+    resposta_inicio(&state, TipoJob::Download, move |progresso| {
+        // O download é assíncrono; cada trabalho tem seu próprio runtime, fora das threads da API
+        let runtime = tokio::runtime::Runtime::new()?;
+        runtime.block_on(download::download_files(&output, true, &progresso))
+    })
+}
+
+/// Inicia o processamento dos ZIPs em segundo plano. O banco novo é montado ao lado do
+/// banco em uso e publicado pela recarga quando o processamento termina
+#[utoipa::path(
+    post,
+    path = "/api/process/start",
+    tag = "trabalhos",
+    request_body(content = Option<PedidoProcessamento>, description = "Opcional; as pastas vêm da seção `[process]` da configuração e o destino é o banco servido"),
+    responses(
+        (status = 202, description = "Trabalho iniciado", body = EstadoJob),
+        (status = 400, description = "JSON inválido ou com campos desconhecidos", body = Problema, content_type = "application/problem+json"),
+        (status = 409, description = "Já existe um processamento em execução (o campo `job` traz o estado dele)", body = Problema, content_type = "application/problem+json"),
+        (status = 403, description = "Com `--chaves`, a rota exige uma chave de administração", body = Problema, content_type = "application/problem+json"),
+    )
+)]
+pub async fn iniciar_processamento(corpo: web::Bytes, state: web::Data<AppState>) -> Result<HttpResponse, ErroApi> {
+    let pedido: PedidoProcessamento = ler_pedido(&corpo)?;
+    let padroes = &state.padroes.process;
+    let opcoes = OpcoesProcessamento {
+        input_dir: padroes.input.clone(),
+        output_dir: padroes.output.clone(),
+        // Definido no trabalho: um arquivo temporário ao lado do banco servido
+        target: None,
+        referencia: pedido.referencia,
+        cleanup: pedido.cleanup.unwrap_or(padroes.cleanup),
+        vacuum: pedido.vacuum.unwrap_or(padroes.vacuum),
+        // Não há terminal para as confirmações
+        auto_yes: true,
+    };
+    let servido = state.banco.atual().caminho.clone();
+    let recarga = Arc::clone(&state.banco);
+    resposta_inicio(&state, TipoJob::Process, move |progresso| {
+        if database::is_duckdb_path(&servido) {
+            anyhow::bail!("O processamento pela API gera um banco SQLite, mas o servidor usa o banco DuckDB {}", servido);
+        }
+        let destino = Path::new(&servido);
+        let temporario = BancoTemporario::new(destino);
+        let caminho = temporario.caminho()?.to_string();
+        process::process_files(&OpcoesProcessamento { target: Some(caminho.clone()), ..opcoes }, &progresso)?;
+        // O processamento também termina sem erro quando não encontra o que fazer
+        if !Path::new(&caminho).exists() {
+            anyhow::bail!("O processamento terminou sem gerar o banco {}", caminho);
+        }
+        // Com o banco aberto pelo próprio servidor, o novo fica como `.pronto` e é a
+        // recarga que faz a troca
+        temporario.publicar(destino)?;
+        recarga.recarregar()?;
+        Ok(())
+    })
+}
+
+fn resposta_cancelamento(state: &AppState, tipo: TipoJob) -> Result<HttpResponse, ErroApi> {
+    let estado = state.jobs.cancelar(tipo).ok_or(ErroApi::NenhumTrabalho { tipo })?;
+    Ok(HttpResponse::Accepted().json(estado))
+}
+
+/// Pede o cancelamento do download em execução
+#[utoipa::path(
+    post,
+    path = "/api/download/cancel",
+    tag = "trabalhos",
+    responses(
+        (status = 202, description = "Cancelamento pedido; atendido no próximo arquivo ou lote", body = EstadoJob),
+        (status = 404, description = "Nenhum download em execução", body = Problema, content_type = "application/problem+json"),
+        (status = 403, description = "Com `--chaves`, a rota exige uma chave de administração", body = Problema, content_type = "application/problem+json"),
+    )
+)]
+pub async fn cancelar_download(state: web::Data<AppState>) -> Result<HttpResponse, ErroApi> {
+    resposta_cancelamento(&state, TipoJob::Download)
+}
+
+/// Pede o cancelamento do processamento em execução
+#[utoipa::path(
+    post,
+    path = "/api/process/cancel",
+    tag = "trabalhos",
+    responses(
+        (status = 202, description = "Cancelamento pedido; atendido no próximo arquivo ou lote", body = EstadoJob),
+        (status = 404, description = "Nenhum processamento em execução", body = Problema, content_type = "application/problem+json"),
+        (status = 403, description = "Com `--chaves`, a rota exige uma chave de administração", body = Problema, content_type = "application/problem+json"),
+    )
+)]
+pub async fn cancelar_processamento(state: web::Data<AppState>) -> Result<HttpResponse, ErroApi> {
+    resposta_cancelamento(&state, TipoJob::Process)
+}
+
+/// Estado do último trabalho de cada tipo (`null` se nenhum foi iniciado)
+#[utoipa::path(
+    get,
+    path = "/api/progress",
+    tag = "trabalhos",
+    responses((status = 200, description = "Situação e últimos eventos de progresso", body = ProgressoTrabalhos))
+)]
+pub async fn progresso(state: web::Data<AppState>) -> Result<HttpResponse, ErroApi> {
+    Ok(HttpResponse::Ok().json(ProgressoTrabalhos {
+        download: state.jobs.estado(TipoJob::Download),
+        process: state.jobs.estado(TipoJob::Process),
+    }))
+}
+
+/// Opções do subcomando `server`
+pub struct OpcoesServidor {
+    pub host: String,
+    pub port: u16,
+    /// Com valor, o arquivo do banco é verificado nesse intervalo e recarregado quando
+    /// for substituído
+    pub vigiar: Option<Duration>,
+    /// Arquivo de chaves de acesso; sem ele, a API não exige autenticação
+    pub arquivo_chaves: Option<String>,
+    /// Respostas de CNPJ guardadas em memória (0 desativa o cache)
+    pub tamanho_cache: usize,
+    /// Padrões dos trabalhos de download e processamento iniciados pela API
+    pub padroes: Config,
+}
+
+/// Inicia o servidor
+pub async fn start_server(db_path: &str, opcoes: &OpcoesServidor) -> anyhow::Result<()> {
+    let OpcoesServidor { host, port, vigiar, arquivo_chaves, tamanho_cache, padroes } = opcoes;
+    let banco = Arc::new(Recarga::new(recarga::abrir_banco(db_path, *tamanho_cache)?, *tamanho_cache));
+    let acesso = arquivo_chaves.as_deref().map(ControleAcesso::abrir).transpose()?;
+    if let Some(intervalo) = *vigiar {
+        recarga::vigiar(Arc::clone(&banco), intervalo);
+    }
     
     let app_state = web::Data::new(AppState {
-        db: Arc::new(Mutex::new(conn)),
+        banco,
+        jobs: GerenciadorJobs::default(),
+        acesso,
+        padroes: padroes.clone(),
     });
     
     let address = format!("{}:{}", host, port);
@@ -354,17 +488,89 @@ pub async fn start_server(db_path: &str, host: &str, port: u16) -> anyhow::Resul
     ui::print_header("🌐 Servidor API REST");
     ui::print_success(&format!("Servidor iniciando em http://{}", address));
     ui::print_info("Endpoints disponíveis:");
-    use colored::Colorize;
-    println!("  {} GET /cnpj/{{cnpj}}  - Consulta dados completos de um CNPJ", "•".cyan());
-    println!("  {} GET /health         - Verifica status do servidor", "•".cyan());
+    ui::print_item("GET /cnpj/{cnpj}  - Consulta dados completos de um CNPJ");
+    ui::print_item("GET /health         - Verifica o servidor e a conexão com o banco");
+    ui::print_item("GET /api/database/status - Metadados do banco (referência, versões, contagens)");
+    ui::print_item("POST /api/download/start, /api/process/start - Inicia download ou processamento");
+    ui::print_item("POST /api/download/cancel, /api/process/cancel - Cancela o trabalho em execução");
+    ui::print_item("GET /api/progress   - Progresso dos trabalhos");
+    ui::print_item("POST /api/admin/reload - Troca o banco consultado sem reiniciar");
+    ui::print_item("GET /metrics        - Métricas no formato do Prometheus");
+    ui::print_item("GET /openapi.json   - Especificação OpenAPI 3 da API");
+    ui::print_item("GET /docs/          - Documentação interativa (Swagger UI)");
+    match arquivo_chaves.as_deref() {
+        Some(arquivo) => ui::print_info(&format!(
+            "Chaves de acesso exigidas (arquivo {}); /health, /metrics e /docs continuam livres",
+            arquivo
+        )),
+        None if host != "127.0.0.1" && host != "localhost" => ui::print_warning(
+            "Servidor acessível pela rede sem chaves de acesso: use --chaves para exigir autenticação",
+        ),
+        None => {}
+    }
+    if *tamanho_cache > 0 {
+        ui::print_verbose(&format!("Cache de até {} respostas de CNPJ em memória", tamanho_cache));
+    }
+    if let Some(intervalo) = *vigiar {
+        ui::print_info(&format!(
+            "Vigiando {} a cada {}s para recarregar quando o arquivo for substituído",
+            db_path,
+            intervalo.as_secs()
+        ));
+    }
     ui::print_verbose(&format!("Exemplo: curl http://{}/cnpj/00000000000191", address));
     ui::print_separator();
     
     actix_web::HttpServer::new(move || {
         actix_web::App::new()
             .app_data(app_state.clone())
+            // Registrado antes das métricas, então as recusas (401, 403 e 429) também são medidas
+            .wrap_fn(|req, srv| match verificar_acesso(&req) {
+                None => Either::Left(srv.call(req)),
+                Some(recusa) => Either::Right(future::ready(Ok(req.into_response(recusa.error_response())))),
+            })
+            .wrap_fn(|req, srv| {
+                let inicio = std::time::Instant::now();
+                let resposta = srv.call(req);
+                async move {
+                    let resposta = resposta.await?;
+                    // O padrão da rota (ex: /cnpj/{cnpj}) evita uma série por CNPJ consultado
+                    let rota = resposta.request().match_pattern().unwrap_or_else(|| "desconhecida".to_string());
+                    metricas::registrar_requisicao(
+                        &rota,
+                        resposta.request().method().as_str(),
+                        resposta.status().as_u16(),
+                        inicio.elapsed(),
+                    );
+                    Ok(resposta)
+                }
+            })
+            // O mais externo: o ID vale para as recusas de acesso e volta em toda resposta
+            .wrap_fn(|req, srv| {
+                let recebido = req.headers().get(erros::CABECALHO_ID).and_then(|valor| valor.to_str().ok());
+                let id = erros::id_requisicao(recebido);
+                let resposta = erros::com_id(id.clone(), || srv.call(req));
+                async move {
+                    let mut resposta = erros::com_id_async(id.clone(), resposta).await?;
+                    if let Ok(valor) = HeaderValue::from_str(&id) {
+                        resposta.headers_mut().insert(HeaderName::from_static(erros::CABECALHO_ID), valor);
+                    }
+                    Ok(resposta)
+                }
+            })
+            .default_service(web::to(rota_nao_encontrada))
             .route("/cnpj/{cnpj}", web::get().to(consultar_cnpj))
             .route("/health", web::get().to(health_check))
+            .route("/api/database/status", web::get().to(database_status))
+            .route("/api/download/start", web::post().to(iniciar_download))
+            .route("/api/download/cancel", web::post().to(cancelar_download))
+            .route("/api/process/start", web::post().to(iniciar_processamento))
+            .route("/api/process/cancel", web::post().to(cancelar_processamento))
+            .route("/api/progress", web::get().to(progresso))
+            .route("/api/admin/reload", web::post().to(recarregar_banco))
+            .route("/metrics", web::get().to(metrics))
+            .route("/docs", web::get().to(redirecionar_docs))
+            .service(SwaggerUi::new("/docs/{_:.*}").url("/openapi.json", openapi::documento()))
     })
     .bind(&address)?
     .workers(num_cpus::get()) // Usa todos os cores disponíveis para multi-threading
@@ -374,10 +580,33 @@ pub async fn start_server(db_path: &str, host: &str, port: u16) -> anyhow::Resul
     Ok(())
 }
 
-async fn health_check() -> ActixResult<HttpResponse> {
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "status": "ok",
-        "mensagem": "API CNPJ está funcionando"
-    })))
+/// Verifica o servidor e a conexão com o banco
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "monitoramento",
+    security(()),
+    responses(
+        (status = 200, description = "Servidor e banco respondendo", body = Saude),
+        (status = 503, description = "O banco não respondeu", body = Saude),
+    )
+)]
+pub async fn health_check(state: web::Data<AppState>) -> Result<HttpResponse, ErroApi> {
+    let ping = state.banco.atual().conexao().ping();
+    match ping {
+        Ok(()) => Ok(HttpResponse::Ok().json(Saude {
+            status: "ok".to_string(),
+            mensagem: "API CNPJ está funcionando".to_string(),
+            banco: "ok".to_string(),
+        })),
+        Err(e) => {
+            ui::print_error(&format!("Health check: banco não respondeu: {}", e));
+            Ok(HttpResponse::ServiceUnavailable().json(Saude {
+                status: "erro".to_string(),
+                mensagem: "Banco de dados indisponível".to_string(),
+                banco: "erro".to_string(),
+            }))
+        }
+    }
 }
 
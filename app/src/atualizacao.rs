@@ -0,0 +1,324 @@
+//! Subcomando `atualizar` (ou `pipeline`): download → verificação → processamento →
+//! CNAE secundário → otimização, sem perguntas, para rodar pelo cron ou por um timer
+//! do systemd.
+//!
+//! O banco novo é montado em um arquivo temporário ao lado do definitivo e só substitui
+//! `cnpj.db` (por um rename atômico) depois que todas as etapas terminam; se algo falhar,
+//! o banco em uso fica intacto e o comando sai com código diferente de zero. Se `cnpj.db`
+//! estiver aberto por outro processo (o servidor da API), o banco novo fica como
+//! `cnpj.db.pronto` e é o servidor que faz a troca, na próxima recarga.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use rusqlite::{Connection, OpenFlags};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use zip::ZipArchive;
+use crate::cnae_secundaria;
+use crate::download;
+use crate::process;
+use crate::progresso::Progresso;
+use crate::recarga;
+use crate::ui;
+use crate::utils;
+
+const TOTAL_ETAPAS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Etapa {
+    Download,
+    Verificacao,
+    Processamento,
+    Cnae,
+    Otimizacao,
+}
+
+/// Etapas que são puladas sozinhas quando já estão em dia e podem ser forçadas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EtapaForcavel {
+    Download,
+    Processamento,
+}
+
+#[derive(Debug, Clone)]
+pub struct OpcoesAtualizacao {
+    /// Pasta dos arquivos ZIP
+    pub input_dir: String,
+    /// Pasta do banco `cnpj.db` (e dos CSVs descompactados durante o processamento)
+    pub output_dir: String,
+    pub pular: Vec<Etapa>,
+    pub forcar: Vec<EtapaForcavel>,
+    /// Método com menos memória na criação da tabela de CNAE secundário
+    pub low_memory: bool,
+}
+
+impl OpcoesAtualizacao {
+    fn pula(&self, etapa: Etapa) -> bool {
+        self.pular.contains(&etapa)
+    }
+
+    fn forca(&self, etapa: EtapaForcavel) -> bool {
+        self.forcar.contains(&etapa)
+    }
+}
+
+/// Banco em construção; apagado ao sair de escopo, a menos que tenha sido publicado.
+/// Também usado pelo processamento iniciado pela API
+pub(crate) struct BancoTemporario {
+    caminho: PathBuf,
+    publicado: bool,
+}
+
+impl BancoTemporario {
+    pub(crate) fn new(destino: &Path) -> Self {
+        let mut nome = destino.as_os_str().to_owned();
+        nome.push(".novo");
+        let temporario = Self { caminho: PathBuf::from(nome), publicado: false };
+        // Sobra de uma execução interrompida
+        temporario.apagar();
+        temporario
+    }
+
+    pub(crate) fn caminho(&self) -> Result<&str> {
+        self.caminho.to_str().with_context(|| format!("Caminho inválido: {:?}", self.caminho))
+    }
+
+    /// Troca o banco em uso pelo novo. O rename é atômico no mesmo sistema de arquivos,
+    /// então quem abrir `destino` vê o banco antigo ou o novo, nunca um pela metade.
+    /// Antes, o WAL de `destino` é consolidado; se os `-wal`/`-shm` continuarem lá, algum
+    /// processo está com o banco aberto e o novo fica como `.pronto` para o servidor
+    /// publicar. Retorna `false` nesse caso
+    pub(crate) fn publicar(mut self, destino: &Path) -> Result<bool> {
+        let destino_str = destino.to_str().with_context(|| format!("Caminho inválido: {:?}", destino))?;
+        if recarga::aberto_por_outro_processo(destino_str) {
+            // Ao fechar, a última conexão apaga o -wal e o -shm
+            let conn = Connection::open(destino).with_context(|| format!("Falha ao abrir {:?}", destino))?;
+            conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+                .with_context(|| format!("Falha ao consolidar o WAL de {:?}", destino))?;
+            drop(conn);
+        }
+
+        let caminho_final = if recarga::aberto_por_outro_processo(destino_str) {
+            recarga::caminho_pronto(destino_str)
+        } else {
+            destino.to_path_buf()
+        };
+        fs::rename(&self.caminho, &caminho_final)
+            .with_context(|| format!("Falha ao mover {:?} para {:?}", self.caminho, caminho_final))?;
+        self.publicado = true;
+        Ok(caminho_final == destino)
+    }
+
+    fn apagar(&self) {
+        for sufixo in ["", "-wal", "-shm", "-journal"] {
+            let mut nome = self.caminho.as_os_str().to_owned();
+            nome.push(sufixo);
+            let _ = fs::remove_file(PathBuf::from(nome));
+        }
+    }
+}
+
+impl Drop for BancoTemporario {
+    fn drop(&mut self) {
+        if !self.publicado {
+            self.apagar();
+        }
+    }
+}
+
+pub async fn atualizar(opcoes: &OpcoesAtualizacao) -> Result<()> {
+    for etapa in &opcoes.forcar {
+        let (pulada, nome) = match etapa {
+            EtapaForcavel::Download => (Etapa::Download, "download"),
+            EtapaForcavel::Processamento => (Etapa::Processamento, "processamento"),
+        };
+        if opcoes.pula(pulada) {
+            anyhow::bail!("A etapa {} foi pedida em --pular e em --forcar ao mesmo tempo", nome);
+        }
+    }
+
+    ui::print_header("🔄 Atualização dos Dados Públicos de CNPJ");
+    let inicio = Instant::now();
+    let destino = Path::new(&opcoes.output_dir).join("cnpj.db");
+    let progresso = Progresso::default();
+    let mut etapas = ui::Etapas::new(TOTAL_ETAPAS);
+
+    // 1. Download
+    etapas.iniciar(1, "Download");
+    if opcoes.pula(Etapa::Download) {
+        ui::print_info("Etapa pulada (--pular download)");
+    } else {
+        baixar(opcoes, &progresso).await?;
+    }
+
+    let opcoes = opcoes.clone();
+    // O processamento é síncrono (e o cliente PostgreSQL cria seu próprio runtime)
+    tokio::task::spawn_blocking(move || {
+        // 2. Verificação
+        etapas.iniciar(2, "Verificação dos arquivos ZIP");
+        if opcoes.pula(Etapa::Verificacao) {
+            ui::print_info("Etapa pulada (--pular verificacao)");
+        } else {
+            verificar_zips(&opcoes.input_dir)?;
+        }
+
+        // 3. Processamento
+        etapas.iniciar(3, "Processamento");
+        if opcoes.pula(Etapa::Processamento) {
+            ui::print_info("Etapa pulada (--pular processamento); CNAE secundário e otimização dependem dela");
+            return Ok(());
+        }
+        if !opcoes.forca(EtapaForcavel::Processamento) {
+            if let Some(referencia) = banco_em_dia(&opcoes.input_dir, &destino)? {
+                ui::print_success(&format!(
+                    "O banco {:?} já está na referência {}; nada a fazer (use --forcar processamento para refazê-lo)",
+                    destino, referencia
+                ));
+                return Ok(());
+            }
+        }
+
+        let temporario = BancoTemporario::new(&destino);
+        let caminho = temporario.caminho()?.to_string();
+        process::process_files(
+            &process::OpcoesProcessamento {
+                input_dir: opcoes.input_dir.clone(),
+                output_dir: opcoes.output_dir.clone(),
+                target: Some(caminho.clone()),
+                referencia: None,
+                cleanup: true,
+                vacuum: false,
+                auto_yes: true,
+            },
+            &progresso,
+        )?;
+        // O processamento também termina sem erro quando não encontra o que fazer
+        if !Path::new(&caminho).exists() {
+            anyhow::bail!("O processamento terminou sem gerar o banco {}", caminho);
+        }
+
+        // 4. CNAE secundário
+        etapas.iniciar(4, "Tabela de CNAE secundário");
+        if opcoes.pula(Etapa::Cnae) {
+            ui::print_info("Etapa pulada (--pular cnae)");
+        } else {
+            cnae_secundaria::create_cnae_secundaria_table(&caminho, opcoes.low_memory)?;
+        }
+
+        // 5. Otimização
+        etapas.iniciar(5, "Otimização (VACUUM)");
+        if opcoes.pula(Etapa::Otimizacao) {
+            ui::print_info("Etapa pulada (--pular otimizacao)");
+        } else {
+            otimizar(&caminho)?;
+        }
+
+        let publicado = temporario.publicar(&destino)?;
+        etapas.encerrar();
+        if publicado {
+            ui::print_success(&format!("Banco atualizado em {:?}", destino));
+        } else {
+            ui::print_warning(&format!(
+                "{:?} está aberto por outro processo; o banco novo ficou em {:?} e é publicado pelo \
+                 servidor em POST /api/admin/reload (ou sozinho, com server --watch)",
+                destino,
+                recarga::caminho_pronto(&destino.to_string_lossy())
+            ));
+        }
+        Ok::<_, anyhow::Error>(())
+    })
+    .await??;
+
+    ui::print_success(&format!("Atualização concluída em {:.0}s", inicio.elapsed().as_secs_f64()));
+    Ok(())
+}
+
+/// Baixa os ZIPs, a menos que os da referência mais recente já estejam na pasta
+async fn baixar(opcoes: &OpcoesAtualizacao, progresso: &Progresso) -> Result<()> {
+    if !opcoes.forca(EtapaForcavel::Download) {
+        let disponivel = download::referencia_disponivel(progresso).await?;
+        if let Some(manifesto) = download::ler_manifesto(&opcoes.input_dir)? {
+            let completo = manifesto
+                .arquivos
+                .iter()
+                .all(|arquivo| Path::new(&opcoes.input_dir).join(&arquivo.nome).exists());
+            if manifesto.referencia == disponivel && completo {
+                ui::print_success(&format!(
+                    "Os arquivos de {} já estão em {} (use --forcar download para baixá-los de novo)",
+                    disponivel, opcoes.input_dir
+                ));
+                return Ok(());
+            }
+        }
+        ui::print_info(&format!("Nova referência disponível: {}", disponivel));
+    }
+    download::download_files(&opcoes.input_dir, true, progresso).await
+}
+
+/// Confere se os ZIPs do manifesto estão todos na pasta e se cada um abre como ZIP
+fn verificar_zips(input_dir: &str) -> Result<()> {
+    let zips = utils::get_files_by_extension(input_dir, ".zip")?;
+    if zips.is_empty() {
+        anyhow::bail!("Nenhum arquivo ZIP encontrado em {}", input_dir);
+    }
+
+    match download::ler_manifesto(input_dir)? {
+        Some(manifesto) => {
+            let ausentes: Vec<&str> = manifesto
+                .arquivos
+                .iter()
+                .filter(|arquivo| !Path::new(input_dir).join(&arquivo.nome).exists())
+                .map(|arquivo| arquivo.nome.as_str())
+                .collect();
+            if !ausentes.is_empty() {
+                anyhow::bail!(
+                    "{} arquivo(s) do manifesto ausente(s) em {}: {}",
+                    ausentes.len(),
+                    input_dir,
+                    ausentes.join(", ")
+                );
+            }
+        }
+        None => ui::print_warning(&format!(
+            "Sem {} em {}: não é possível conferir se todos os arquivos foram baixados",
+            download::ARQUIVO_MANIFESTO,
+            input_dir
+        )),
+    }
+
+    for zip in &zips {
+        let arquivo = File::open(zip).with_context(|| format!("Falha ao abrir {:?}", zip))?;
+        let conteudo = ZipArchive::new(arquivo).with_context(|| format!("Arquivo ZIP corrompido: {:?}", zip))?;
+        if conteudo.is_empty() {
+            anyhow::bail!("Arquivo ZIP vazio: {:?}", zip);
+        }
+    }
+    ui::print_success(&format!("{} arquivo(s) ZIP verificado(s)", zips.len()));
+    Ok(())
+}
+
+/// Referência do banco em uso, se for a mesma dos ZIPs baixados
+fn banco_em_dia(input_dir: &str, destino: &Path) -> Result<Option<String>> {
+    let Some(manifesto) = download::ler_manifesto(input_dir)? else {
+        return Ok(None);
+    };
+    // Banco ausente, antigo ou ilegível: é reprocessado
+    let atual: Option<String> = Connection::open_with_flags(destino, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .and_then(|conn| {
+            conn.query_row("SELECT valor FROM _referencia WHERE referencia = 'CNPJ'", [], |row| row.get(0))
+        })
+        .ok();
+    Ok(atual.filter(|atual| *atual == manifesto.referencia))
+}
+
+/// Compacta o banco novo depois do CNAE secundário; as estatísticas do planejador já
+/// foram atualizadas pelo processamento. O arquivo ainda não está em uso, então o
+/// VACUUM não atrapalha consultas
+fn otimizar(caminho: &str) -> Result<()> {
+    let conn = Connection::open(caminho).with_context(|| format!("Falha ao abrir banco: {}", caminho))?;
+    conn.execute_batch("VACUUM;")
+        .with_context(|| format!("Falha ao otimizar {}", caminho))?;
+    let _: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+    Ok(())
+}
@@ -0,0 +1,96 @@
+//! Cache HTTP das consultas de CNPJ.
+//!
+//! Os dados só mudam quando o banco é trocado, então cada resposta leva um ETag (mês de
+//! referência + hash do corpo) e um Last-Modified (`gerado_em` de `_referencia`), e os
+//! clientes revalidam com `If-None-Match`/`If-Modified-Since` recebendo `304`. As
+//! respostas prontas também ficam em um LRU em memória, que pertence ao banco ativo e é
+//! descartado junto com ele em uma recarga.
+
+use actix_web::http::header::{self, HttpDate};
+use actix_web::web::Bytes;
+use actix_web::HttpRequest;
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::metricas;
+
+/// Quantidade padrão de respostas guardadas no LRU
+pub const TAMANHO_PADRAO: usize = 10_000;
+
+/// Corpo JSON pronto de uma consulta e o ETag correspondente
+pub struct RespostaCacheada {
+    pub corpo: Bytes,
+    pub etag: String,
+}
+
+impl RespostaCacheada {
+    pub fn new(referencia: &str, corpo: Bytes) -> Self {
+        let hash = Sha256::digest(&corpo);
+        let resumo: String = hash[..8].iter().map(|b| format!("{:02x}", b)).collect();
+        Self { etag: format!("\"{}-{}\"", referencia, resumo), corpo }
+    }
+}
+
+/// LRU de respostas por CNPJ; com tamanho zero não guarda nada
+pub struct CacheRespostas {
+    lru: Option<Mutex<LruCache<String, Arc<RespostaCacheada>>>>,
+}
+
+impl CacheRespostas {
+    pub fn new(tamanho: usize) -> Self {
+        Self { lru: NonZeroUsize::new(tamanho).map(|tamanho| Mutex::new(LruCache::new(tamanho))) }
+    }
+
+    pub fn obter(&self, cnpj: &str) -> Option<Arc<RespostaCacheada>> {
+        let lru = self.lru.as_ref()?;
+        let resposta = lru.lock().unwrap().get(cnpj).cloned();
+        metricas::registrar_cache(resposta.is_some());
+        resposta
+    }
+
+    pub fn guardar(&self, cnpj: String, resposta: Arc<RespostaCacheada>) {
+        if let Some(lru) = &self.lru {
+            lru.lock().unwrap().put(cnpj, resposta);
+        }
+    }
+}
+
+/// Data de geração do banco para o Last-Modified: `gerado_em` de `_referencia` ou,
+/// em bancos antigos sem essa chave, o primeiro dia do mês de referência
+pub fn ultima_modificacao(gerado_em: Option<&str>, referencia: &str) -> SystemTime {
+    let segundos = gerado_em
+        .and_then(|valor| chrono::DateTime::parse_from_rfc3339(valor).ok())
+        .map(|data| data.timestamp())
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(&format!("{}-01", referencia), "%Y-%m-%d")
+                .ok()
+                .map(|dia| dia.and_time(chrono::NaiveTime::MIN).and_utc().timestamp())
+        })
+        .unwrap_or(0);
+    // Os cabeçalhos HTTP só têm precisão de segundos
+    UNIX_EPOCH + Duration::from_secs(segundos.max(0) as u64)
+}
+
+/// Se o cliente já tem esta versão da resposta. `If-None-Match` tem prioridade; sem
+/// ele, vale `If-Modified-Since`
+pub fn nao_modificado(req: &HttpRequest, etag: &str, modificado_em: SystemTime) -> bool {
+    if let Some(valor) = req.headers().get(header::IF_NONE_MATCH) {
+        let Ok(valor) = valor.to_str() else { return false };
+        return valor
+            .split(',')
+            .map(|item| item.trim().trim_start_matches("W/"))
+            .any(|item| item == "*" || item == etag);
+    }
+    req.headers()
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|valor| valor.to_str().ok())
+        .and_then(|valor| valor.parse::<HttpDate>().ok())
+        .is_some_and(|desde| modificado_em <= SystemTime::from(desde))
+}
+
+/// Valor do cabeçalho Last-Modified
+pub fn data_http(momento: SystemTime) -> String {
+    HttpDate::from(momento).to_string()
+}
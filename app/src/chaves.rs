@@ -0,0 +1,404 @@
+//! Chaves de acesso à API, com limite de requisições por minuto e cota diária.
+//!
+//! As chaves ficam em um arquivo SQLite próprio (separado do banco de dados da Receita,
+//! que é substituído a cada mês), guardadas só como SHA-256. A chave em si aparece uma
+//! única vez, quando é criada pelo subcomando `chaves criar`.
+
+use anyhow::{Context, Result};
+use chrono::{Duration as DuracaoDia, Local, NaiveTime};
+use colored::Colorize;
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+use crate::ui;
+
+/// Prefixo das chaves geradas, para reconhecê-las em arquivos de configuração e logs
+const PREFIXO_CHAVE: &str = "cnpj_";
+
+/// Quantos caracteres da chave (depois do prefixo) ficam visíveis em `chaves listar`
+const TAMANHO_IDENTIFICACAO: usize = 8;
+
+const SQL_TABELAS: &str = r#"
+    CREATE TABLE IF NOT EXISTS chaves (
+        id INTEGER PRIMARY KEY,
+        nome TEXT NOT NULL UNIQUE,
+        inicio TEXT NOT NULL,
+        hash TEXT NOT NULL UNIQUE,
+        por_minuto INTEGER NOT NULL,
+        cota_diaria INTEGER,
+        admin INTEGER NOT NULL DEFAULT 0,
+        criada_em TEXT NOT NULL,
+        revogada_em TEXT
+    );
+    CREATE TABLE IF NOT EXISTS uso_diario (
+        chave_id INTEGER NOT NULL,
+        dia TEXT NOT NULL,
+        requisicoes INTEGER NOT NULL,
+        PRIMARY KEY (chave_id, dia)
+    );
+"#;
+
+fn abrir(arquivo: &str) -> Result<Connection> {
+    let conn = Connection::open(arquivo)
+        .with_context(|| format!("Falha ao abrir o arquivo de chaves: {}", arquivo))?;
+    // O servidor grava o uso a cada requisição; sem fsync por transação fica barato
+    conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")?;
+    conn.execute_batch(SQL_TABELAS)?;
+    // Arquivos criados antes das chaves de administração não têm a coluna
+    let tem_admin: bool = conn
+        .prepare("SELECT 1 FROM pragma_table_info('chaves') WHERE name = 'admin'")?
+        .exists([])?;
+    if !tem_admin {
+        conn.execute_batch("ALTER TABLE chaves ADD COLUMN admin INTEGER NOT NULL DEFAULT 0")?;
+    }
+    Ok(conn)
+}
+
+fn hash_chave(chave: &str) -> String {
+    format!("{:x}", Sha256::digest(chave.as_bytes()))
+}
+
+fn gerar_chave() -> Result<String> {
+    let mut bytes = [0u8; 24];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| anyhow::anyhow!("Falha ao gerar bytes aleatórios para a chave: {}", e))?;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(format!("{}{}", PREFIXO_CHAVE, hex))
+}
+
+fn hoje() -> String {
+    Local::now().format("%Y-%m-%d").to_string()
+}
+
+/// Cria uma chave e a mostra uma única vez; só o hash fica gravado. Só chaves com `admin`
+/// podem usar as rotas de trabalhos e de recarga do banco
+pub fn criar(arquivo: &str, nome: &str, por_minuto: u32, cota_diaria: Option<u32>, admin: bool) -> Result<()> {
+    if por_minuto == 0 {
+        anyhow::bail!("O limite por minuto deve ser maior que zero");
+    }
+    let conn = abrir(arquivo)?;
+    let chave = gerar_chave()?;
+    let inicio = &chave[..PREFIXO_CHAVE.len() + TAMANHO_IDENTIFICACAO];
+
+    conn.execute(
+        "INSERT INTO chaves (nome, inicio, hash, por_minuto, cota_diaria, admin, criada_em) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![nome, inicio, hash_chave(&chave), por_minuto, cota_diaria, admin, Local::now().to_rfc3339()],
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(erro, _) if erro.code == rusqlite::ErrorCode::ConstraintViolation => {
+            anyhow::anyhow!("Já existe uma chave com o nome '{}'", nome)
+        }
+        e => e.into(),
+    })?;
+
+    ui::print_success(&format!(
+        "Chave '{}' criada em {}{}",
+        nome,
+        arquivo,
+        if admin { " (administração)" } else { "" }
+    ));
+    ui::print_warning("Guarde a chave agora: ela não é gravada e não poderá ser mostrada de novo");
+    // A chave vai sempre para a saída padrão, mesmo no modo silencioso
+    println!("{}", chave);
+    Ok(())
+}
+
+pub fn listar(arquivo: &str) -> Result<()> {
+    if !Path::new(arquivo).exists() {
+        anyhow::bail!("Arquivo de chaves não encontrado: {}", arquivo);
+    }
+    let conn = abrir(arquivo)?;
+    let mut stmt = conn.prepare(
+        "SELECT c.nome, c.inicio, c.por_minuto, c.cota_diaria, c.admin, c.criada_em, c.revogada_em, u.requisicoes
+         FROM chaves c LEFT JOIN uso_diario u ON u.chave_id = c.id AND u.dia = ?1
+         ORDER BY c.nome",
+    )?;
+    let linhas = stmt
+        .query_map([hoje()], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, Option<i64>>(3)?,
+                row.get::<_, bool>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, Option<String>>(6)?,
+                row.get::<_, Option<i64>>(7)?.unwrap_or(0),
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    if linhas.is_empty() {
+        ui::print_info(&format!("Nenhuma chave em {}", arquivo));
+        return Ok(());
+    }
+
+    println!(
+        "{:<20} {:<16} {:>10} {:>12} {:>10}  {:<5}  {:<10}  {}",
+        "NOME".bold(), "CHAVE".bold(), "POR MINUTO".bold(), "COTA DIÁRIA".bold(),
+        "USO HOJE".bold(), "ADMIN".bold(), "CRIADA EM".bold(), "SITUAÇÃO".bold()
+    );
+    for (nome, inicio, por_minuto, cota, admin, criada_em, revogada_em, uso) in linhas {
+        let cota = cota.map(|c| c.to_string()).unwrap_or_else(|| "sem limite".to_string());
+        let situacao = match revogada_em {
+            Some(data) => format!("revogada em {}", &data[..10]).red(),
+            None => "ativa".green(),
+        };
+        println!(
+            "{:<20} {:<16} {:>10} {:>12} {:>10}  {:<5}  {:<10}  {}",
+            nome, format!("{}…", inicio), por_minuto, cota, uso, if admin { "sim" } else { "não" },
+            &criada_em[..10], situacao
+        );
+    }
+    Ok(())
+}
+
+pub fn revogar(arquivo: &str, nome: &str) -> Result<()> {
+    if !Path::new(arquivo).exists() {
+        anyhow::bail!("Arquivo de chaves não encontrado: {}", arquivo);
+    }
+    let conn = abrir(arquivo)?;
+    let alteradas = conn.execute(
+        "UPDATE chaves SET revogada_em = ?1 WHERE nome = ?2 AND revogada_em IS NULL",
+        params![Local::now().to_rfc3339(), nome],
+    )?;
+    if alteradas == 0 {
+        anyhow::bail!("Nenhuma chave ativa com o nome '{}'", nome);
+    }
+    ui::print_success(&format!("Chave '{}' revogada; o servidor passa a recusá-la imediatamente", nome));
+    Ok(())
+}
+
+/// Motivo de uma requisição recusada
+#[derive(Debug)]
+pub enum Recusa {
+    SemChave,
+    ChaveInvalida,
+    /// Chave válida, mas a rota exige uma chave de administração
+    SemPermissao,
+    /// Limite por minuto excedido; `segundos` até haver uma requisição disponível
+    LimiteExcedido { segundos: u64 },
+    /// Cota do dia esgotada; `segundos` até a meia-noite
+    CotaEsgotada { segundos: u64 },
+}
+
+/// Balde de fichas: começa cheio com `por_minuto` fichas e recupera uma a cada
+/// `60 / por_minuto` segundos, então aceita rajadas até o limite do minuto
+struct Balde {
+    fichas: f64,
+    atualizado_em: Instant,
+}
+
+/// Verificação das chaves pelo servidor. A consulta ao arquivo é feita a cada requisição,
+/// então chaves criadas ou revogadas valem sem reiniciar o servidor
+pub struct ControleAcesso {
+    conn: Mutex<Connection>,
+    baldes: Mutex<HashMap<i64, Balde>>,
+}
+
+impl ControleAcesso {
+    pub fn abrir(arquivo: &str) -> Result<Self> {
+        if !Path::new(arquivo).exists() {
+            anyhow::bail!(
+                "Arquivo de chaves não encontrado: {} (crie uma chave com: cnpj-sqlite chaves --arquivo {} criar --nome <nome>)",
+                arquivo, arquivo
+            );
+        }
+        let conn = abrir(arquivo)?;
+        let ativas: i64 = conn.query_row("SELECT COUNT(*) FROM chaves WHERE revogada_em IS NULL", [], |row| row.get(0))?;
+        if ativas == 0 {
+            ui::print_warning(&format!("Nenhuma chave ativa em {}: todas as requisições serão recusadas", arquivo));
+        }
+        Ok(Self { conn: Mutex::new(conn), baldes: Mutex::new(HashMap::new()) })
+    }
+
+    /// Confere a chave (e, com `exige_admin`, se ela é de administração), desconta uma
+    /// ficha do limite por minuto e uma requisição da cota do dia. Uma requisição recusada
+    /// não gasta ficha nem cota
+    pub fn autorizar(&self, chave: Option<&str>, exige_admin: bool) -> Result<(), Recusa> {
+        let chave = chave.ok_or(Recusa::SemChave)?;
+        let conn = self.conn.lock().unwrap();
+        let encontrada = conn
+            .query_row(
+                "SELECT c.id, c.por_minuto, c.cota_diaria, c.admin, u.requisicoes
+                 FROM chaves c LEFT JOIN uso_diario u ON u.chave_id = c.id AND u.dia = ?2
+                 WHERE c.hash = ?1 AND c.revogada_em IS NULL",
+                params![hash_chave(chave), hoje()],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, Option<i64>>(2)?,
+                        row.get::<_, bool>(3)?,
+                        row.get::<_, Option<i64>>(4)?.unwrap_or(0),
+                    ))
+                },
+            )
+            .optional();
+        let (id, por_minuto, cota_diaria, admin, uso_hoje) = match encontrada {
+            Ok(Some(dados)) => dados,
+            Ok(None) => return Err(Recusa::ChaveInvalida),
+            Err(e) => {
+                ui::print_error(&format!("Falha ao consultar o arquivo de chaves: {}", e));
+                return Err(Recusa::ChaveInvalida);
+            }
+        };
+
+        if exige_admin && !admin {
+            return Err(Recusa::SemPermissao);
+        }
+        // A cota é conferida antes da ficha: uma chave sem cota não esvazia o balde nem
+        // grava no arquivo a cada tentativa
+        if cota_diaria.is_some_and(|cota| uso_hoje >= cota) {
+            return Err(Recusa::CotaEsgotada { segundos: segundos_ate_meia_noite() });
+        }
+        self.retirar_ficha(id, por_minuto as f64)?;
+
+        if let Some(cota) = cota_diaria {
+            // Confere de novo ao gravar, para o caso de outro servidor usar o mesmo arquivo;
+            // se a cota acabou nesse meio-tempo, a ficha volta para o balde
+            let contadas = conn.execute(
+                "INSERT INTO uso_diario (chave_id, dia, requisicoes) VALUES (?1, ?2, 1)
+                 ON CONFLICT (chave_id, dia) DO UPDATE SET requisicoes = requisicoes + 1
+                 WHERE requisicoes < ?3",
+                params![id, hoje(), cota],
+            );
+            match contadas {
+                Ok(0) => {
+                    self.devolver_ficha(id, por_minuto as f64);
+                    return Err(Recusa::CotaEsgotada { segundos: segundos_ate_meia_noite() });
+                }
+                Ok(_) => {}
+                Err(e) => ui::print_error(&format!("Falha ao registrar o uso da chave: {}", e)),
+            }
+        } else if let Err(e) = conn.execute(
+            "INSERT INTO uso_diario (chave_id, dia, requisicoes) VALUES (?1, ?2, 1)
+             ON CONFLICT (chave_id, dia) DO UPDATE SET requisicoes = requisicoes + 1",
+            params![id, hoje()],
+        ) {
+            ui::print_error(&format!("Falha ao registrar o uso da chave: {}", e));
+        }
+        Ok(())
+    }
+
+    fn retirar_ficha(&self, id: i64, por_minuto: f64) -> Result<(), Recusa> {
+        let por_segundo = por_minuto / 60.0;
+        let agora = Instant::now();
+        let mut baldes = self.baldes.lock().unwrap();
+        let balde = baldes.entry(id).or_insert(Balde { fichas: por_minuto, atualizado_em: agora });
+
+        let decorrido = agora.duration_since(balde.atualizado_em).as_secs_f64();
+        // O limite pode ter mudado no arquivo; o balde nunca passa do limite atual
+        balde.fichas = (balde.fichas + decorrido * por_segundo).min(por_minuto);
+        balde.atualizado_em = agora;
+
+        if balde.fichas < 1.0 {
+            let segundos = ((1.0 - balde.fichas) / por_segundo).ceil() as u64;
+            return Err(Recusa::LimiteExcedido { segundos: segundos.max(1) });
+        }
+        balde.fichas -= 1.0;
+        Ok(())
+    }
+
+    fn devolver_ficha(&self, id: i64, por_minuto: f64) {
+        if let Some(balde) = self.baldes.lock().unwrap().get_mut(&id) {
+            balde.fichas = (balde.fichas + 1.0).min(por_minuto);
+        }
+    }
+}
+
+fn segundos_ate_meia_noite() -> u64 {
+    let agora = Local::now().naive_local();
+    let meia_noite = (agora.date() + DuracaoDia::days(1)).and_time(NaiveTime::MIN);
+    (meia_noite - agora).num_seconds().max(1) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Arquivo de chaves com uma chave conhecida, `cnpj_<nome>`
+    fn controle(pasta: &Path, nome: &str, por_minuto: u32, cota_diaria: Option<u32>, admin: bool) -> (ControleAcesso, String) {
+        let arquivo = pasta.join("chaves.db");
+        let arquivo = arquivo.to_str().unwrap();
+        let chave = format!("{}{}", PREFIXO_CHAVE, nome);
+        abrir(arquivo)
+            .unwrap()
+            .execute(
+                "INSERT INTO chaves (nome, inicio, hash, por_minuto, cota_diaria, admin, criada_em) VALUES (?1, ?1, ?2, ?3, ?4, ?5, '')",
+                params![nome, hash_chave(&chave), por_minuto, cota_diaria, admin],
+            )
+            .unwrap();
+        (ControleAcesso::abrir(arquivo).unwrap(), chave)
+    }
+
+    fn fichas(acesso: &ControleAcesso) -> f64 {
+        acesso.baldes.lock().unwrap().values().next().unwrap().fichas
+    }
+
+    #[test]
+    fn aceita_rajada_ate_o_limite_por_minuto() {
+        let pasta = tempfile::tempdir().unwrap();
+        let (acesso, chave) = controle(pasta.path(), "rajada", 5, None, false);
+        for _ in 0..5 {
+            acesso.autorizar(Some(&chave), false).unwrap();
+        }
+        // Uma ficha a cada 12s com 5 por minuto
+        match acesso.autorizar(Some(&chave), false) {
+            Err(Recusa::LimiteExcedido { segundos }) => assert_eq!(segundos, 12),
+            outro => panic!("esperava LimiteExcedido, veio {:?}", outro),
+        }
+    }
+
+    #[test]
+    fn cota_esgotada_nao_gasta_ficha() {
+        let pasta = tempfile::tempdir().unwrap();
+        let (acesso, chave) = controle(pasta.path(), "cota", 10, Some(3), false);
+        for _ in 0..3 {
+            acesso.autorizar(Some(&chave), false).unwrap();
+        }
+        for _ in 0..5 {
+            match acesso.autorizar(Some(&chave), false) {
+                Err(Recusa::CotaEsgotada { segundos }) => assert!((1..=86_400).contains(&segundos)),
+                outro => panic!("esperava CotaEsgotada, veio {:?}", outro),
+            }
+        }
+        assert!((7.0..7.1).contains(&fichas(&acesso)));
+        let uso: i64 = acesso.conn.lock().unwrap()
+            .query_row("SELECT requisicoes FROM uso_diario", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(uso, 3);
+    }
+
+    #[test]
+    fn cota_esgotada_por_outro_servidor_devolve_a_ficha() {
+        let pasta = tempfile::tempdir().unwrap();
+        let (acesso, chave) = controle(pasta.path(), "disputa", 10, Some(3), false);
+        // Faz a gravação do uso não contar, como se outro servidor tivesse usado a última
+        // requisição do dia entre a leitura e a gravação
+        acesso.conn.lock().unwrap()
+            .execute_batch("CREATE TRIGGER outro_servidor BEFORE INSERT ON uso_diario BEGIN SELECT RAISE(IGNORE); END")
+            .unwrap();
+        assert!(matches!(acesso.autorizar(Some(&chave), false), Err(Recusa::CotaEsgotada { .. })));
+        assert!((9.99..=10.0).contains(&fichas(&acesso)));
+    }
+
+    #[test]
+    fn rotas_de_administracao_exigem_chave_admin() {
+        let pasta = tempfile::tempdir().unwrap();
+        let (acesso, chave) = controle(pasta.path(), "comum", 10, None, false);
+        assert!(matches!(acesso.autorizar(Some(&chave), true), Err(Recusa::SemPermissao)));
+        // A recusa não gasta ficha; a rota comum continua aceita
+        assert!(acesso.baldes.lock().unwrap().is_empty());
+        acesso.autorizar(Some(&chave), false).unwrap();
+        assert!(matches!(acesso.autorizar(None, false), Err(Recusa::SemChave)));
+        assert!(matches!(acesso.autorizar(Some("cnpj_outra"), false), Err(Recusa::ChaveInvalida)));
+
+        let pasta = tempfile::tempdir().unwrap();
+        let (acesso, chave) = controle(pasta.path(), "admin", 10, None, true);
+        acesso.autorizar(Some(&chave), true).unwrap();
+    }
+}
@@ -1,10 +1,20 @@
 use anyhow::{Context, Result};
 use rusqlite::{Connection, params};
 use chrono::Local;
+use crate::database;
+use crate::database_duckdb;
+use crate::ui;
 
 pub fn create_cnae_secundaria_table(db_path: &str, low_memory: bool) -> Result<()> {
-    println!("Iniciando criação da tabela cnae_secundaria...");
-    println!("Hora de início: {}", Local::now().format("%Y-%m-%d %H:%M:%S"));
+    ui::print_info("Iniciando criação da tabela cnae_secundaria...");
+    ui::print_info(&format!("Hora de início: {}", Local::now().format("%Y-%m-%d %H:%M:%S")));
+    
+    if database::is_duckdb_path(db_path) {
+        database_duckdb::create_cnae_secundaria_table(db_path.trim_start_matches("duckdb://"))?;
+        ui::print_info(&format!("Hora de término: {}", Local::now().format("%Y-%m-%d %H:%M:%S")));
+        ui::print_success("Tabela cnae_secundaria criada com sucesso!");
+        return Ok(());
+    }
     
     let mut conn = Connection::open(db_path)
         .with_context(|| format!("Falha ao abrir banco: {}", db_path))?;
@@ -17,14 +27,14 @@ pub fn create_cnae_secundaria_table(db_path: &str, low_memory: bool) -> Result<(
         create_with_pandas_approach(&mut conn)?;
     }
     
-    println!("Hora de término: {}", Local::now().format("%Y-%m-%d %H:%M:%S"));
-    println!("Tabela cnae_secundaria criada com sucesso!");
+    ui::print_info(&format!("Hora de término: {}", Local::now().format("%Y-%m-%d %H:%M:%S")));
+    ui::print_success("Tabela cnae_secundaria criada com sucesso!");
     
     Ok(())
 }
 
 fn create_with_pandas_approach(conn: &mut Connection) -> Result<()> {
-    println!("Usando método Pandas (carrega tudo na memória)...");
+    ui::print_info("Usando método Pandas (carrega tudo na memória)...");
     
     // Primeiro coletamos todos os dados
     let mut data = Vec::new();
@@ -60,7 +70,7 @@ fn create_with_pandas_approach(conn: &mut Connection) -> Result<()> {
     let mut count = 0;
     
     for chunk in data.chunks(chunk_size) {
-        let mut tx = conn.transaction()?;
+        let tx = conn.transaction()?;
         {
             let mut insert_stmt = tx.prepare("INSERT INTO cnae_secundaria (cnpj, cnae_fiscal_secundaria) VALUES (?1, ?2)")?;
             for (cnpj, cnae) in chunk {
@@ -71,7 +81,7 @@ fn create_with_pandas_approach(conn: &mut Connection) -> Result<()> {
         tx.commit()?;
         
         if count % 100_000 == 0 {
-            println!("  Processados {} registros...", count);
+            ui::print_verbose(&format!("Processados {} registros...", count));
         }
     }
     
@@ -79,13 +89,13 @@ fn create_with_pandas_approach(conn: &mut Connection) -> Result<()> {
     conn.execute("CREATE INDEX IF NOT EXISTS idx_cnae_secundaria_cnpj ON cnae_secundaria(cnpj)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_cnae_secundaria_cnae ON cnae_secundaria(cnae_fiscal_secundaria)", [])?;
     
-    println!("Total de registros inseridos: {}", count);
+    ui::print_info(&format!("Total de registros inseridos: {}", count));
     
     Ok(())
 }
 
 fn create_with_low_memory(conn: &mut Connection) -> Result<()> {
-    println!("Usando método Dask-like (baixo uso de memória)...");
+    ui::print_info("Usando método Dask-like (baixo uso de memória)...");
     
     // Cria tabela temporária
     conn.execute(
@@ -129,7 +139,7 @@ fn create_with_low_memory(conn: &mut Connection) -> Result<()> {
     let chunk_size = 100_000;
     
     for chunk in data.chunks(chunk_size) {
-        let mut tx = conn.transaction()?;
+        let tx = conn.transaction()?;
         {
             let mut insert_stmt = tx.prepare("INSERT INTO cnae_secundaria (cnpj, cnae_fiscal_secundaria) VALUES (?1, ?2)")?;
             for (cnpj, cnae) in chunk {
@@ -140,7 +150,7 @@ fn create_with_low_memory(conn: &mut Connection) -> Result<()> {
         tx.commit()?;
         
         if count % 100_000 == 0 {
-            println!("  Processados {} registros...", count);
+            ui::print_verbose(&format!("Processados {} registros...", count));
         }
     }
     
@@ -151,7 +161,7 @@ fn create_with_low_memory(conn: &mut Connection) -> Result<()> {
     conn.execute("CREATE INDEX IF NOT EXISTS idx_cnae_secundaria_cnpj ON cnae_secundaria(cnpj)", [])?;
     conn.execute("CREATE INDEX IF NOT EXISTS idx_cnae_secundaria_cnae ON cnae_secundaria(cnae_fiscal_secundaria)", [])?;
     
-    println!("Total de registros inseridos: {}", count);
+    ui::print_info(&format!("Total de registros inseridos: {}", count));
     
     Ok(())
 }
@@ -0,0 +1,50 @@
+//! Utilitários de CNPJ: limpeza do que foi digitado, dígitos verificadores e formatação.
+//!
+//! Todas as funções trabalham com o CNPJ numérico de 14 dígitos: 8 do CNPJ básico (a
+//! empresa), 4 da ordem (o estabelecimento) e 2 dígitos verificadores.
+
+/// Deixa só os dígitos de um CNPJ digitado com ou sem pontuação; `None` se não
+/// sobrarem exatamente 14 dígitos
+pub fn limpar(cnpj: &str) -> Option<String> {
+    let limpo = cnpj.trim().replace(['.', '/', '-'], "");
+    (limpo.len() == 14 && limpo.bytes().all(|b| b.is_ascii_digit())).then_some(limpo)
+}
+
+/// Calcula os dois dígitos verificadores a partir dos 12 primeiros dígitos (CNPJ básico e
+/// ordem); `None` se a base não tiver exatamente 12 dígitos
+pub fn calcular_dv(base: &str) -> Option<String> {
+    if base.len() != 12 || !base.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let mut digitos: Vec<u32> = base.bytes().map(|b| (b - b'0') as u32).collect();
+
+    let calcula_dv = |base: &[u32]| -> u32 {
+        // Pesos de 2 a 9, repetidos, da direita para a esquerda
+        let soma: u32 = base.iter().rev().enumerate()
+            .map(|(i, d)| d * (2 + (i as u32 % 8)))
+            .sum();
+        let resto = soma % 11;
+        if resto < 2 { 0 } else { 11 - resto }
+    };
+
+    let primeiro = calcula_dv(&digitos);
+    digitos.push(primeiro);
+    let segundo = calcula_dv(&digitos);
+    Some(format!("{}{}", primeiro, segundo))
+}
+
+/// Confere os dois dígitos verificadores de um CNPJ com 14 dígitos
+pub fn dv_valido(cnpj: &str) -> bool {
+    if cnpj.len() != 14 || !cnpj.is_ascii() {
+        return false;
+    }
+    calcular_dv(&cnpj[..12]).is_some_and(|dv| dv == cnpj[12..])
+}
+
+/// CNPJ de 14 dígitos no formato `00.000.000/0000-00`; outros valores voltam como vieram
+pub fn formatar(cnpj: &str) -> String {
+    if cnpj.len() != 14 || !cnpj.bytes().all(|b| b.is_ascii_digit()) {
+        return cnpj.to_string();
+    }
+    format!("{}.{}.{}/{}-{}", &cnpj[..2], &cnpj[2..5], &cnpj[5..8], &cnpj[8..12], &cnpj[12..])
+}
@@ -0,0 +1,693 @@
+//! Subcomando `comparar`: o que mudou entre dois bancos processados, normalmente de
+//! meses seguidos, antes de publicar a base nova.
+//!
+//! Os dois bancos são anexados (`ATTACH`) a uma conexão em memória e comparados por SQL,
+//! sem alterar nenhum deles. Cada diferença vira uma [`Alteracao`]: CNPJs novos e que
+//! saíram da base, mudanças de situação cadastral e de endereço, entradas e saídas de
+//! sócios e opções e exclusões do Simples e do MEI. As alterações são contadas por tipo,
+//! UF e CNAE e, com `--output`, gravadas uma por linha em CSV ou JSON.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use crate::migrations;
+use crate::recorte::Situacao;
+use crate::ui;
+use crate::utils;
+
+/// UFs e CNAEs mostrados no terminal; os arquivos de saída têm todos
+const MAX_GRUPOS_TERMINAL: usize = 10;
+
+/// Colunas de endereço comparadas, na ordem em que são formatadas
+const COLUNAS_ENDERECO: &[&str] = &[
+    "tipo_logradouro",
+    "logradouro",
+    "numero",
+    "complemento",
+    "bairro",
+    "cep",
+    "municipio",
+    "uf",
+];
+
+/// Colunas do CSV de alterações
+const COLUNAS_CSV: &[&str] = &["tipo", "cnpj", "uf", "cnae_fiscal", "antes", "depois", "data"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FormatoComparacao {
+    Csv,
+    Json,
+}
+
+/// Tipo de diferença entre a base antiga e a nova
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TipoAlteracao {
+    /// Estabelecimento que não existia na base antiga
+    Novo,
+    /// Estabelecimento que não está mais na base nova
+    Removido,
+    Situacao,
+    Endereco,
+    EntradaSocio,
+    SaidaSocio,
+    OpcaoSimples,
+    ExclusaoSimples,
+    OpcaoMei,
+    ExclusaoMei,
+}
+
+impl TipoAlteracao {
+    pub const TODOS: [TipoAlteracao; 10] = [
+        TipoAlteracao::Novo,
+        TipoAlteracao::Removido,
+        TipoAlteracao::Situacao,
+        TipoAlteracao::Endereco,
+        TipoAlteracao::EntradaSocio,
+        TipoAlteracao::SaidaSocio,
+        TipoAlteracao::OpcaoSimples,
+        TipoAlteracao::ExclusaoSimples,
+        TipoAlteracao::OpcaoMei,
+        TipoAlteracao::ExclusaoMei,
+    ];
+
+    /// Nome gravado nos arquivos de saída (ex: `entrada_socio`)
+    pub fn nome(self) -> &'static str {
+        match self {
+            TipoAlteracao::Novo => "novo",
+            TipoAlteracao::Removido => "removido",
+            TipoAlteracao::Situacao => "situacao",
+            TipoAlteracao::Endereco => "endereco",
+            TipoAlteracao::EntradaSocio => "entrada_socio",
+            TipoAlteracao::SaidaSocio => "saida_socio",
+            TipoAlteracao::OpcaoSimples => "opcao_simples",
+            TipoAlteracao::ExclusaoSimples => "exclusao_simples",
+            TipoAlteracao::OpcaoMei => "opcao_mei",
+            TipoAlteracao::ExclusaoMei => "exclusao_mei",
+        }
+    }
+
+    fn rotulo(self) -> &'static str {
+        match self {
+            TipoAlteracao::Novo => "CNPJs novos",
+            TipoAlteracao::Removido => "CNPJs que saíram da base",
+            TipoAlteracao::Situacao => "Mudanças de situação cadastral",
+            TipoAlteracao::Endereco => "Mudanças de endereço",
+            TipoAlteracao::EntradaSocio => "Entradas de sócios",
+            TipoAlteracao::SaidaSocio => "Saídas de sócios",
+            TipoAlteracao::OpcaoSimples => "Opções pelo Simples",
+            TipoAlteracao::ExclusaoSimples => "Exclusões do Simples",
+            TipoAlteracao::OpcaoMei => "Opções pelo MEI",
+            TipoAlteracao::ExclusaoMei => "Exclusões do MEI",
+        }
+    }
+}
+
+/// Uma diferença entre as bases. Alterações de sócios e do Simples são da empresa e
+/// aparecem no CNPJ da matriz (ou no CNPJ básico, se a matriz não estiver na base)
+#[derive(Debug, Clone, Serialize)]
+pub struct Alteracao {
+    pub tipo: TipoAlteracao,
+    pub cnpj: String,
+    pub uf: String,
+    pub cnae_fiscal: String,
+    /// Valor na base antiga: situação, endereço, sócio que saiu, opção anterior
+    pub antes: String,
+    /// Valor na base nova
+    pub depois: String,
+    /// Data do evento informada pela Receita (AAAAMMDD), quando houver
+    pub data: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResumoComparacao {
+    pub antigo: String,
+    pub novo: String,
+    pub referencia_antiga: Option<String>,
+    pub referencia_nova: Option<String>,
+    pub totais: BTreeMap<TipoAlteracao, u64>,
+    /// Estabelecimentos que passaram a baixada
+    pub encerrados: u64,
+    /// Mudanças de situação por par (ex: `ativa → baixada`)
+    pub transicoes_situacao: BTreeMap<String, u64>,
+    pub por_uf: BTreeMap<String, BTreeMap<TipoAlteracao, u64>>,
+    pub por_cnae: BTreeMap<String, BTreeMap<TipoAlteracao, u64>>,
+}
+
+impl ResumoComparacao {
+    fn registrar(&mut self, alteracao: &Alteracao) {
+        *self.totais.entry(alteracao.tipo).or_default() += 1;
+        if alteracao.tipo == TipoAlteracao::Situacao {
+            let transicao = format!("{} → {}", alteracao.antes, alteracao.depois);
+            *self.transicoes_situacao.entry(transicao).or_default() += 1;
+            if alteracao.depois == Situacao::Baixada.nome() {
+                self.encerrados += 1;
+            }
+        }
+        *self
+            .por_uf
+            .entry(alteracao.uf.clone())
+            .or_default()
+            .entry(alteracao.tipo)
+            .or_default() += 1;
+        *self
+            .por_cnae
+            .entry(alteracao.cnae_fiscal.clone())
+            .or_default()
+            .entry(alteracao.tipo)
+            .or_default() += 1;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.totais.values().sum()
+    }
+}
+
+pub struct OpcoesComparacao {
+    pub antigo: String,
+    pub novo: String,
+    /// Arquivo com uma linha por alteração
+    pub output: Option<String>,
+    /// Padrão: pela extensão de `output` (`.json` ou CSV)
+    pub formato: Option<FormatoComparacao>,
+}
+
+/// Arquivo das alterações. O JSON é escrito aos poucos, com o resumo no fim, para não
+/// guardar em memória as alterações de uma base inteira
+enum Saida {
+    Csv {
+        writer: Box<csv::Writer<BufWriter<fs::File>>>,
+        caminho_resumo: PathBuf,
+    },
+    Json {
+        writer: BufWriter<fs::File>,
+        primeira: bool,
+    },
+}
+
+impl Saida {
+    fn new(caminho: &Path, formato: FormatoComparacao) -> Result<Self> {
+        if let Some(pasta) = caminho.parent().and_then(|p| p.to_str()).filter(|p| !p.is_empty()) {
+            utils::ensure_dir(pasta)?;
+        }
+        let file = fs::File::create(caminho)
+            .with_context(|| format!("Falha ao criar arquivo: {:?}", caminho))?;
+
+        match formato {
+            FormatoComparacao::Csv => {
+                let mut writer = csv::WriterBuilder::new().from_writer(BufWriter::new(file));
+                writer.write_record(COLUNAS_CSV)?;
+                Ok(Saida::Csv { writer: Box::new(writer), caminho_resumo: caminho_resumo(caminho) })
+            }
+            FormatoComparacao::Json => {
+                let mut writer = BufWriter::new(file);
+                writer.write_all(b"{\"alteracoes\":[")?;
+                Ok(Saida::Json { writer, primeira: true })
+            }
+        }
+    }
+
+    fn gravar(&mut self, alteracao: &Alteracao) -> Result<()> {
+        match self {
+            Saida::Csv { writer, .. } => writer.write_record([
+                alteracao.tipo.nome(),
+                &alteracao.cnpj,
+                &alteracao.uf,
+                &alteracao.cnae_fiscal,
+                &alteracao.antes,
+                &alteracao.depois,
+                &alteracao.data,
+            ])?,
+            Saida::Json { writer, primeira } => {
+                if !*primeira {
+                    writer.write_all(b",")?;
+                }
+                *primeira = false;
+                writer.write_all(b"\n")?;
+                serde_json::to_writer(&mut *writer, alteracao)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fecha o arquivo; no CSV o resumo vai para um segundo arquivo, cujo caminho é retornado
+    fn finish(self, resumo: &ResumoComparacao) -> Result<Option<PathBuf>> {
+        match self {
+            Saida::Csv { mut writer, caminho_resumo } => {
+                writer.flush()?;
+                gravar_resumo_csv(&caminho_resumo, resumo)?;
+                Ok(Some(caminho_resumo))
+            }
+            Saida::Json { mut writer, .. } => {
+                writer.write_all(b"\n],\"resumo\":")?;
+                serde_json::to_writer_pretty(&mut writer, resumo)?;
+                writer.write_all(b"}\n")?;
+                writer.flush()?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// `alteracoes.csv` → `alteracoes-resumo.csv`
+fn caminho_resumo(caminho: &Path) -> PathBuf {
+    let nome = caminho.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    caminho.with_file_name(format!("{}-resumo.csv", nome))
+}
+
+/// Contagens do resumo em formato longo: agrupamento (total, situacao, uf, cnae), grupo,
+/// tipo e quantidade
+fn gravar_resumo_csv(caminho: &Path, resumo: &ResumoComparacao) -> Result<()> {
+    let file = fs::File::create(caminho)
+        .with_context(|| format!("Falha ao criar arquivo: {:?}", caminho))?;
+    let mut writer = csv::WriterBuilder::new().from_writer(BufWriter::new(file));
+    writer.write_record(["agrupamento", "grupo", "tipo", "quantidade"])?;
+    for (tipo, quantidade) in &resumo.totais {
+        writer.write_record(["total", "", tipo.nome(), &quantidade.to_string()])?;
+    }
+    for (transicao, quantidade) in &resumo.transicoes_situacao {
+        writer.write_record(["situacao", transicao, TipoAlteracao::Situacao.nome(), &quantidade.to_string()])?;
+    }
+    for (agrupamento, grupos) in [("uf", &resumo.por_uf), ("cnae", &resumo.por_cnae)] {
+        for (grupo, contagens) in grupos {
+            for (tipo, quantidade) in contagens {
+                writer.write_record([agrupamento, grupo, tipo.nome(), &quantidade.to_string()])?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Soma as alterações no resumo e as grava na saída, se houver
+struct Coleta {
+    resumo: ResumoComparacao,
+    saida: Option<Saida>,
+}
+
+impl Coleta {
+    fn registrar(&mut self, alteracao: Alteracao) -> Result<()> {
+        self.resumo.registrar(&alteracao);
+        if let Some(saida) = self.saida.as_mut() {
+            saida.gravar(&alteracao)?;
+        }
+        Ok(())
+    }
+
+    /// Executa `sql` e registra as alterações que `montar` tirar de cada linha
+    fn varrer<F>(&mut self, conn: &Connection, sql: &str, mut montar: F) -> Result<()>
+    where
+        F: FnMut(&Row) -> rusqlite::Result<Vec<Alteracao>>,
+    {
+        let mut stmt = conn.prepare(sql)?;
+        let mut linhas = stmt.query([])?;
+        while let Some(linha) = linhas.next()? {
+            for alteracao in montar(linha)? {
+                self.registrar(alteracao)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn texto(linha: &Row, i: usize) -> rusqlite::Result<String> {
+    Ok(linha.get::<_, Option<String>>(i)?.unwrap_or_default())
+}
+
+/// Nome da situação cadastral (ex: `08` → `baixada`); códigos desconhecidos voltam como vieram
+fn nome_situacao(codigo: &str) -> String {
+    Situacao::do_codigo(codigo).map(|s| s.nome().to_string()).unwrap_or_else(|| codigo.to_string())
+}
+
+/// Endereço em uma linha, a partir das colunas de `COLUNAS_ENDERECO` (com o nome do
+/// município no lugar do código, quando conhecido)
+fn formatar_endereco(campos: &[String]) -> String {
+    let [tipo, logradouro, numero, complemento, bairro, cep, municipio, uf] = campos else {
+        return campos.join(", ");
+    };
+    let rua = format!("{} {}", tipo, logradouro).trim().to_string();
+    let cep = if cep.is_empty() { String::new() } else { format!("CEP {}", cep) };
+    let cidade = [municipio.as_str(), uf.as_str()]
+        .into_iter()
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+    [rua, numero.clone(), complemento.clone(), bairro.clone(), cep, cidade]
+        .into_iter()
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Sócio como aparece nas alterações: nome e CPF/CNPJ (mascarado pela Receita)
+fn descrever_socio(nome: String, documento: String) -> String {
+    if documento.is_empty() {
+        nome
+    } else {
+        format!("{} ({})", nome, documento)
+    }
+}
+
+/// CNPJ da matriz ou, sem ela, o CNPJ básico
+fn cnpj_ou_basico(cnpj: String, cnpj_basico: String) -> String {
+    if cnpj.is_empty() {
+        cnpj_basico
+    } else {
+        cnpj
+    }
+}
+
+fn abrir(banco: &str) -> Result<()> {
+    if !Path::new(banco).exists() {
+        anyhow::bail!("Banco de dados não encontrado: {}", banco);
+    }
+    let conn = Connection::open(banco).with_context(|| format!("Falha ao abrir banco de dados: {}", banco))?;
+    migrations::verificar_versao(&conn, banco)
+}
+
+fn ler_referencia(conn: &Connection, esquema: &str) -> Result<Option<String>> {
+    let sql = format!("SELECT valor FROM {}._referencia WHERE referencia = 'CNPJ'", esquema);
+    Ok(conn.query_row(&sql, [], |row| row.get(0)).optional()?)
+}
+
+/// Compara a base `antigo` com a `novo`, mostra o resumo e grava as alterações em
+/// `output`, se informado
+pub fn comparar(opcoes: &OpcoesComparacao) -> Result<ResumoComparacao> {
+    abrir(&opcoes.antigo)?;
+    abrir(&opcoes.novo)?;
+
+    ui::print_header("🔀 Comparação entre Bases de CNPJ");
+    let conn = Connection::open_in_memory()?;
+    conn.execute("ATTACH DATABASE ?1 AS antigo", params![opcoes.antigo])?;
+    conn.execute("ATTACH DATABASE ?1 AS novo", params![opcoes.novo])?;
+
+    let referencia_antiga = ler_referencia(&conn, "antigo")?;
+    let referencia_nova = ler_referencia(&conn, "novo")?;
+    let mostrar = |referencia: &Option<String>| referencia.clone().unwrap_or_else(|| "desconhecida".to_string());
+    ui::print_info(&format!("Base antiga: {} (referência {})", opcoes.antigo, mostrar(&referencia_antiga)));
+    ui::print_info(&format!("Base nova: {} (referência {})", opcoes.novo, mostrar(&referencia_nova)));
+    if let (Some(antiga), Some(nova)) = (&referencia_antiga, &referencia_nova) {
+        if antiga == nova {
+            ui::print_warning("As duas bases têm a mesma referência");
+        } else if antiga > nova {
+            ui::print_warning("A base antiga tem referência mais recente que a nova; confira a ordem dos argumentos");
+        }
+    }
+
+    let formato = opcoes.formato.unwrap_or_else(|| match &opcoes.output {
+        Some(output) if output.to_lowercase().ends_with(".json") => FormatoComparacao::Json,
+        _ => FormatoComparacao::Csv,
+    });
+    let saida = match &opcoes.output {
+        Some(output) => Some(Saida::new(Path::new(output), formato)?),
+        None => None,
+    };
+    let mut coleta = Coleta {
+        resumo: ResumoComparacao {
+            antigo: opcoes.antigo.clone(),
+            novo: opcoes.novo.clone(),
+            referencia_antiga,
+            referencia_nova,
+            totais: TipoAlteracao::TODOS.iter().map(|tipo| (*tipo, 0)).collect(),
+            encerrados: 0,
+            transicoes_situacao: BTreeMap::new(),
+            por_uf: BTreeMap::new(),
+            por_cnae: BTreeMap::new(),
+        },
+        saida,
+    };
+    let inicio = Instant::now();
+    let mut etapas = ui::Etapas::new(4);
+
+    etapas.iniciar(1, "Estabelecimentos novos e removidos");
+    comparar_presenca(&conn, &mut coleta)?;
+    etapas.iniciar(2, "Situação cadastral e endereço");
+    comparar_estabelecimentos(&conn, &mut coleta)?;
+    etapas.iniciar(3, "Sócios");
+    comparar_socios(&conn, &mut coleta)?;
+    etapas.iniciar(4, "Simples e MEI");
+    comparar_simples(&conn, &mut coleta)?;
+    etapas.encerrar();
+
+    let Coleta { resumo, saida } = coleta;
+    let arquivo_resumo = match saida {
+        Some(saida) => saida.finish(&resumo)?,
+        None => None,
+    };
+
+    ui::print_separator();
+    ui::print_success(&format!(
+        "{} alterações encontradas em {:.1}s",
+        resumo.total(),
+        inicio.elapsed().as_secs_f64()
+    ));
+    let mut totais: Vec<(&str, u64)> = resumo.totais.iter().map(|(tipo, qtde)| (tipo.rotulo(), *qtde)).collect();
+    totais.push(("CNPJs encerrados (passaram a baixada)", resumo.encerrados));
+    ui::print_statistics(&totais);
+    if !resumo.transicoes_situacao.is_empty() {
+        ui::print_info("Mudanças de situação cadastral:");
+        for (transicao, quantidade) in &resumo.transicoes_situacao {
+            ui::print_item(&format!("{}: {}", transicao, quantidade));
+        }
+    }
+    mostrar_grupos("UFs", &resumo.por_uf);
+    mostrar_grupos("CNAEs", &resumo.por_cnae);
+
+    if let Some(output) = &opcoes.output {
+        match arquivo_resumo {
+            Some(arquivo) => ui::print_success(&format!(
+                "Alterações salvas em {} e contagens em {}",
+                output,
+                arquivo.display()
+            )),
+            None => ui::print_success(&format!("Alterações e resumo salvos em {}", output)),
+        }
+    }
+    Ok(resumo)
+}
+
+/// Os grupos (UFs ou CNAEs) com mais alterações
+fn mostrar_grupos(titulo: &str, grupos: &BTreeMap<String, BTreeMap<TipoAlteracao, u64>>) {
+    if grupos.is_empty() {
+        return;
+    }
+    let mut totais: Vec<(&String, &BTreeMap<TipoAlteracao, u64>, u64)> = grupos
+        .iter()
+        .map(|(grupo, contagens)| (grupo, contagens, contagens.values().sum()))
+        .collect();
+    totais.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(b.0)));
+
+    ui::print_info(&format!("{} com mais alterações:", titulo));
+    for (grupo, contagens, total) in totais.iter().take(MAX_GRUPOS_TERMINAL) {
+        let detalhes: Vec<String> = contagens
+            .iter()
+            .map(|(tipo, quantidade)| format!("{} {}", tipo.nome(), quantidade))
+            .collect();
+        let nome = if grupo.is_empty() { "(vazio)" } else { grupo.as_str() };
+        ui::print_item(&format!("{}: {} ({})", nome, total, detalhes.join(", ")));
+    }
+}
+
+/// Estabelecimentos que só existem em uma das bases
+fn comparar_presenca(conn: &Connection, coleta: &mut Coleta) -> Result<()> {
+    let sql = |de: &str, ate: &str| {
+        format!(
+            "SELECT x.cnpj, x.uf, x.cnae_fiscal, x.situacao_cadastral, x.data_inicio_atividades \
+             FROM {de}.estabelecimento x \
+             WHERE NOT EXISTS (SELECT 1 FROM {ate}.estabelecimento y WHERE y.cnpj = x.cnpj)",
+            de = de,
+            ate = ate
+        )
+    };
+    coleta.varrer(conn, &sql("novo", "antigo"), |linha| {
+        Ok(vec![Alteracao {
+            tipo: TipoAlteracao::Novo,
+            cnpj: texto(linha, 0)?,
+            uf: texto(linha, 1)?,
+            cnae_fiscal: texto(linha, 2)?,
+            antes: String::new(),
+            depois: nome_situacao(&texto(linha, 3)?),
+            data: texto(linha, 4)?,
+        }])
+    })?;
+    coleta.varrer(conn, &sql("antigo", "novo"), |linha| {
+        Ok(vec![Alteracao {
+            tipo: TipoAlteracao::Removido,
+            cnpj: texto(linha, 0)?,
+            uf: texto(linha, 1)?,
+            cnae_fiscal: texto(linha, 2)?,
+            antes: nome_situacao(&texto(linha, 3)?),
+            depois: String::new(),
+            data: String::new(),
+        }])
+    })
+}
+
+/// Situação cadastral e endereço dos estabelecimentos presentes nas duas bases
+fn comparar_estabelecimentos(conn: &Connection, coleta: &mut Coleta) -> Result<()> {
+    // O município sai pelo nome da tabela de códigos da base nova
+    let colunas_endereco = |alias: &str, municipio: &str| -> Vec<String> {
+        COLUNAS_ENDERECO
+            .iter()
+            .map(|coluna| match *coluna {
+                "municipio" => format!("COALESCE({}.descricao, {}.municipio)", municipio, alias),
+                _ => format!("{}.{}", alias, coluna),
+            })
+            .collect()
+    };
+    let diferencas: Vec<String> = std::iter::once("situacao_cadastral")
+        .chain(COLUNAS_ENDERECO.iter().copied())
+        .map(|coluna| format!("a.{c} IS NOT n.{c}", c = coluna))
+        .collect();
+    let sql = format!(
+        "SELECT n.cnpj, n.uf, n.cnae_fiscal, a.situacao_cadastral, n.situacao_cadastral, \
+         n.data_situacao_cadastral, {}, {} \
+         FROM novo.estabelecimento n \
+         JOIN antigo.estabelecimento a ON a.cnpj = n.cnpj \
+         LEFT JOIN novo.municipio ma ON ma.codigo = a.municipio \
+         LEFT JOIN novo.municipio mn ON mn.codigo = n.municipio \
+         WHERE {}",
+        colunas_endereco("a", "ma").join(", "),
+        colunas_endereco("n", "mn").join(", "),
+        diferencas.join(" OR ")
+    );
+
+    let inicio_antes = 6;
+    let inicio_depois = inicio_antes + COLUNAS_ENDERECO.len();
+    coleta.varrer(conn, &sql, |linha| {
+        let (cnpj, uf, cnae_fiscal) = (texto(linha, 0)?, texto(linha, 1)?, texto(linha, 2)?);
+        let mut alteracoes = Vec::new();
+
+        let (situacao_antes, situacao_depois) = (texto(linha, 3)?, texto(linha, 4)?);
+        if situacao_antes != situacao_depois {
+            alteracoes.push(Alteracao {
+                tipo: TipoAlteracao::Situacao,
+                cnpj: cnpj.clone(),
+                uf: uf.clone(),
+                cnae_fiscal: cnae_fiscal.clone(),
+                antes: nome_situacao(&situacao_antes),
+                depois: nome_situacao(&situacao_depois),
+                data: texto(linha, 5)?,
+            });
+        }
+
+        let endereco_antes = (inicio_antes..inicio_depois)
+            .map(|i| texto(linha, i))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let endereco_depois = (inicio_depois..inicio_depois + COLUNAS_ENDERECO.len())
+            .map(|i| texto(linha, i))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if endereco_antes != endereco_depois {
+            alteracoes.push(Alteracao {
+                tipo: TipoAlteracao::Endereco,
+                cnpj,
+                uf,
+                cnae_fiscal,
+                antes: formatar_endereco(&endereco_antes),
+                depois: formatar_endereco(&endereco_depois),
+                data: String::new(),
+            });
+        }
+        Ok(alteracoes)
+    })
+}
+
+/// Sócios que entraram ou saíram de empresas presentes nas duas bases. Um sócio é
+/// identificado pelo nome e pelo CPF/CNPJ; os das empresas novas já aparecem como `novo`
+fn comparar_socios(conn: &Connection, coleta: &mut Coleta) -> Result<()> {
+    let sql = |de: &str, ate: &str| {
+        format!(
+            "SELECT x.cnpj, x.cnpj_basico, e.uf, e.cnae_fiscal, x.nome_socio, x.cnpj_cpf_socio, \
+             x.data_entrada_sociedade \
+             FROM {de}.socios x \
+             LEFT JOIN {de}.estabelecimento e ON e.cnpj = x.cnpj \
+             WHERE EXISTS (SELECT 1 FROM {ate}.empresas emp WHERE emp.cnpj_basico = x.cnpj_basico) \
+             AND NOT EXISTS (SELECT 1 FROM {ate}.socios y WHERE y.cnpj_basico = x.cnpj_basico \
+             AND y.nome_socio IS x.nome_socio AND y.cnpj_cpf_socio IS x.cnpj_cpf_socio)",
+            de = de,
+            ate = ate
+        )
+    };
+    coleta.varrer(conn, &sql("novo", "antigo"), |linha| {
+        Ok(vec![Alteracao {
+            tipo: TipoAlteracao::EntradaSocio,
+            cnpj: cnpj_ou_basico(texto(linha, 0)?, texto(linha, 1)?),
+            uf: texto(linha, 2)?,
+            cnae_fiscal: texto(linha, 3)?,
+            antes: String::new(),
+            depois: descrever_socio(texto(linha, 4)?, texto(linha, 5)?),
+            data: texto(linha, 6)?,
+        }])
+    })?;
+    coleta.varrer(conn, &sql("antigo", "novo"), |linha| {
+        Ok(vec![Alteracao {
+            tipo: TipoAlteracao::SaidaSocio,
+            cnpj: cnpj_ou_basico(texto(linha, 0)?, texto(linha, 1)?),
+            uf: texto(linha, 2)?,
+            cnae_fiscal: texto(linha, 3)?,
+            antes: descrever_socio(texto(linha, 4)?, texto(linha, 5)?),
+            depois: String::new(),
+            data: String::new(),
+        }])
+    })
+}
+
+/// Opções e exclusões do Simples e do MEI de empresas presentes nas duas bases. Empresa
+/// ausente da tabela simples conta como não optante
+fn comparar_simples(conn: &Connection, coleta: &mut Coleta) -> Result<()> {
+    // Colunas: cnpj da matriz, cnpj_basico, uf, cnae, opção do Simples antes e depois,
+    // datas de opção e exclusão, e o mesmo para o MEI
+    let sql_alteradas = "SELECT m.cnpj, n.cnpj_basico, m.uf, m.cnae_fiscal, \
+         a.opcao_simples, n.opcao_simples, n.data_opcao_simples, n.data_exclusao_simples, \
+         a.opcao_mei, n.opcao_mei, n.data_opcao_mei, n.data_exclusao_mei \
+         FROM novo.simples n \
+         LEFT JOIN antigo.simples a ON a.cnpj_basico = n.cnpj_basico \
+         LEFT JOIN novo.estabelecimento m ON m.cnpj_basico = n.cnpj_basico AND m.matriz_filial = '1' \
+         WHERE EXISTS (SELECT 1 FROM antigo.empresas emp WHERE emp.cnpj_basico = n.cnpj_basico) \
+         AND (a.opcao_simples IS NOT n.opcao_simples OR a.opcao_mei IS NOT n.opcao_mei)";
+    // Empresas que saíram da tabela simples, mas continuam na base
+    let sql_removidas = "SELECT m.cnpj, a.cnpj_basico, m.uf, m.cnae_fiscal, \
+         a.opcao_simples, NULL, NULL, NULL, a.opcao_mei, NULL, NULL, NULL \
+         FROM antigo.simples a \
+         LEFT JOIN novo.estabelecimento m ON m.cnpj_basico = a.cnpj_basico AND m.matriz_filial = '1' \
+         WHERE EXISTS (SELECT 1 FROM novo.empresas emp WHERE emp.cnpj_basico = a.cnpj_basico) \
+         AND NOT EXISTS (SELECT 1 FROM novo.simples n WHERE n.cnpj_basico = a.cnpj_basico) \
+         AND (a.opcao_simples = 'S' OR a.opcao_mei = 'S')";
+
+    let montar = |linha: &Row| -> rusqlite::Result<Vec<Alteracao>> {
+        let cnpj = cnpj_ou_basico(texto(linha, 0)?, texto(linha, 1)?);
+        let (uf, cnae_fiscal) = (texto(linha, 2)?, texto(linha, 3)?);
+        let mut alteracoes = Vec::new();
+        // (opção, exclusão, primeira coluna)
+        for (opcao, exclusao, coluna) in [
+            (TipoAlteracao::OpcaoSimples, TipoAlteracao::ExclusaoSimples, 4),
+            (TipoAlteracao::OpcaoMei, TipoAlteracao::ExclusaoMei, 8),
+        ] {
+            let antes = texto(linha, coluna)?;
+            let depois = texto(linha, coluna + 1)?;
+            let (tipo, data) = match (antes == "S", depois == "S") {
+                (false, true) => (opcao, texto(linha, coluna + 2)?),
+                (true, false) => (exclusao, texto(linha, coluna + 3)?),
+                _ => continue,
+            };
+            alteracoes.push(Alteracao {
+                tipo,
+                cnpj: cnpj.clone(),
+                uf: uf.clone(),
+                cnae_fiscal: cnae_fiscal.clone(),
+                antes,
+                depois,
+                data,
+            });
+        }
+        Ok(alteracoes)
+    };
+    coleta.varrer(conn, sql_alteradas, montar)?;
+    coleta.varrer(conn, sql_removidas, montar)
+}
@@ -0,0 +1,291 @@
+//! Arquivo de configuração (`cnpj-sqlite.toml`) com as opções de download, processamento
+//! e servidor.
+//!
+//! Cada opção é resolvida em camadas, da maior para a menor prioridade: linha de comando,
+//! variável de ambiente `CNPJ_SQLITE_<SEÇÃO>_<CHAVE>` (ex: `CNPJ_SQLITE_SERVER_PORT`),
+//! arquivo de configuração e padrão embutido. A linha de comando é aplicada por quem usa
+//! a configuração; aqui ficam as outras três camadas.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::cache;
+use crate::database::Target;
+
+/// Arquivo lido da pasta atual quando não há `--config` nem `CNPJ_SQLITE_CONFIG`
+pub const ARQUIVO_PADRAO: &str = "cnpj-sqlite.toml";
+
+/// Variável com o caminho do arquivo de configuração
+pub const VARIAVEL_ARQUIVO: &str = "CNPJ_SQLITE_CONFIG";
+
+const PREFIXO_AMBIENTE: &str = "CNPJ_SQLITE";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub download: ConfigDownload,
+    pub process: ConfigProcess,
+    pub server: ConfigServer,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigDownload {
+    /// Pasta para salvar os arquivos ZIP
+    pub output: String,
+}
+
+impl Default for ConfigDownload {
+    fn default() -> Self {
+        Self { output: "dados-publicos-zip".to_string() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigProcess {
+    /// Pasta com os arquivos ZIP
+    pub input: String,
+    /// Pasta para descompactar e gerar o banco
+    pub output: String,
+    /// Banco de destino; sem valor, `<output>/cnpj.db`
+    pub target: Option<String>,
+    pub cleanup: bool,
+    pub vacuum: bool,
+}
+
+impl Default for ConfigProcess {
+    fn default() -> Self {
+        Self {
+            input: "dados-publicos-zip".to_string(),
+            output: "dados-publicos".to_string(),
+            target: None,
+            cleanup: true,
+            vacuum: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigServer {
+    pub database: String,
+    pub host: String,
+    pub port: u16,
+    pub watch: bool,
+    pub watch_interval: u64,
+    pub chaves: Option<String>,
+    pub cache_size: usize,
+}
+
+impl Default for ConfigServer {
+    fn default() -> Self {
+        Self {
+            database: "dados-publicos/cnpj.db".to_string(),
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            watch: false,
+            watch_interval: 30,
+            chaves: None,
+            cache_size: cache::TAMANHO_PADRAO,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Tipo {
+    Texto,
+    Inteiro,
+    Booleano,
+}
+
+/// Todas as chaves do arquivo, na ordem de `config show`; o tipo diz como ler a
+/// variável de ambiente correspondente
+const CHAVES: &[(&str, &str, Tipo)] = &[
+    ("download", "output", Tipo::Texto),
+    ("process", "input", Tipo::Texto),
+    ("process", "output", Tipo::Texto),
+    ("process", "target", Tipo::Texto),
+    ("process", "cleanup", Tipo::Booleano),
+    ("process", "vacuum", Tipo::Booleano),
+    ("server", "database", Tipo::Texto),
+    ("server", "host", Tipo::Texto),
+    ("server", "port", Tipo::Inteiro),
+    ("server", "watch", Tipo::Booleano),
+    ("server", "watch_interval", Tipo::Inteiro),
+    ("server", "chaves", Tipo::Texto),
+    ("server", "cache_size", Tipo::Inteiro),
+];
+
+/// Nomes antigos, ainda aceitos, usados pelos scripts `start-api`
+const ALIASES_AMBIENTE: &[(&str, &str, &str, Tipo)] = &[
+    ("API_HOST", "server", "host", Tipo::Texto),
+    ("API_PORT", "server", "port", Tipo::Inteiro),
+];
+
+/// De onde veio o valor efetivo de uma chave
+#[derive(Debug, Clone)]
+enum Origem {
+    Padrao,
+    Arquivo,
+    Ambiente(String),
+}
+
+/// Configuração efetiva e a origem de cada valor
+pub struct ConfigCarregada {
+    pub config: Config,
+    /// Arquivo lido, se houver
+    pub arquivo: Option<PathBuf>,
+    origens: HashMap<(String, String), Origem>,
+}
+
+fn nome_variavel(secao: &str, chave: &str) -> String {
+    format!("{}_{}_{}", PREFIXO_AMBIENTE, secao, chave).to_uppercase()
+}
+
+/// Arquivo a ler: `--config`, depois `CNPJ_SQLITE_CONFIG` (ambos obrigatórios se
+/// informados) e por fim `cnpj-sqlite.toml` na pasta atual, se existir
+fn localizar(informado: Option<&str>) -> Result<Option<PathBuf>> {
+    let explicito = informado
+        .map(str::to_string)
+        .or_else(|| std::env::var(VARIAVEL_ARQUIVO).ok().filter(|valor| !valor.is_empty()));
+    match explicito {
+        Some(caminho) => {
+            if !Path::new(&caminho).exists() {
+                anyhow::bail!("Arquivo de configuração não encontrado: {}", caminho);
+            }
+            Ok(Some(PathBuf::from(caminho)))
+        }
+        None => Ok(Some(PathBuf::from(ARQUIVO_PADRAO)).filter(|caminho| caminho.exists())),
+    }
+}
+
+fn valor_ambiente(variavel: &str, valor: &str, tipo: Tipo) -> Result<toml::Value> {
+    match tipo {
+        Tipo::Texto => Ok(toml::Value::String(valor.to_string())),
+        Tipo::Inteiro => valor
+            .trim()
+            .parse::<i64>()
+            .map(toml::Value::Integer)
+            .with_context(|| format!("{} deve ser um número inteiro; recebido: '{}'", variavel, valor)),
+        Tipo::Booleano => match valor.trim().to_lowercase().as_str() {
+            "true" | "1" | "sim" => Ok(toml::Value::Boolean(true)),
+            "false" | "0" | "nao" | "não" => Ok(toml::Value::Boolean(false)),
+            _ => anyhow::bail!("{} deve ser true ou false; recebido: '{}'", variavel, valor),
+        },
+    }
+}
+
+impl ConfigCarregada {
+    /// Junta padrões, arquivo e variáveis de ambiente
+    pub fn carregar(informado: Option<&str>) -> Result<Self> {
+        let mut tabela = toml::Table::try_from(Config::default())
+            .context("Falha ao montar a configuração padrão")?;
+        let mut origens: HashMap<(String, String), Origem> = HashMap::new();
+        for (secao, chave, _) in CHAVES {
+            origens.insert((secao.to_string(), chave.to_string()), Origem::Padrao);
+        }
+
+        let arquivo = localizar(informado)?;
+        if let Some(caminho) = &arquivo {
+            let conteudo = fs::read_to_string(caminho)
+                .with_context(|| format!("Falha ao ler o arquivo de configuração {:?}", caminho))?;
+            let lida: toml::Table = toml::from_str(&conteudo)
+                .with_context(|| format!("Arquivo de configuração inválido: {:?}", caminho))?;
+            // Valida nomes e tipos antes de misturar com os padrões
+            lida.clone()
+                .try_into::<Config>()
+                .with_context(|| format!("Arquivo de configuração inválido: {:?}", caminho))?;
+            for (secao, valores) in lida {
+                if let toml::Value::Table(valores) = valores {
+                    for (chave, valor) in valores {
+                        origens.insert((secao.clone(), chave.clone()), Origem::Arquivo);
+                        definir(&mut tabela, &secao, &chave, valor);
+                    }
+                }
+            }
+        }
+
+        // Os nomes antigos vêm antes para que os novos tenham prioridade
+        let mut variaveis: Vec<(String, &str, &str, Tipo)> = Vec::new();
+        for (variavel, secao, chave, tipo) in ALIASES_AMBIENTE {
+            variaveis.push((variavel.to_string(), secao, chave, *tipo));
+        }
+        for (secao, chave, tipo) in CHAVES {
+            variaveis.push((nome_variavel(secao, chave), secao, chave, *tipo));
+        }
+        for (variavel, secao, chave, tipo) in variaveis {
+            if let Ok(valor) = std::env::var(&variavel) {
+                definir(&mut tabela, secao, chave, valor_ambiente(&variavel, &valor, tipo)?);
+                origens.insert((secao.to_string(), chave.to_string()), Origem::Ambiente(variavel));
+            }
+        }
+
+        let config = tabela.try_into::<Config>().context("Configuração inválida")?;
+        Ok(Self { config, arquivo, origens })
+    }
+
+    /// Banco dos subcomandos que usam um banco já processado (`server`, `consulta`, `export`,
+    /// `relatorio`, `migrate`, `cnae-secundaria`, `recortar`): `server.database`, se
+    /// definido no arquivo ou no ambiente; senão, o banco gerado por `process`
+    /// (`process.target`, se for um arquivo, ou `<process.output>/cnpj.db`)
+    pub fn banco(&self) -> String {
+        let definido = |secao: &str, chave: &str| {
+            !matches!(self.origens.get(&(secao.to_string(), chave.to_string())), Some(Origem::Padrao) | None)
+        };
+        if definido("server", "database") {
+            return self.config.server.database.clone();
+        }
+        match self.config.process.target.as_deref().map(Target::parse) {
+            Some(Target::Sqlite(caminho)) => caminho.to_string_lossy().into_owned(),
+            Some(Target::Duckdb(caminho)) => format!("duckdb://{}", caminho.to_string_lossy()),
+            _ => Path::new(&self.config.process.output).join("cnpj.db").to_string_lossy().into_owned(),
+        }
+    }
+
+    /// Configuração efetiva em TOML, com a origem de cada valor em comentário; a saída
+    /// pode ser usada como ponto de partida de um `cnpj-sqlite.toml`
+    pub fn mostrar(&self) -> Result<String> {
+        let tabela = toml::Table::try_from(&self.config)?;
+        let mut saida = match &self.arquivo {
+            Some(caminho) => format!("# Arquivo de configuração: {}\n", caminho.display()),
+            None => "# Nenhum arquivo de configuração; valores padrão e variáveis de ambiente\n".to_string(),
+        };
+
+        let mut secao_atual = "";
+        for (secao, chave, _) in CHAVES {
+            if *secao != secao_atual {
+                saida.push_str(&format!("\n[{}]\n", secao));
+                secao_atual = secao;
+            }
+            let variavel = nome_variavel(secao, chave);
+            let origem = match self.origens.get(&(secao.to_string(), chave.to_string())) {
+                Some(Origem::Arquivo) => format!("arquivo ({})", variavel),
+                Some(Origem::Ambiente(lida)) => format!("variável {}", lida),
+                Some(Origem::Padrao) | None => format!("padrão ({})", variavel),
+            };
+            match tabela.get(*secao).and_then(|valores| valores.get(*chave)) {
+                Some(valor) => {
+                    let linha = format!("{} = {}", chave, valor);
+                    saida.push_str(&format!("{:<40} # {}\n", linha, origem));
+                }
+                None => {
+                    let linha = format!("# {} =", chave);
+                    saida.push_str(&format!("{:<40} # não definido ({})\n", linha, variavel));
+                }
+            }
+        }
+        Ok(saida)
+    }
+}
+
+fn definir(tabela: &mut toml::Table, secao: &str, chave: &str, valor: toml::Value) {
+    let secao = tabela
+        .entry(secao.to_string())
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    if let toml::Value::Table(valores) = secao {
+        valores.insert(chave.to_string(), valor);
+    }
+}
@@ -0,0 +1,342 @@
+//! Subcomando `consulta`: busca CNPJs direto no banco, sem subir o servidor.
+//!
+//! Um CNPJ é mostrado como tabela, JSON (o mesmo corpo de `/cnpj/{cnpj}`) ou YAML;
+//! um arquivo de CNPJs gera um CSV com uma linha por CNPJ lido.
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use colored::Colorize;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use crate::cnpj;
+use crate::consultas::{self, CnpjResponse, FonteConsulta};
+use crate::ui;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FormatoConsulta {
+    Tabela,
+    Json,
+    Yaml,
+}
+
+/// Colunas do CSV da consulta em lote
+const COLUNAS_LOTE: &[&str] = &[
+    "cnpj",
+    "resultado",
+    "razao_social",
+    "nome_fantasia",
+    "matriz_filial",
+    "situacao_cadastral",
+    "data_situacao_cadastral",
+    "motivo_situacao_cadastral_desc",
+    "data_inicio_atividades",
+    "natureza_juridica",
+    "natureza_juridica_desc",
+    "capital_social",
+    "porte_empresa",
+    "cnae_fiscal",
+    "cnae_fiscal_desc",
+    "cnae_fiscal_secundaria",
+    "tipo_logradouro",
+    "logradouro",
+    "numero",
+    "complemento",
+    "bairro",
+    "cep",
+    "uf",
+    "municipio_desc",
+    "ddd1",
+    "telefone1",
+    "correio_eletronico",
+    "opcao_simples",
+    "opcao_mei",
+    "quantidade_socios",
+    "socios",
+];
+
+fn abrir(db_path: &str) -> Result<Box<dyn FonteConsulta>> {
+    // O SQLite criaria um arquivo vazio no caminho errado
+    if !db_path.starts_with("postgres") && !Path::new(db_path).exists() {
+        anyhow::bail!("Banco de dados não encontrado: {}", db_path);
+    }
+    consultas::abrir_fonte(db_path)
+}
+
+/// Consulta um CNPJ e mostra o resultado no formato pedido
+pub fn consultar(db_path: &str, cnpj: &str, formato: FormatoConsulta) -> Result<()> {
+    let cnpj_limpo = cnpj::limpar(cnpj)
+        .with_context(|| format!("CNPJ inválido: '{}' (use 14 dígitos, com ou sem pontuação)", cnpj))?;
+    let fonte = abrir(db_path)?;
+    let dados = consultas::buscar_cnpj(&*fonte, &cnpj_limpo)?
+        .with_context(|| format!("CNPJ {} não encontrado em {}", cnpj_limpo, db_path))?;
+
+    match formato {
+        FormatoConsulta::Tabela => imprimir_tabela(&dados),
+        FormatoConsulta::Json => println!("{}", serde_json::to_string_pretty(&dados)?),
+        FormatoConsulta::Yaml => print!("{}", serde_yaml::to_string(&dados)?),
+    }
+    Ok(())
+}
+
+/// Consulta os CNPJs de um arquivo (um por linha ou na primeira coluna de um CSV) e grava
+/// um CSV em `saida`, ou na saída padrão. CNPJs inválidos ou ausentes também viram linha,
+/// com o motivo na coluna `resultado`
+pub fn consultar_lote(db_path: &str, arquivo: &str, saida: Option<&str>) -> Result<()> {
+    let cnpjs = ler_cnpjs(arquivo)?;
+    let fonte = abrir(db_path)?;
+
+    let destino: Box<dyn Write> = match saida {
+        Some(caminho) => Box::new(
+            File::create(caminho).with_context(|| format!("Falha ao criar arquivo: {}", caminho))?,
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut writer = csv::Writer::from_writer(destino);
+    writer.write_record(COLUNAS_LOTE)?;
+
+    let (mut encontrados, mut ausentes, mut invalidos) = (0u64, 0u64, 0u64);
+    for cnpj in &cnpjs {
+        let Some(cnpj_limpo) = cnpj::limpar(cnpj) else {
+            invalidos += 1;
+            writer.write_record(linha_vazia(cnpj, "invalido"))?;
+            continue;
+        };
+        match consultas::buscar_cnpj(&*fonte, &cnpj_limpo)? {
+            Some(dados) => {
+                encontrados += 1;
+                writer.write_record(linha_lote(&dados))?;
+            }
+            None => {
+                ausentes += 1;
+                writer.write_record(linha_vazia(&cnpj_limpo, "nao_encontrado"))?;
+            }
+        }
+    }
+    writer.flush()?;
+
+    // Com o CSV na saída padrão, o resumo atrapalharia quem lê o resultado
+    if let Some(caminho) = saida {
+        ui::print_success(&format!("{} CNPJs consultados, resultado em {}", cnpjs.len(), caminho));
+        ui::print_statistics(&[
+            ("Encontrados", encontrados),
+            ("Não encontrados", ausentes),
+            ("Inválidos", invalidos),
+        ]);
+    }
+    Ok(())
+}
+
+/// Lê a primeira coluna de cada linha, ignorando linhas vazias, comentários (`#`) e um
+/// cabeçalho sem dígitos na primeira linha
+pub(crate) fn ler_cnpjs(arquivo: &str) -> Result<Vec<String>> {
+    let leitor = BufReader::new(
+        File::open(arquivo).with_context(|| format!("Falha ao abrir arquivo de CNPJs: {}", arquivo))?,
+    );
+    let mut cnpjs = Vec::new();
+    for (numero, linha) in leitor.lines().enumerate() {
+        let linha = linha.with_context(|| format!("Falha ao ler {} (linha {})", arquivo, numero + 1))?;
+        let campo = linha
+            .split([',', ';', '\t'])
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .trim_matches('"')
+            .trim();
+        if campo.is_empty() || campo.starts_with('#') {
+            continue;
+        }
+        if numero == 0 && !campo.bytes().any(|b| b.is_ascii_digit()) {
+            continue;
+        }
+        cnpjs.push(campo.to_string());
+    }
+    if cnpjs.is_empty() {
+        anyhow::bail!("Nenhum CNPJ encontrado em {}", arquivo);
+    }
+    Ok(cnpjs)
+}
+
+fn linha_vazia(cnpj: &str, resultado: &str) -> Vec<String> {
+    let mut linha = vec![String::new(); COLUNAS_LOTE.len()];
+    linha[0] = cnpj.to_string();
+    linha[1] = resultado.to_string();
+    linha
+}
+
+fn linha_lote(dados: &CnpjResponse) -> Vec<String> {
+    let est = &dados.estabelecimento;
+    let empresa = dados.empresa.as_ref();
+    let simples = dados.simples.as_ref();
+    let texto = |valor: Option<&String>| valor.cloned().unwrap_or_default();
+
+    vec![
+        dados.cnpj.clone(),
+        "encontrado".to_string(),
+        texto(empresa.map(|e| &e.razao_social)),
+        est.nome_fantasia.clone(),
+        est.matriz_filial.clone(),
+        est.situacao_cadastral.clone(),
+        est.data_situacao_cadastral.clone(),
+        texto(est.motivo_situacao_cadastral_desc.as_ref()),
+        est.data_inicio_atividades.clone(),
+        texto(empresa.map(|e| &e.natureza_juridica)),
+        texto(empresa.and_then(|e| e.natureza_juridica_desc.as_ref())),
+        empresa.and_then(|e| e.capital_social).map(|c| c.to_string()).unwrap_or_default(),
+        texto(empresa.map(|e| &e.porte_empresa)),
+        est.cnae_fiscal.clone(),
+        texto(est.cnae_fiscal_desc.as_ref()),
+        est.cnae_fiscal_secundaria.clone(),
+        est.tipo_logradouro.clone(),
+        est.logradouro.clone(),
+        est.numero.clone(),
+        est.complemento.clone(),
+        est.bairro.clone(),
+        est.cep.clone(),
+        est.uf.clone(),
+        texto(est.municipio_desc.as_ref()),
+        est.ddd1.clone(),
+        est.telefone1.clone(),
+        est.correio_eletronico.clone(),
+        texto(simples.map(|s| &s.opcao_simples)),
+        texto(simples.map(|s| &s.opcao_mei)),
+        dados.socios.len().to_string(),
+        dados.socios.iter().map(|s| s.nome_socio.as_str()).collect::<Vec<_>>().join("; "),
+    ]
+}
+
+/// Data AAAAMMDD da Receita como DD/MM/AAAA; outros valores ficam como estão
+fn data(valor: &str) -> String {
+    if valor.len() == 8 && valor.bytes().all(|b| b.is_ascii_digit()) && valor != "00000000" {
+        format!("{}/{}/{}", &valor[6..], &valor[4..6], &valor[..4])
+    } else {
+        valor.to_string()
+    }
+}
+
+/// Código seguido da descrição, quando houver
+fn com_descricao(codigo: &str, descricao: Option<&str>) -> String {
+    match descricao {
+        Some(descricao) if !descricao.is_empty() => format!("{} - {}", codigo, descricao),
+        _ => codigo.to_string(),
+    }
+}
+
+fn descricao_situacao(codigo: &str) -> Option<&'static str> {
+    match codigo {
+        "01" | "1" => Some("Nula"),
+        "02" | "2" => Some("Ativa"),
+        "03" | "3" => Some("Suspensa"),
+        "04" | "4" => Some("Inapta"),
+        "08" | "8" => Some("Baixada"),
+        _ => None,
+    }
+}
+
+fn descricao_porte(codigo: &str) -> Option<&'static str> {
+    match codigo {
+        "00" => Some("Não informado"),
+        "01" => Some("Micro empresa"),
+        "03" => Some("Empresa de pequeno porte"),
+        "05" => Some("Demais"),
+        _ => None,
+    }
+}
+
+fn secao(titulo: &str) {
+    println!("\n{}", titulo.bold().cyan());
+    println!("{}", "─".repeat(titulo.chars().count()).cyan());
+}
+
+fn campo(rotulo: &str, valor: &str) {
+    // Campos vazios só poluiriam a tabela
+    if !valor.trim().is_empty() {
+        println!("  {:<28} {}", format!("{}:", rotulo).bold(), valor);
+    }
+}
+
+fn telefone(ddd: &str, numero: &str) -> String {
+    if ddd.trim().is_empty() {
+        numero.to_string()
+    } else {
+        format!("({}) {}", ddd.trim(), numero)
+    }
+}
+
+/// Tabela legível no terminal; sai mesmo no modo silencioso, já que é o resultado pedido
+fn imprimir_tabela(dados: &CnpjResponse) {
+    let est = &dados.estabelecimento;
+    secao(&format!("CNPJ {}", cnpj::formatar(&dados.cnpj)));
+    if let Some(empresa) = &dados.empresa {
+        campo("Razão social", &empresa.razao_social);
+    }
+    campo("Nome fantasia", &est.nome_fantasia);
+    campo(
+        "Tipo",
+        match est.matriz_filial.as_str() {
+            "1" => "Matriz",
+            "2" => "Filial",
+            outro => outro,
+        },
+    );
+    campo("Situação cadastral", &com_descricao(&est.situacao_cadastral, descricao_situacao(&est.situacao_cadastral)));
+    campo("Data da situação", &data(&est.data_situacao_cadastral));
+    campo("Motivo da situação", &com_descricao(&est.motivo_situacao_cadastral, est.motivo_situacao_cadastral_desc.as_deref()));
+    campo("Início das atividades", &data(&est.data_inicio_atividades));
+    campo("CNAE principal", &com_descricao(&est.cnae_fiscal, est.cnae_fiscal_desc.as_deref()));
+    campo("CNAEs secundários", &est.cnae_fiscal_secundaria.replace(',', ", "));
+    campo("Situação especial", &est.situacao_especial);
+    campo("Data da situação especial", &data(&est.data_situacao_especial));
+
+    secao("Endereço");
+    campo(
+        "Logradouro",
+        format!("{} {}, {} {}", est.tipo_logradouro, est.logradouro, est.numero, est.complemento).trim(),
+    );
+    campo("Bairro", &est.bairro);
+    campo("Município", &format!("{} - {}", est.municipio_desc.as_deref().unwrap_or(&est.municipio), est.uf));
+    campo("CEP", &est.cep);
+    campo("Cidade no exterior", &est.nome_cidade_exterior);
+    campo("País", &com_descricao(&est.pais, est.pais_desc.as_deref()));
+    campo("Telefone", &telefone(&est.ddd1, &est.telefone1));
+    campo("Telefone 2", &telefone(&est.ddd2, &est.telefone2));
+    campo("Fax", &telefone(&est.ddd_fax, &est.fax));
+    campo("E-mail", &est.correio_eletronico);
+
+    if let Some(empresa) = &dados.empresa {
+        secao("Empresa");
+        campo("Natureza jurídica", &com_descricao(&empresa.natureza_juridica, empresa.natureza_juridica_desc.as_deref()));
+        campo(
+            "Qualificação do responsável",
+            &com_descricao(&empresa.qualificacao_responsavel, empresa.qualificacao_responsavel_desc.as_deref()),
+        );
+        if let Some(capital) = empresa.capital_social {
+            campo("Capital social", &format!("R$ {:.2}", capital).replace('.', ","));
+        }
+        campo("Porte", &com_descricao(&empresa.porte_empresa, descricao_porte(&empresa.porte_empresa)));
+        campo("Ente federativo", &empresa.ente_federativo_responsavel);
+    }
+
+    if let Some(simples) = &dados.simples {
+        secao("Simples Nacional");
+        campo("Optante pelo Simples", &simples.opcao_simples);
+        campo("Data de opção", &data(&simples.data_opcao_simples));
+        campo("Data de exclusão", &data(&simples.data_exclusao_simples));
+        campo("Optante pelo MEI", &simples.opcao_mei);
+        campo("Data de opção pelo MEI", &data(&simples.data_opcao_mei));
+        campo("Data de exclusão do MEI", &data(&simples.data_exclusao_mei));
+    }
+
+    secao(&format!("Sócios ({})", dados.socios.len()));
+    if dados.socios.is_empty() {
+        println!("  {}", "Nenhum sócio cadastrado".dimmed());
+    }
+    for socio in &dados.socios {
+        println!("  {}", socio.nome_socio.bold());
+        campo("  CPF/CNPJ", &socio.cnpj_cpf_socio);
+        campo("  Qualificação", &com_descricao(&socio.qualificacao_socio, socio.qualificacao_socio_desc.as_deref()));
+        campo("  Entrada na sociedade", &data(&socio.data_entrada_sociedade));
+        campo("  Representante legal", &socio.nome_representante);
+    }
+    println!();
+}
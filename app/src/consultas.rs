@@ -0,0 +1,414 @@
+//! Camada de consulta aos dados de um CNPJ, usada pela API e pelo subcomando `consulta`.
+//!
+//! As mesmas funções servem bancos SQLite e DuckDB: cada um implementa `FonteConsulta`
+//! e `LinhaConsulta`, e as consultas e a montagem das structs ficam aqui.
+
+use anyhow::Context;
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::database;
+use crate::database_duckdb;
+use crate::migrations;
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CnpjResponse {
+    pub cnpj: String,
+    pub estabelecimento: EstabelecimentoCompleto,
+    pub empresa: Option<EmpresaCompleta>,
+    pub socios: Vec<SocioCompleto>,
+    pub simples: Option<SimplesDados>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct EstabelecimentoCompleto {
+    // Dados do estabelecimento
+    pub cnpj: String,
+    pub cnpj_basico: String,
+    pub cnpj_ordem: String,
+    pub cnpj_dv: String,
+    pub matriz_filial: String,
+    pub nome_fantasia: String,
+    pub situacao_cadastral: String,
+    pub data_situacao_cadastral: String,
+    pub motivo_situacao_cadastral: String,
+    pub motivo_situacao_cadastral_desc: Option<String>,
+    pub nome_cidade_exterior: String,
+    pub pais: String,
+    pub pais_desc: Option<String>,
+    pub data_inicio_atividades: String,
+    pub cnae_fiscal: String,
+    pub cnae_fiscal_desc: Option<String>,
+    pub cnae_fiscal_secundaria: String,
+    pub tipo_logradouro: String,
+    pub logradouro: String,
+    pub numero: String,
+    pub complemento: String,
+    pub bairro: String,
+    pub cep: String,
+    pub uf: String,
+    pub municipio: String,
+    pub municipio_desc: Option<String>,
+    pub ddd1: String,
+    pub telefone1: String,
+    pub ddd2: String,
+    pub telefone2: String,
+    pub ddd_fax: String,
+    pub fax: String,
+    pub correio_eletronico: String,
+    pub situacao_especial: String,
+    pub data_situacao_especial: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct EmpresaCompleta {
+    pub cnpj_basico: String,
+    pub razao_social: String,
+    pub natureza_juridica: String,
+    pub natureza_juridica_desc: Option<String>,
+    pub qualificacao_responsavel: String,
+    pub qualificacao_responsavel_desc: Option<String>,
+    pub capital_social: Option<f64>,
+    pub porte_empresa: String,
+    pub ente_federativo_responsavel: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SocioCompleto {
+    pub cnpj: String,
+    pub cnpj_basico: String,
+    pub identificador_de_socio: String,
+    pub nome_socio: String,
+    pub cnpj_cpf_socio: String,
+    pub qualificacao_socio: String,
+    pub qualificacao_socio_desc: Option<String>,
+    pub data_entrada_sociedade: String,
+    pub pais: String,
+    pub pais_desc: Option<String>,
+    pub representante_legal: String,
+    pub nome_representante: String,
+    pub qualificacao_representante_legal: String,
+    pub qualificacao_representante_legal_desc: Option<String>,
+    pub faixa_etaria: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SimplesDados {
+    pub cnpj_basico: String,
+    pub opcao_simples: String,
+    pub data_opcao_simples: String,
+    pub data_exclusao_simples: String,
+    pub opcao_mei: String,
+    pub data_opcao_mei: String,
+    pub data_exclusao_mei: String,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ArquivoOrigem {
+    pub arquivo: String,
+    pub tamanho: i64,
+    pub sha256: String,
+}
+
+/// Acesso de leitura aos dados de um CNPJ
+pub trait FonteConsulta: Send {
+    fn buscar_estabelecimento(&self, cnpj: &str) -> anyhow::Result<Option<EstabelecimentoCompleto>>;
+    fn buscar_empresa(&self, cnpj_basico: &str) -> anyhow::Result<Option<EmpresaCompleta>>;
+    /// Sócios são da empresa, então a busca é pelo CNPJ básico (vale para matriz e filiais)
+    fn buscar_socios(&self, cnpj_basico: &str) -> anyhow::Result<Vec<SocioCompleto>>;
+    fn buscar_simples(&self, cnpj_basico: &str) -> anyhow::Result<Option<SimplesDados>>;
+    /// Consulta trivial para confirmar que o banco responde
+    fn ping(&self) -> anyhow::Result<()>;
+    /// Pares chave → valor da tabela `_referencia`
+    fn ler_referencia(&self) -> anyhow::Result<Vec<(String, String)>>;
+    fn ler_arquivos_origem(&self) -> anyhow::Result<Vec<ArquivoOrigem>>;
+    fn versao_esquema(&self) -> anyhow::Result<i64>;
+}
+
+pub const SQL_ESTABELECIMENTO: &str = r#"
+    SELECT 
+        t.cnpj, t.cnpj_basico, t.cnpj_ordem, t.cnpj_dv, t.matriz_filial,
+        t.nome_fantasia, t.situacao_cadastral, t.data_situacao_cadastral,
+        t.motivo_situacao_cadastral, t.nome_cidade_exterior, t.pais,
+        t.data_inicio_atividades, t.cnae_fiscal, t.cnae_fiscal_secundaria,
+        t.tipo_logradouro, t.logradouro, t.numero, t.complemento,
+        t.bairro, t.cep, t.uf, t.municipio,
+        t.ddd1, t.telefone1, t.ddd2, t.telefone2,
+        t.ddd_fax, t.fax, t.correio_eletronico,
+        t.situacao_especial, t.data_situacao_especial,
+        tmot.descricao as motivo_situacao_cadastral_desc,
+        tmun.descricao as municipio_desc,
+        tc.descricao as cnae_fiscal_desc,
+        tpa.descricao as pais_desc
+    FROM estabelecimento t
+    LEFT JOIN motivo tmot ON tmot.codigo = t.motivo_situacao_cadastral
+    LEFT JOIN municipio tmun ON tmun.codigo = t.municipio
+    LEFT JOIN cnae tc ON tc.codigo = t.cnae_fiscal
+    LEFT JOIN pais tpa ON tpa.codigo = t.pais
+    WHERE t.cnpj = ?1
+"#;
+
+pub const SQL_EMPRESA: &str = r#"
+    SELECT 
+        te.cnpj_basico, te.razao_social, te.natureza_juridica,
+        te.qualificacao_responsavel, te.capital_social, te.porte_empresa,
+        te.ente_federativo_responsavel,
+        tnat.descricao as natureza_juridica_desc,
+        tq.descricao as qualificacao_responsavel_desc
+    FROM empresas te
+    LEFT JOIN natureza_juridica tnat ON tnat.codigo = te.natureza_juridica
+    LEFT JOIN qualificacao_socio tq ON tq.codigo = te.qualificacao_responsavel
+    WHERE te.cnpj_basico = ?1
+"#;
+
+pub const SQL_SOCIOS: &str = r#"
+    SELECT 
+        ts.cnpj, ts.cnpj_basico, ts.identificador_de_socio, ts.nome_socio,
+        ts.cnpj_cpf_socio, ts.qualificacao_socio, ts.data_entrada_sociedade,
+        ts.pais, ts.representante_legal, ts.nome_representante,
+        ts.qualificacao_representante_legal, ts.faixa_etaria,
+        tq.descricao as qualificacao_socio_desc,
+        tq2.descricao as qualificacao_representante_legal_desc,
+        tpa.descricao as pais_desc
+    FROM socios ts
+    LEFT JOIN qualificacao_socio tq ON tq.codigo = ts.qualificacao_socio
+    LEFT JOIN qualificacao_socio tq2 ON tq2.codigo = ts.qualificacao_representante_legal
+    LEFT JOIN pais tpa ON tpa.codigo = ts.pais
+    WHERE ts.cnpj_basico = ?1
+"#;
+
+pub const SQL_SIMPLES: &str = r#"
+    SELECT 
+        cnpj_basico, opcao_simples, data_opcao_simples,
+        data_exclusao_simples, opcao_mei, data_opcao_mei,
+        data_exclusao_mei
+    FROM simples
+    WHERE cnpj_basico = ?1
+"#;
+
+pub const SQL_REFERENCIA: &str = "SELECT referencia, valor FROM _referencia";
+
+pub const SQL_ARQUIVOS_ORIGEM: &str =
+    "SELECT arquivo, tamanho, sha256 FROM _arquivos_origem ORDER BY arquivo";
+
+/// Leitura das colunas de uma linha, comum aos bancos suportados pela API
+pub trait LinhaConsulta {
+    type Erro;
+
+    /// Valor texto da coluna; NULL vira string vazia
+    fn texto(&self, idx: usize) -> Result<String, Self::Erro>;
+    fn texto_opcional(&self, idx: usize) -> Result<Option<String>, Self::Erro>;
+    fn real_opcional(&self, idx: usize) -> Result<Option<f64>, Self::Erro>;
+}
+
+impl LinhaConsulta for rusqlite::Row<'_> {
+    type Erro = rusqlite::Error;
+
+    fn texto(&self, idx: usize) -> rusqlite::Result<String> {
+        Ok(self.get::<_, Option<String>>(idx)?.unwrap_or_default())
+    }
+
+    fn texto_opcional(&self, idx: usize) -> rusqlite::Result<Option<String>> {
+        self.get(idx)
+    }
+
+    fn real_opcional(&self, idx: usize) -> rusqlite::Result<Option<f64>> {
+        self.get(idx)
+    }
+}
+
+pub fn estabelecimento_from_row<R: LinhaConsulta>(row: &R) -> Result<EstabelecimentoCompleto, R::Erro> {
+    Ok(EstabelecimentoCompleto {
+        cnpj: row.texto(0)?,
+        cnpj_basico: row.texto(1)?,
+        cnpj_ordem: row.texto(2)?,
+        cnpj_dv: row.texto(3)?,
+        matriz_filial: row.texto(4)?,
+        nome_fantasia: row.texto(5)?,
+        situacao_cadastral: row.texto(6)?,
+        data_situacao_cadastral: row.texto(7)?,
+        motivo_situacao_cadastral: row.texto(8)?,
+        motivo_situacao_cadastral_desc: row.texto_opcional(31)?,
+        nome_cidade_exterior: row.texto(9)?,
+        pais: row.texto(10)?,
+        pais_desc: row.texto_opcional(34)?,
+        data_inicio_atividades: row.texto(11)?,
+        cnae_fiscal: row.texto(12)?,
+        cnae_fiscal_desc: row.texto_opcional(33)?,
+        cnae_fiscal_secundaria: row.texto(13)?,
+        tipo_logradouro: row.texto(14)?,
+        logradouro: row.texto(15)?,
+        numero: row.texto(16)?,
+        complemento: row.texto(17)?,
+        bairro: row.texto(18)?,
+        cep: row.texto(19)?,
+        uf: row.texto(20)?,
+        municipio: row.texto(21)?,
+        municipio_desc: row.texto_opcional(32)?,
+        ddd1: row.texto(22)?,
+        telefone1: row.texto(23)?,
+        ddd2: row.texto(24)?,
+        telefone2: row.texto(25)?,
+        ddd_fax: row.texto(26)?,
+        fax: row.texto(27)?,
+        correio_eletronico: row.texto(28)?,
+        situacao_especial: row.texto(29)?,
+        data_situacao_especial: row.texto(30)?,
+    })
+}
+
+pub fn empresa_from_row<R: LinhaConsulta>(row: &R) -> Result<EmpresaCompleta, R::Erro> {
+    Ok(EmpresaCompleta {
+        cnpj_basico: row.texto(0)?,
+        razao_social: row.texto(1)?,
+        natureza_juridica: row.texto(2)?,
+        natureza_juridica_desc: row.texto_opcional(7)?,
+        qualificacao_responsavel: row.texto(3)?,
+        qualificacao_responsavel_desc: row.texto_opcional(8)?,
+        capital_social: row.real_opcional(4)?,
+        porte_empresa: row.texto(5)?,
+        ente_federativo_responsavel: row.texto(6)?,
+    })
+}
+
+pub fn socio_from_row<R: LinhaConsulta>(row: &R) -> Result<SocioCompleto, R::Erro> {
+    Ok(SocioCompleto {
+        cnpj: row.texto(0)?,
+        cnpj_basico: row.texto(1)?,
+        identificador_de_socio: row.texto(2)?,
+        nome_socio: row.texto(3)?,
+        cnpj_cpf_socio: row.texto(4)?,
+        qualificacao_socio: row.texto(5)?,
+        qualificacao_socio_desc: row.texto_opcional(12)?,
+        data_entrada_sociedade: row.texto(6)?,
+        pais: row.texto(7)?,
+        pais_desc: row.texto_opcional(14)?,
+        representante_legal: row.texto(8)?,
+        nome_representante: row.texto(9)?,
+        qualificacao_representante_legal: row.texto(10)?,
+        qualificacao_representante_legal_desc: row.texto_opcional(13)?,
+        faixa_etaria: row.texto(11)?,
+    })
+}
+
+pub fn simples_from_row<R: LinhaConsulta>(row: &R) -> Result<SimplesDados, R::Erro> {
+    Ok(SimplesDados {
+        cnpj_basico: row.texto(0)?,
+        opcao_simples: row.texto(1)?,
+        data_opcao_simples: row.texto(2)?,
+        data_exclusao_simples: row.texto(3)?,
+        opcao_mei: row.texto(4)?,
+        data_opcao_mei: row.texto(5)?,
+        data_exclusao_mei: row.texto(6)?,
+    })
+}
+
+impl FonteConsulta for Connection {
+    fn buscar_estabelecimento(&self, cnpj: &str) -> anyhow::Result<Option<EstabelecimentoCompleto>> {
+        let mut stmt = self.prepare_cached(SQL_ESTABELECIMENTO)?;
+        Ok(stmt.query_row(params![cnpj], |row| estabelecimento_from_row(row)).optional()?)
+    }
+
+    fn buscar_empresa(&self, cnpj_basico: &str) -> anyhow::Result<Option<EmpresaCompleta>> {
+        let mut stmt = self.prepare_cached(SQL_EMPRESA)?;
+        Ok(stmt.query_row(params![cnpj_basico], |row| empresa_from_row(row)).optional()?)
+    }
+
+    fn buscar_socios(&self, cnpj_basico: &str) -> anyhow::Result<Vec<SocioCompleto>> {
+        let mut stmt = self.prepare_cached(SQL_SOCIOS)?;
+        let socios = stmt.query_map(params![cnpj_basico], |row| socio_from_row(row))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(socios)
+    }
+
+    fn buscar_simples(&self, cnpj_basico: &str) -> anyhow::Result<Option<SimplesDados>> {
+        let mut stmt = self.prepare_cached(SQL_SIMPLES)?;
+        Ok(stmt.query_row(params![cnpj_basico], |row| simples_from_row(row)).optional()?)
+    }
+
+    fn ping(&self) -> anyhow::Result<()> {
+        self.query_row("SELECT 1", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    fn ler_referencia(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let mut stmt = self.prepare_cached(SQL_REFERENCIA)?;
+        let pares = stmt.query_map([], |row| Ok((row.texto(0)?, row.texto(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(pares)
+    }
+
+    fn ler_arquivos_origem(&self) -> anyhow::Result<Vec<ArquivoOrigem>> {
+        let mut stmt = self.prepare_cached(SQL_ARQUIVOS_ORIGEM)?;
+        let arquivos = stmt.query_map([], |row| {
+            Ok(ArquivoOrigem { arquivo: row.texto(0)?, tamanho: row.get(1)?, sha256: row.texto(2)? })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(arquivos)
+    }
+
+    fn versao_esquema(&self) -> anyhow::Result<i64> {
+        migrations::versao_atual(self)
+    }
+}
+
+/// Abre um banco somente para leitura: arquivos `.duckdb` usam DuckDB, os demais SQLite.
+/// Não altera o arquivo, então serve para consultar um banco que outro processo está servindo
+pub fn abrir_fonte(db_path: &str) -> anyhow::Result<Box<dyn FonteConsulta>> {
+    if database::is_duckdb_path(db_path) {
+        return database_duckdb::abrir_consulta(db_path);
+    }
+
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Falha ao abrir banco de dados: {}", db_path))?;
+    migrations::verificar_versao(&conn, db_path)?;
+    conn.execute("PRAGMA cache_size = -64000", [])?;
+
+    Ok(Box::new(conn))
+}
+
+/// Abre um banco para o servidor: como `abrir_fonte`, mas deixa o SQLite em modo WAL para
+/// que as leituras da API não esperem uma escrita
+pub fn abrir_fonte_servidor(db_path: &str) -> anyhow::Result<Box<dyn FonteConsulta>> {
+    if database::is_duckdb_path(db_path) {
+        return database_duckdb::abrir_consulta(db_path);
+    }
+
+    let conn = Connection::open(db_path)
+        .with_context(|| format!("Falha ao abrir banco de dados: {}", db_path))?;
+    migrations::verificar_versao(&conn, db_path)?;
+    
+    // Configura SQLite para melhor performance em multi-thread
+    // PRAGMA journal_mode retorna um valor, então usamos query_row
+    let _: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+    conn.execute("PRAGMA synchronous = NORMAL", [])?;
+    conn.execute("PRAGMA cache_size = -64000", [])?;
+    conn.execute("PRAGMA foreign_keys = ON", [])?;
+    
+    Ok(Box::new(conn))
+}
+
+/// As quatro consultas de um CNPJ (já limpo com `cnpj::limpar`), feitas com a mesma conexão. `None` se
+/// o estabelecimento não existir
+pub fn buscar_cnpj(db: &dyn FonteConsulta, cnpj: &str) -> anyhow::Result<Option<CnpjResponse>> {
+    // Busca dados do estabelecimento e empresa (query principal)
+    let Some(estabelecimento) = db.buscar_estabelecimento(cnpj).context("Erro ao buscar estabelecimento")? else {
+        return Ok(None);
+    };
+    
+    let empresa = db.buscar_empresa(&estabelecimento.cnpj_basico).context("Erro ao buscar empresa")?;
+    
+    // Busca sócios pelo CNPJ básico
+    let socios = db.buscar_socios(&estabelecimento.cnpj_basico).context("Erro ao buscar sócios")?;
+    
+    // Busca dados do Simples
+    let simples = db.buscar_simples(&estabelecimento.cnpj_basico).context("Erro ao buscar Simples")?;
+    
+    Ok(Some(CnpjResponse {
+        cnpj: cnpj.to_string(),
+        estabelecimento,
+        empresa,
+        socios,
+        simples,
+    }))
+}
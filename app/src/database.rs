@@ -1,5 +1,236 @@
 use anyhow::{Context, Result};
 use rusqlite::{Connection, params, Transaction};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use crate::database_duckdb;
+use crate::database_postgres::PostgresDatabase;
+use crate::migrations;
+use crate::ui;
+use crate::utils;
+
+/// Tabelas de código (código → descrição)
+pub const TABELAS_CODIGO: &[&str] = &[
+    "cnae", "motivo", "municipio", "natureza_juridica", "pais", "qualificacao_socio",
+];
+
+/// Tabelas principais na forma final, depois de `finalize_processing`
+pub const TABELAS_PRINCIPAIS: &[&str] = &["empresas", "estabelecimento", "socios", "simples"];
+
+pub const COLUNAS_EMPRESAS: &[&str] = &[
+    "cnpj_basico", "razao_social", "natureza_juridica",
+    "qualificacao_responsavel", "capital_social_str",
+    "porte_empresa", "ente_federativo_responsavel",
+];
+
+pub const COLUNAS_ESTABELECIMENTO: &[&str] = &[
+    "cnpj_basico", "cnpj_ordem", "cnpj_dv", "matriz_filial",
+    "nome_fantasia", "situacao_cadastral", "data_situacao_cadastral",
+    "motivo_situacao_cadastral", "nome_cidade_exterior", "pais",
+    "data_inicio_atividades", "cnae_fiscal", "cnae_fiscal_secundaria",
+    "tipo_logradouro", "logradouro", "numero", "complemento",
+    "bairro", "cep", "uf", "municipio", "ddd1", "telefone1",
+    "ddd2", "telefone2", "ddd_fax", "fax", "correio_eletronico",
+    "situacao_especial", "data_situacao_especial",
+];
+
+pub const COLUNAS_SOCIOS: &[&str] = &[
+    "cnpj_basico", "identificador_de_socio", "nome_socio",
+    "cnpj_cpf_socio", "qualificacao_socio", "data_entrada_sociedade",
+    "pais", "representante_legal", "nome_representante",
+    "qualificacao_representante_legal", "faixa_etaria",
+];
+
+pub const COLUNAS_SIMPLES: &[&str] = &[
+    "cnpj_basico", "opcao_simples", "data_opcao_simples",
+    "data_exclusao_simples", "opcao_mei", "data_opcao_mei",
+    "data_exclusao_mei",
+];
+
+/// Linhas carregadas de cada arquivo da Receita, usadas pelo relatório de qualidade
+pub const COLUNAS_ARQUIVOS: &[&str] = &["arquivo", "tabela", "linhas"];
+
+/// Metadados do processamento em `_referencia` (chave → valor)
+pub const COLUNAS_REFERENCIA: &[&str] = &["referencia", "valor"];
+
+/// ZIPs de origem do banco, com tamanho e SHA-256
+pub const COLUNAS_ARQUIVOS_ORIGEM: &[&str] = &["arquivo", "tamanho", "sha256"];
+
+/// Tabelas de controle do processamento, com tipos aceitos pelos três bancos de destino
+pub const SQL_TABELAS_CONTROLE: &str = r#"
+    CREATE TABLE IF NOT EXISTS _arquivos (arquivo TEXT, tabela TEXT, linhas BIGINT);
+    CREATE TABLE IF NOT EXISTS _qualidade (gerado_em TEXT, relatorio TEXT);
+"#;
+
+/// Tabela dos ZIPs de origem, criada à parte porque entrou em uma versão posterior do esquema
+pub const SQL_TABELA_ORIGEM: &str =
+    "CREATE TABLE IF NOT EXISTS _arquivos_origem (arquivo TEXT, tamanho BIGINT, sha256 TEXT)";
+
+/// Índices criados logo após a carga, antes de montar a tabela socios
+pub const INDICES_PRINCIPAIS: &[(&str, &str)] = &[
+    ("empresas", "cnpj_basico"),
+    ("empresas", "razao_social"),
+    ("estabelecimento", "cnpj_basico"),
+    ("estabelecimento", "cnpj"),
+    ("estabelecimento", "nome_fantasia"),
+    ("socios_original", "cnpj_basico"),
+];
+
+/// Índices criados depois da tabela socios
+pub const INDICES_SOCIOS: &[(&str, &str)] = &[
+    ("socios", "cnpj"),
+    ("socios", "cnpj_basico"),
+    ("socios", "cnpj_cpf_socio"),
+    ("socios", "nome_socio"),
+    ("socios", "representante_legal"),
+    ("socios", "nome_representante"),
+    ("simples", "cnpj_basico"),
+];
+
+/// Operações de escrita usadas pelo processamento, independentes do banco de destino
+pub trait DatabaseWriter {
+    /// Cria as tabelas de código e as tabelas principais
+    fn create_tables(&mut self) -> Result<()>;
+
+    /// Grava os registros de uma tabela de código, substituindo códigos repetidos
+    fn insert_codigo_descricao_batch(&mut self, table: &str, rows: &[(String, String)]) -> Result<()>;
+
+    /// Grava um lote de linhas em uma tabela principal
+    fn insert_rows(&mut self, table: &str, columns: &[&str], rows: &[Vec<String>]) -> Result<()>;
+
+    /// Carrega um arquivo CSV da Receita com o leitor do próprio banco e retorna as linhas
+    /// inseridas. `None` indica que o destino não tem leitor nativo e as linhas devem
+    /// chegar por `insert_rows`
+    fn load_csv_native(&mut self, _table: &str, _columns: &[&str], _file: &Path) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    fn create_index(&mut self, table: &str, column: &str) -> Result<()>;
+
+    /// Ajusta os dados carregados e cria índices, a tabela socios e a tabela _referencia
+    fn finalize_processing(&mut self, data_referencia: &str) -> Result<()>;
+
+    fn count_rows(&mut self, table: &str) -> Result<i64>;
+
+    /// Fase final após a carga: estatísticas do planejador e, quando pedido, compactação.
+    /// Destinos que já fazem isso em `finalize_processing` não precisam implementar
+    fn optimize(&mut self, _vacuum: bool) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Banco de destino do processamento, escolhido por `process --target`
+#[derive(Debug, Clone)]
+pub enum Target {
+    Sqlite(PathBuf),
+    Postgres(String),
+    Duckdb(PathBuf),
+}
+
+/// Arquivos `.duckdb` (ou `duckdb://caminho`) são tratados como bancos DuckDB
+pub fn is_duckdb_path(path: &str) -> bool {
+    path.starts_with("duckdb://") || path.to_lowercase().ends_with(".duckdb")
+}
+
+impl Target {
+    /// URLs `postgres://` e `postgresql://` usam PostgreSQL, caminhos `.duckdb` ou
+    /// `duckdb://` usam DuckDB e qualquer outro valor é um arquivo SQLite
+    pub fn parse(target: &str) -> Self {
+        if target.starts_with("postgres://") || target.starts_with("postgresql://") {
+            Target::Postgres(target.to_string())
+        } else if is_duckdb_path(target) {
+            Target::Duckdb(PathBuf::from(target.trim_start_matches("duckdb://")))
+        } else {
+            Target::Sqlite(PathBuf::from(target.trim_start_matches("sqlite://")))
+        }
+    }
+
+    /// Falha se o destino já contém um banco processado
+    pub fn ensure_empty(&self) -> Result<()> {
+        match self {
+            Target::Sqlite(path) | Target::Duckdb(path) => {
+                if path.exists() {
+                    anyhow::bail!("Banco de dados já existe: {:?}", path);
+                }
+                if let Some(parent) = path.parent().and_then(|p| p.to_str()).filter(|p| !p.is_empty()) {
+                    utils::ensure_dir(parent)?;
+                }
+                Ok(())
+            }
+            Target::Postgres(url) => PostgresDatabase::ensure_empty(url),
+        }
+    }
+
+    /// Tamanho do arquivo de destino em bytes (`None` para bancos em servidor)
+    pub fn file_size(&self) -> Option<u64> {
+        match self {
+            Target::Sqlite(path) | Target::Duckdb(path) => {
+                std::fs::metadata(path).ok().map(|m| m.len())
+            }
+            Target::Postgres(_) => None,
+        }
+    }
+
+    /// Apaga o arquivo de destino e os auxiliares (WAL, journal). No PostgreSQL não há
+    /// arquivo: apaga as tabelas criadas no servidor
+    pub fn remove_files(&self) {
+        let (path, sufixos): (&PathBuf, &[&str]) = match self {
+            Target::Sqlite(path) => (path, &["", "-wal", "-shm", "-journal"]),
+            Target::Duckdb(path) => (path, &["", ".wal"]),
+            Target::Postgres(url) => {
+                if let Err(e) = PostgresDatabase::remover_tabelas(url) {
+                    ui::print_warning(&format!("Falha ao apagar as tabelas do processamento incompleto: {:#}", e));
+                }
+                return;
+            }
+        };
+        for sufixo in sufixos {
+            let mut nome = path.as_os_str().to_owned();
+            nome.push(sufixo);
+            let _ = std::fs::remove_file(PathBuf::from(nome));
+        }
+    }
+
+    pub fn open(&self) -> Result<Box<dyn DatabaseWriter>> {
+        match self {
+            Target::Sqlite(path) => {
+                let path = path.to_str()
+                    .with_context(|| format!("Caminho inválido: {:?}", path))?;
+                let db = Database::new(path)?;
+                db.configure_bulk_load()?;
+                Ok(Box::new(db))
+            }
+            Target::Postgres(url) => Ok(Box::new(PostgresDatabase::connect(url)?)),
+            Target::Duckdb(path) => {
+                let path = path.to_str()
+                    .with_context(|| format!("Caminho inválido: {:?}", path))?;
+                database_duckdb::abrir_escrita(path)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Sqlite(path) => write!(f, "SQLite ({})", path.display()),
+            Target::Postgres(url) => write!(f, "PostgreSQL ({})", redact_url(url)),
+            Target::Duckdb(path) => write!(f, "DuckDB ({})", path.display()),
+        }
+    }
+}
+
+/// Esconde a senha de uma URL de conexão antes de exibi-la
+fn redact_url(url: &str) -> String {
+    if let (Some(scheme_end), Some(at)) = (url.find("://"), url.rfind('@')) {
+        let start = scheme_end + 3;
+        if at > start {
+            if let Some(colon) = url[start..at].find(':') {
+                return format!("{}:***{}", &url[..start + colon], &url[at..]);
+            }
+        }
+    }
+    url.to_string()
+}
 
 pub struct Database {
     conn: Connection,
@@ -13,7 +244,39 @@ impl Database {
         Ok(Self { conn })
     }
 
-    pub fn create_tables(&self) -> Result<()> {
+    /// Configura a conexão para a carga em massa. Sem journal e sem sync um processamento
+    /// interrompido deixa o arquivo inutilizável, o que é aceitável porque o destino
+    /// sempre é um arquivo novo
+    pub fn configure_bulk_load(&self) -> Result<()> {
+        // PRAGMA journal_mode retorna um valor, então usamos query_row
+        let _: String = self.conn.query_row("PRAGMA journal_mode = OFF", [], |row| row.get(0))?;
+        self.conn.execute("PRAGMA synchronous = OFF", [])?;
+        self.conn.execute("PRAGMA cache_size = -1000000", [])?; // ~1 GB
+        self.conn.execute("PRAGMA temp_store = MEMORY", [])?;
+        Ok(())
+    }
+
+    pub fn begin_transaction(&mut self) -> Result<Transaction<'_>> {
+        Ok(self.conn.transaction()?)
+    }
+
+    pub fn execute(&self, sql: &str) -> Result<()> {
+        self.conn.execute(sql, [])?;
+        Ok(())
+    }
+
+    pub fn execute_with_params(&self, sql: &str, params: &[&dyn rusqlite::ToSql]) -> Result<()> {
+        self.conn.execute(sql, params)?;
+        Ok(())
+    }
+
+    pub fn get_connection(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+impl DatabaseWriter for Database {
+    fn create_tables(&mut self) -> Result<()> {
         // Tabelas de referência
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS cnae (codigo TEXT PRIMARY KEY, descricao TEXT)",
@@ -133,42 +396,62 @@ impl Database {
             [],
         )?;
 
+        // Tabelas de controle
+        self.conn.execute_batch(SQL_TABELAS_CONTROLE)?;
+        self.conn.execute_batch(SQL_TABELA_ORIGEM)?;
+
         Ok(())
     }
 
-    pub fn insert_codigo_descricao(&self, table: &str, codigo: &str, descricao: &str) -> Result<()> {
+    fn insert_codigo_descricao_batch(&mut self, table: &str, rows: &[(String, String)]) -> Result<()> {
         let sql = format!("INSERT OR REPLACE INTO {} (codigo, descricao) VALUES (?1, ?2)", table);
-        self.conn.execute(&sql, params![codigo, descricao])?;
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(&sql)?;
+            for (codigo, descricao) in rows {
+                stmt.execute(params![codigo, descricao])?;
+            }
+        }
+        tx.commit()?;
         Ok(())
     }
 
-    pub fn create_index(&self, table: &str, column: &str) -> Result<()> {
-        let index_name = format!("idx_{}_{}", table, column);
-        let sql = format!("CREATE INDEX IF NOT EXISTS {} ON {}({})", index_name, table, column);
-        self.conn.execute(&sql, [])?;
-        Ok(())
-    }
+    fn insert_rows(&mut self, table: &str, columns: &[&str], rows: &[Vec<String>]) -> Result<()> {
+        let placeholders: Vec<String> = (0..columns.len())
+            .map(|i| format!("?{}", i + 1))
+            .collect();
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            table,
+            columns.join(", "),
+            placeholders.join(", ")
+        );
 
-    pub fn begin_transaction(&mut self) -> Result<Transaction> {
-        Ok(self.conn.transaction()?)
-    }
-
-    pub fn execute(&self, sql: &str) -> Result<()> {
-        self.conn.execute(sql, [])?;
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(&sql)?;
+            for row in rows {
+                let param_refs: Vec<&dyn rusqlite::ToSql> = row.iter().map(|p| p as &dyn rusqlite::ToSql).collect();
+                stmt.execute(&param_refs[..])?;
+            }
+        }
+        tx.commit()?;
         Ok(())
     }
 
-    pub fn execute_with_params(&self, sql: &str, params: &[&dyn rusqlite::ToSql]) -> Result<()> {
-        self.conn.execute(sql, params)?;
+    fn create_index(&mut self, table: &str, column: &str) -> Result<()> {
+        let index_name = format!("idx_{}_{}", table, column);
+        let sql = format!("CREATE INDEX IF NOT EXISTS {} ON {}({})", index_name, table, column);
+        self.conn.execute(&sql, [])?;
         Ok(())
     }
 
-    pub fn finalize_processing(&self, data_referencia: &str) -> Result<()> {
+    fn finalize_processing(&mut self, data_referencia: &str) -> Result<()> {
         // Ajusta capital social
         self.conn.execute(
             "ALTER TABLE empresas ADD COLUMN capital_social REAL",
             [],
-        ).ok(); // Ignora se já existe
+        )?;
         
         self.conn.execute(
             "UPDATE empresas SET capital_social = CAST(REPLACE(capital_social_str, ',', '.') AS REAL)",
@@ -178,13 +461,13 @@ impl Database {
         self.conn.execute(
             "ALTER TABLE empresas DROP COLUMN capital_social_str",
             [],
-        ).ok(); // Ignora se não existe
+        )?;
 
         // Cria campo CNPJ completo
         self.conn.execute(
             "ALTER TABLE estabelecimento ADD COLUMN cnpj TEXT",
             [],
-        ).ok();
+        )?;
         
         self.conn.execute(
             "UPDATE estabelecimento SET cnpj = cnpj_basico || cnpj_ordem || cnpj_dv",
@@ -192,21 +475,18 @@ impl Database {
         )?;
 
         // Cria índices principais
-        self.create_index("empresas", "cnpj_basico")?;
-        self.create_index("empresas", "razao_social")?;
-        self.create_index("estabelecimento", "cnpj_basico")?;
-        self.create_index("estabelecimento", "cnpj")?;
-        self.create_index("estabelecimento", "nome_fantasia")?;
-        self.create_index("socios_original", "cnpj_basico")?;
-
-        // Cria tabela socios apenas com matrizes
+        for (table, column) in INDICES_PRINCIPAIS {
+            self.create_index(table, column)?;
+        }
+
+        // Cria tabela socios; cnpj fica nulo quando a empresa não tem matriz na base
         self.conn.execute(
             r#"
             CREATE TABLE IF NOT EXISTS socios AS 
             SELECT te.cnpj as cnpj, ts.*
             FROM socios_original ts
-            LEFT JOIN estabelecimento te ON te.cnpj_basico = ts.cnpj_basico
-            WHERE te.matriz_filial = '1'
+            LEFT JOIN estabelecimento te
+                ON te.cnpj_basico = ts.cnpj_basico AND te.matriz_filial = '1'
             "#,
             [],
         )?;
@@ -214,12 +494,9 @@ impl Database {
         self.conn.execute("DROP TABLE IF EXISTS socios_original", [])?;
 
         // Índices na tabela socios
-        self.create_index("socios", "cnpj")?;
-        self.create_index("socios", "cnpj_cpf_socio")?;
-        self.create_index("socios", "nome_socio")?;
-        self.create_index("socios", "representante_legal")?;
-        self.create_index("socios", "nome_representante")?;
-        self.create_index("simples", "cnpj_basico")?;
+        for (table, column) in INDICES_SOCIOS {
+            self.create_index(table, column)?;
+        }
 
         // Tabela de referência
         self.conn.execute(
@@ -238,6 +515,12 @@ impl Database {
             |row| row.get(0),
         )?;
 
+        let socios_orfaos: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM socios WHERE cnpj IS NULL",
+            [],
+            |row| row.get(0),
+        )?;
+
         self.conn.execute(
             "INSERT INTO _referencia (referencia, valor) VALUES ('CNPJ', ?1)",
             params![data_referencia],
@@ -248,11 +531,36 @@ impl Database {
             params![qtde_cnpjs.to_string()],
         )?;
 
+        self.conn.execute(
+            "INSERT INTO _referencia (referencia, valor) VALUES ('socios_orfaos', ?1)",
+            params![socios_orfaos.to_string()],
+        )?;
+
+        // O banco recém-criado já tem o layout da última versão do esquema
+        self.conn.execute_batch(&migrations::sql_registro_versao_atual())?;
+
         Ok(())
     }
 
-    pub fn get_connection(&self) -> &Connection {
-        &self.conn
+    fn count_rows(&mut self, table: &str) -> Result<i64> {
+        let count = self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM {}", table),
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(count)
     }
-}
 
+    fn optimize(&mut self, vacuum: bool) -> Result<()> {
+        self.conn.execute("ANALYZE", [])?;
+
+        if vacuum {
+            self.conn.execute("VACUUM", [])?;
+        }
+
+        // Volta para as configurações de leitura usadas pelo servidor da API
+        let _: String = self.conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+        self.conn.execute("PRAGMA synchronous = NORMAL", [])?;
+        Ok(())
+    }
+}
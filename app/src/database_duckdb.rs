@@ -0,0 +1,385 @@
+//! Destino DuckDB para o processamento e fonte DuckDB para a API.
+//!
+//! Só funciona quando o binário é compilado com `--features duckdb`; sem a feature
+//! as funções públicas retornam um erro explicando como habilitá-la.
+
+pub use imp::{abrir_consulta, abrir_escrita, create_cnae_secundaria_table};
+
+#[cfg(not(feature = "duckdb"))]
+mod imp {
+    use anyhow::Result;
+    use crate::consultas::FonteConsulta;
+    use crate::database::DatabaseWriter;
+
+    const SEM_SUPORTE: &str =
+        "Suporte a DuckDB não habilitado. Compile com: cargo build --release --features duckdb";
+
+    pub fn abrir_escrita(_path: &str) -> Result<Box<dyn DatabaseWriter>> {
+        anyhow::bail!(SEM_SUPORTE)
+    }
+
+    pub fn abrir_consulta(_path: &str) -> Result<Box<dyn FonteConsulta>> {
+        anyhow::bail!(SEM_SUPORTE)
+    }
+
+    pub fn create_cnae_secundaria_table(_path: &str) -> Result<()> {
+        anyhow::bail!(SEM_SUPORTE)
+    }
+}
+
+#[cfg(feature = "duckdb")]
+mod imp {
+    use anyhow::{Context, Result};
+    use duckdb::{params, AccessMode, Config, Connection, OptionalExt};
+    use std::path::Path;
+    use crate::consultas::{
+        self, ArquivoOrigem, EmpresaCompleta, EstabelecimentoCompleto, FonteConsulta, LinhaConsulta,
+        SimplesDados, SocioCompleto,
+    };
+    use crate::database::{
+        DatabaseWriter, COLUNAS_EMPRESAS, COLUNAS_ESTABELECIMENTO, COLUNAS_SIMPLES, COLUNAS_SOCIOS,
+        INDICES_PRINCIPAIS, INDICES_SOCIOS, SQL_TABELAS_CONTROLE, SQL_TABELA_ORIGEM,
+        TABELAS_CODIGO,
+    };
+    use crate::migrations;
+    use crate::ui;
+
+    const SQL_CNAE_SECUNDARIA: &str = r#"
+        DROP TABLE IF EXISTS cnae_secundaria;
+        CREATE TABLE cnae_secundaria AS
+        SELECT cnpj, cnae_fiscal_secundaria
+        FROM (
+            SELECT cnpj, TRIM(UNNEST(string_split(cnae_fiscal_secundaria, ','))) AS cnae_fiscal_secundaria
+            FROM estabelecimento
+            WHERE cnae_fiscal_secundaria <> ''
+        )
+        WHERE cnae_fiscal_secundaria <> '';
+        CREATE INDEX idx_cnae_secundaria_cnpj ON cnae_secundaria(cnpj);
+        CREATE INDEX idx_cnae_secundaria_cnae ON cnae_secundaria(cnae_fiscal_secundaria);
+    "#;
+
+    pub struct DuckDbDatabase {
+        conn: Connection,
+    }
+
+    pub fn abrir_escrita(path: &str) -> Result<Box<dyn DatabaseWriter>> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Falha ao criar/abrir banco DuckDB: {}", path))?;
+        Ok(Box::new(DuckDbDatabase { conn }))
+    }
+
+    pub fn abrir_consulta(path: &str) -> Result<Box<dyn FonteConsulta>> {
+        let config = Config::default().access_mode(AccessMode::ReadOnly)?;
+        let conn = Connection::open_with_flags(path, config)
+            .with_context(|| format!("Falha ao abrir banco DuckDB: {}", path))?;
+        migrations::conferir_versao(conn.versao_esquema()?, path)?;
+        Ok(Box::new(conn))
+    }
+
+    fn tabela_existe(conn: &Connection, tabela: &str) -> Result<bool> {
+        let existe: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM information_schema.tables WHERE table_name = ?",
+            params![tabela],
+            |row| row.get(0),
+        )?;
+        Ok(existe > 0)
+    }
+
+    pub fn create_cnae_secundaria_table(path: &str) -> Result<()> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Falha ao abrir banco DuckDB: {}", path))?;
+        conn.execute_batch(SQL_CNAE_SECUNDARIA)?;
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM cnae_secundaria", [], |row| row.get(0))?;
+        ui::print_info(&format!("Total de registros inseridos: {}", count));
+        Ok(())
+    }
+
+    impl DuckDbDatabase {
+        fn create_main_table(&mut self, table: &str, columns: &[&str]) -> Result<()> {
+            let columns_sql: Vec<String> = columns.iter().map(|c| format!("{} VARCHAR", c)).collect();
+            self.conn.execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {} ({})",
+                table,
+                columns_sql.join(", ")
+            ))?;
+            Ok(())
+        }
+    }
+
+    impl DatabaseWriter for DuckDbDatabase {
+        fn create_tables(&mut self) -> Result<()> {
+            // Tabelas de referência
+            for table in TABELAS_CODIGO {
+                self.conn.execute_batch(&format!(
+                    "CREATE TABLE IF NOT EXISTS {} (codigo VARCHAR PRIMARY KEY, descricao VARCHAR)",
+                    table
+                ))?;
+            }
+
+            // Tabelas principais
+            self.create_main_table("empresas", COLUNAS_EMPRESAS)?;
+            self.create_main_table("estabelecimento", COLUNAS_ESTABELECIMENTO)?;
+            self.create_main_table("socios_original", COLUNAS_SOCIOS)?;
+            self.create_main_table("simples", COLUNAS_SIMPLES)?;
+
+            // Tabelas de controle
+            self.conn.execute_batch(SQL_TABELAS_CONTROLE)?;
+            self.conn.execute_batch(SQL_TABELA_ORIGEM)?;
+
+            Ok(())
+        }
+
+        fn insert_codigo_descricao_batch(&mut self, table: &str, rows: &[(String, String)]) -> Result<()> {
+            let sql = format!("INSERT OR REPLACE INTO {} (codigo, descricao) VALUES (?, ?)", table);
+            let tx = self.conn.transaction()?;
+            {
+                let mut stmt = tx.prepare(&sql)?;
+                for (codigo, descricao) in rows {
+                    stmt.execute(params![codigo, descricao])?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        }
+
+        fn insert_rows(&mut self, table: &str, columns: &[&str], rows: &[Vec<String>]) -> Result<()> {
+            let mut appender = self.conn.appender_with_columns(table, columns)?;
+            for row in rows {
+                appender.append_row(duckdb::appender_params_from_iter(row))?;
+            }
+            appender.flush()?;
+            Ok(())
+        }
+
+        fn load_csv_native(&mut self, table: &str, columns: &[&str], file: &Path) -> Result<Option<u64>> {
+            let arquivo = format!("'{}'", file.to_string_lossy().replace('\'', "''"));
+            let colunas: Vec<String> = columns.iter().map(|c| format!("'{}': 'VARCHAR'", c)).collect();
+
+            // Os arquivos da Receita vêm em Latin-1, sem cabeçalho e com todos os campos entre aspas;
+            // allow_quoted_nulls = false mantém "" como string vazia, igual ao SQLite
+            let sql = format!(
+                r#"
+                INSERT INTO {} ({})
+                SELECT * FROM read_csv(
+                    {},
+                    delim = ';',
+                    quote = '"',
+                    escape = '"',
+                    header = false,
+                    encoding = 'latin-1',
+                    allow_quoted_nulls = false,
+                    null_padding = true,
+                    columns = {{{}}}
+                )
+                "#,
+                table,
+                columns.join(", "),
+                arquivo,
+                colunas.join(", ")
+            );
+            let linhas = self.conn.execute(&sql, [])
+                .with_context(|| format!("Falha ao ler CSV {:?} com o DuckDB", file))?;
+            Ok(Some(linhas as u64))
+        }
+
+        fn create_index(&mut self, table: &str, column: &str) -> Result<()> {
+            let index_name = format!("idx_{}_{}", table, column);
+            self.conn.execute_batch(&format!(
+                "CREATE INDEX IF NOT EXISTS {} ON {}({})",
+                index_name, table, column
+            ))?;
+            Ok(())
+        }
+
+        fn finalize_processing(&mut self, data_referencia: &str) -> Result<()> {
+            // Ajusta capital social e cria o CNPJ completo antes dos índices,
+            // porque o DuckDB não altera colunas de tabelas indexadas
+            self.conn.execute_batch(
+                r#"
+                ALTER TABLE empresas ADD COLUMN IF NOT EXISTS capital_social DOUBLE;
+                UPDATE empresas SET capital_social = TRY_CAST(REPLACE(capital_social_str, ',', '.') AS DOUBLE);
+                ALTER TABLE empresas DROP COLUMN IF EXISTS capital_social_str;
+                ALTER TABLE estabelecimento ADD COLUMN IF NOT EXISTS cnpj VARCHAR;
+                UPDATE estabelecimento SET cnpj = cnpj_basico || cnpj_ordem || cnpj_dv;
+                "#,
+            )?;
+
+            // Cria índices principais
+            for (table, column) in INDICES_PRINCIPAIS {
+                self.create_index(table, column)?;
+            }
+
+            // Cria tabela socios; cnpj fica nulo quando a empresa não tem matriz na base
+            self.conn.execute_batch(
+                r#"
+                CREATE TABLE IF NOT EXISTS socios AS
+                SELECT te.cnpj as cnpj, ts.*
+                FROM socios_original ts
+                LEFT JOIN estabelecimento te
+                    ON te.cnpj_basico = ts.cnpj_basico AND te.matriz_filial = '1';
+                DROP TABLE IF EXISTS socios_original;
+                "#,
+            )?;
+
+            // Índices na tabela socios
+            for (table, column) in INDICES_SOCIOS {
+                self.create_index(table, column)?;
+            }
+
+            // No DuckDB a normalização dos CNAEs secundários é uma única consulta
+            self.conn.execute_batch(SQL_CNAE_SECUNDARIA)?;
+
+            // Tabela de referência
+            self.conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS _referencia (referencia VARCHAR, valor VARCHAR)",
+            )?;
+
+            let qtde_cnpjs = self.count_rows("estabelecimento")?;
+            let socios_orfaos: i64 = self.conn.query_row(
+                "SELECT COUNT(*) FROM socios WHERE cnpj IS NULL",
+                [],
+                |row| row.get(0),
+            )?;
+            self.conn.execute(
+                "INSERT INTO _referencia (referencia, valor) VALUES ('CNPJ', ?)",
+                params![data_referencia],
+            )?;
+            self.conn.execute(
+                "INSERT INTO _referencia (referencia, valor) VALUES ('cnpj_qtde', ?)",
+                params![qtde_cnpjs.to_string()],
+            )?;
+            self.conn.execute(
+                "INSERT INTO _referencia (referencia, valor) VALUES ('socios_orfaos', ?)",
+                params![socios_orfaos.to_string()],
+            )?;
+
+            self.conn.execute_batch(&migrations::sql_registro_versao_atual())?;
+
+            Ok(())
+        }
+
+        fn count_rows(&mut self, table: &str) -> Result<i64> {
+            let count = self.conn.query_row(
+                &format!("SELECT COUNT(*) FROM {}", table),
+                [],
+                |row| row.get(0),
+            )?;
+            Ok(count)
+        }
+    }
+
+    impl LinhaConsulta for duckdb::Row<'_> {
+        type Erro = duckdb::Error;
+
+        fn texto(&self, idx: usize) -> duckdb::Result<String> {
+            Ok(self.get::<_, Option<String>>(idx)?.unwrap_or_default())
+        }
+
+        fn texto_opcional(&self, idx: usize) -> duckdb::Result<Option<String>> {
+            self.get(idx)
+        }
+
+        fn real_opcional(&self, idx: usize) -> duckdb::Result<Option<f64>> {
+            self.get(idx)
+        }
+    }
+
+    /// As consultas da API usam `?1`; o DuckDB espera `$1`
+    fn sql_duckdb(sql: &str) -> String {
+        sql.replace("?1", "$1")
+    }
+
+    impl FonteConsulta for Connection {
+        fn buscar_estabelecimento(&self, cnpj: &str) -> Result<Option<EstabelecimentoCompleto>> {
+            let mut stmt = self.prepare_cached(&sql_duckdb(consultas::SQL_ESTABELECIMENTO))?;
+            Ok(stmt.query_row(params![cnpj], |row| consultas::estabelecimento_from_row(row)).optional()?)
+        }
+
+        fn buscar_empresa(&self, cnpj_basico: &str) -> Result<Option<EmpresaCompleta>> {
+            let mut stmt = self.prepare_cached(&sql_duckdb(consultas::SQL_EMPRESA))?;
+            Ok(stmt.query_row(params![cnpj_basico], |row| consultas::empresa_from_row(row)).optional()?)
+        }
+
+        fn buscar_socios(&self, cnpj_basico: &str) -> Result<Vec<SocioCompleto>> {
+            let mut stmt = self.prepare_cached(&sql_duckdb(consultas::SQL_SOCIOS))?;
+            let socios = stmt.query_map(params![cnpj_basico], |row| consultas::socio_from_row(row))?
+                .collect::<duckdb::Result<Vec<_>>>()?;
+            Ok(socios)
+        }
+
+        fn buscar_simples(&self, cnpj_basico: &str) -> Result<Option<SimplesDados>> {
+            let mut stmt = self.prepare_cached(&sql_duckdb(consultas::SQL_SIMPLES))?;
+            Ok(stmt.query_row(params![cnpj_basico], |row| consultas::simples_from_row(row)).optional()?)
+        }
+
+        fn ping(&self) -> Result<()> {
+            self.query_row("SELECT 1", [], |_| Ok(()))?;
+            Ok(())
+        }
+
+        fn ler_referencia(&self) -> Result<Vec<(String, String)>> {
+            let mut stmt = self.prepare_cached(consultas::SQL_REFERENCIA)?;
+            let pares = stmt.query_map([], |row| Ok((row.texto(0)?, row.texto(1)?)))?
+                .collect::<duckdb::Result<Vec<_>>>()?;
+            Ok(pares)
+        }
+
+        fn ler_arquivos_origem(&self) -> Result<Vec<ArquivoOrigem>> {
+            let mut stmt = self.prepare_cached(consultas::SQL_ARQUIVOS_ORIGEM)?;
+            let arquivos = stmt.query_map([], |row| {
+                Ok(ArquivoOrigem { arquivo: row.texto(0)?, tamanho: row.get(1)?, sha256: row.texto(2)? })
+            })?
+            .collect::<duckdb::Result<Vec<_>>>()?;
+            Ok(arquivos)
+        }
+
+        /// Mesma regra de `migrations::versao_atual`: sem `_schema_version` a versão é 0,
+        /// desde que o arquivo seja um banco de CNPJ
+        fn versao_esquema(&self) -> Result<i64> {
+            if !tabela_existe(self, migrations::TABELA_VERSAO)? {
+                if !tabela_existe(self, "estabelecimento")? {
+                    anyhow::bail!("O arquivo não contém um banco de CNPJ processado (tabela estabelecimento ausente)");
+                }
+                return Ok(0);
+            }
+
+            let versao: Option<i64> = self.query_row(
+                &format!("SELECT MAX(versao) FROM {}", migrations::TABELA_VERSAO),
+                [],
+                |row| row.get(0),
+            )?;
+            Ok(versao.unwrap_or(0))
+        }
+    }
+}
+
+#[cfg(all(test, feature = "duckdb"))]
+mod tests {
+    use super::abrir_consulta;
+    use duckdb::Connection;
+
+    fn criar(caminho: &str, sql: &str) {
+        Connection::open(caminho).unwrap().execute_batch(sql).unwrap();
+    }
+
+    #[test]
+    fn banco_sem_versao_pede_novo_processamento() {
+        let pasta = tempfile::tempdir().unwrap();
+        let caminho = pasta.path().join("antigo.duckdb").to_string_lossy().into_owned();
+        criar(&caminho, "CREATE TABLE estabelecimento (cnpj TEXT)");
+
+        let erro = abrir_consulta(&caminho).err().unwrap().to_string();
+        assert!(erro.contains("versão de esquema 0"), "{}", erro);
+        assert!(erro.contains("Processe a base novamente"), "{}", erro);
+    }
+
+    #[test]
+    fn arquivo_que_nao_e_banco_de_cnpj_e_recusado() {
+        let pasta = tempfile::tempdir().unwrap();
+        let caminho = pasta.path().join("outro.duckdb").to_string_lossy().into_owned();
+        criar(&caminho, "CREATE TABLE qualquer (id INTEGER)");
+
+        let erro = abrir_consulta(&caminho).err().unwrap().to_string();
+        assert!(erro.contains("tabela estabelecimento ausente"), "{}", erro);
+    }
+}
@@ -0,0 +1,252 @@
+use anyhow::{Context, Result};
+use postgres::{Client, NoTls};
+use std::io::Write;
+use crate::database::{
+    DatabaseWriter, COLUNAS_EMPRESAS, COLUNAS_ESTABELECIMENTO, COLUNAS_SIMPLES, COLUNAS_SOCIOS,
+    INDICES_PRINCIPAIS, INDICES_SOCIOS, SQL_TABELAS_CONTROLE, SQL_TABELA_ORIGEM,
+    TABELAS_CODIGO, TABELAS_PRINCIPAIS,
+};
+use crate::migrations;
+
+/// Destino PostgreSQL: mesmo esquema e índices do SQLite, com carga via `COPY FROM STDIN`
+pub struct PostgresDatabase {
+    client: Client,
+}
+
+impl PostgresDatabase {
+    pub fn connect(url: &str) -> Result<Self> {
+        let client = Client::connect(url, NoTls)
+            .context("Falha ao conectar ao PostgreSQL")?;
+        Ok(Self { client })
+    }
+
+    /// Falha se o schema de destino já contém as tabelas de um processamento anterior
+    pub fn ensure_empty(url: &str) -> Result<()> {
+        let mut db = Self::connect(url)?;
+        for table in ["estabelecimento", "socios", "socios_original"] {
+            let row = db.client.query_one("SELECT to_regclass($1)::text", &[&table])?;
+            if row.get::<_, Option<String>>(0).is_some() {
+                anyhow::bail!("A tabela {} já existe no PostgreSQL de destino", table);
+            }
+        }
+        Ok(())
+    }
+
+    /// Apaga as tabelas criadas por um processamento que não terminou, para que
+    /// `ensure_empty` aceite a próxima execução
+    pub fn remover_tabelas(url: &str) -> Result<()> {
+        let mut db = Self::connect(url)?;
+        let controle = [
+            "socios_original", "_referencia", "_arquivos", "_qualidade", "_arquivos_origem",
+            "_schema_version",
+        ];
+        let tabelas: Vec<&str> = TABELAS_PRINCIPAIS.iter()
+            .chain(TABELAS_CODIGO)
+            .chain(&controle)
+            .copied()
+            .collect();
+        db.client.batch_execute(&format!("DROP TABLE IF EXISTS {}", tabelas.join(", ")))?;
+        Ok(())
+    }
+
+    fn create_main_table(&mut self, table: &str, columns: &[&str]) -> Result<()> {
+        let columns_sql: Vec<String> = columns.iter().map(|c| format!("{} TEXT", c)).collect();
+        self.client.batch_execute(&format!(
+            "CREATE TABLE IF NOT EXISTS {} ({})",
+            table,
+            columns_sql.join(", ")
+        ))?;
+        Ok(())
+    }
+}
+
+/// Escapa um valor para o formato texto do COPY (tab como separador, `\N` é nulo)
+fn escape_copy_value(value: &str, out: &mut Vec<u8>) {
+    for ch in value.chars() {
+        match ch {
+            '\\' => out.extend_from_slice(b"\\\\"),
+            '\t' => out.extend_from_slice(b"\\t"),
+            '\n' => out.extend_from_slice(b"\\n"),
+            '\r' => out.extend_from_slice(b"\\r"),
+            // PostgreSQL não aceita NUL em TEXT
+            '\0' => {}
+            _ => {
+                let mut buf = [0u8; 4];
+                out.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+}
+
+impl DatabaseWriter for PostgresDatabase {
+    fn create_tables(&mut self) -> Result<()> {
+        // Tabelas de referência
+        for table in TABELAS_CODIGO {
+            self.client.batch_execute(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (codigo TEXT PRIMARY KEY, descricao TEXT)",
+                table
+            ))?;
+        }
+
+        // Tabelas principais
+        self.create_main_table("empresas", COLUNAS_EMPRESAS)?;
+        self.create_main_table("estabelecimento", COLUNAS_ESTABELECIMENTO)?;
+        self.create_main_table("socios_original", COLUNAS_SOCIOS)?;
+        self.create_main_table("simples", COLUNAS_SIMPLES)?;
+
+        // Tabelas de controle
+        self.client.batch_execute(SQL_TABELAS_CONTROLE)?;
+        self.client.batch_execute(SQL_TABELA_ORIGEM)?;
+
+        Ok(())
+    }
+
+    fn insert_codigo_descricao_batch(&mut self, table: &str, rows: &[(String, String)]) -> Result<()> {
+        let sql = format!(
+            "INSERT INTO {} (codigo, descricao) VALUES ($1, $2) \
+             ON CONFLICT (codigo) DO UPDATE SET descricao = EXCLUDED.descricao",
+            table
+        );
+        let mut tx = self.client.transaction()?;
+        let stmt = tx.prepare(&sql)?;
+        for (codigo, descricao) in rows {
+            tx.execute(&stmt, &[codigo, descricao])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn insert_rows(&mut self, table: &str, columns: &[&str], rows: &[Vec<String>]) -> Result<()> {
+        let mut buffer = Vec::with_capacity(rows.len() * 200);
+        for row in rows {
+            for (i, value) in row.iter().enumerate() {
+                if i > 0 {
+                    buffer.push(b'\t');
+                }
+                escape_copy_value(value, &mut buffer);
+            }
+            buffer.push(b'\n');
+        }
+
+        let sql = format!("COPY {} ({}) FROM STDIN", table, columns.join(", "));
+        let mut writer = self.client.copy_in(&sql)
+            .with_context(|| format!("Falha ao iniciar COPY em {}", table))?;
+        writer.write_all(&buffer)?;
+        writer.finish()
+            .with_context(|| format!("Falha no COPY em {}", table))?;
+        Ok(())
+    }
+
+    fn create_index(&mut self, table: &str, column: &str) -> Result<()> {
+        let index_name = format!("idx_{}_{}", table, column);
+        self.client.batch_execute(&format!(
+            "CREATE INDEX IF NOT EXISTS {} ON {}({})",
+            index_name, table, column
+        ))?;
+        Ok(())
+    }
+
+    fn finalize_processing(&mut self, data_referencia: &str) -> Result<()> {
+        // Ajusta capital social
+        self.client.batch_execute(
+            r#"
+            ALTER TABLE empresas ADD COLUMN IF NOT EXISTS capital_social DOUBLE PRECISION;
+            UPDATE empresas
+                SET capital_social = CAST(NULLIF(REPLACE(capital_social_str, ',', '.'), '') AS DOUBLE PRECISION);
+            ALTER TABLE empresas DROP COLUMN IF EXISTS capital_social_str;
+            "#,
+        )?;
+
+        // Cria campo CNPJ completo
+        self.client.batch_execute(
+            r#"
+            ALTER TABLE estabelecimento ADD COLUMN IF NOT EXISTS cnpj TEXT;
+            UPDATE estabelecimento SET cnpj = cnpj_basico || cnpj_ordem || cnpj_dv;
+            "#,
+        )?;
+
+        // Cria índices principais
+        for (table, column) in INDICES_PRINCIPAIS {
+            self.create_index(table, column)?;
+        }
+
+        // Cria tabela socios; cnpj fica nulo quando a empresa não tem matriz na base
+        self.client.batch_execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS socios AS
+            SELECT te.cnpj as cnpj, ts.*
+            FROM socios_original ts
+            LEFT JOIN estabelecimento te
+                ON te.cnpj_basico = ts.cnpj_basico AND te.matriz_filial = '1';
+            DROP TABLE IF EXISTS socios_original;
+            "#,
+        )?;
+
+        // Índices na tabela socios
+        for (table, column) in INDICES_SOCIOS {
+            self.create_index(table, column)?;
+        }
+
+        // Tabela de referência
+        self.client.batch_execute(
+            "CREATE TABLE IF NOT EXISTS _referencia (referencia TEXT, valor TEXT)",
+        )?;
+
+        let qtde_cnpjs = self.count_rows("estabelecimento")?;
+        let socios_orfaos: i64 = self.client
+            .query_one("SELECT COUNT(*) FROM socios WHERE cnpj IS NULL", &[])?
+            .get(0);
+        self.client.execute(
+            "INSERT INTO _referencia (referencia, valor) VALUES ('CNPJ', $1)",
+            &[&data_referencia],
+        )?;
+        self.client.execute(
+            "INSERT INTO _referencia (referencia, valor) VALUES ('cnpj_qtde', $1)",
+            &[&qtde_cnpjs.to_string()],
+        )?;
+        self.client.execute(
+            "INSERT INTO _referencia (referencia, valor) VALUES ('socios_orfaos', $1)",
+            &[&socios_orfaos.to_string()],
+        )?;
+
+        self.client.batch_execute(&migrations::sql_registro_versao_atual())?;
+
+        Ok(())
+    }
+
+    fn count_rows(&mut self, table: &str) -> Result<i64> {
+        let row = self.client.query_one(&format!("SELECT COUNT(*) FROM {}", table), &[])?;
+        Ok(row.get(0))
+    }
+
+    fn optimize(&mut self, vacuum: bool) -> Result<()> {
+        // Atualiza as estatísticas do planejador após a carga
+        self.client.batch_execute(if vacuum { "VACUUM ANALYZE" } else { "ANALYZE" })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape_copy_value;
+
+    fn escapar(valor: &str) -> String {
+        let mut out = Vec::new();
+        escape_copy_value(valor, &mut out);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn escapa_separadores_do_copy() {
+        assert_eq!(escapar("SALA 1\\2"), "SALA 1\\\\2");
+        assert_eq!(escapar("linha\tcoluna"), "linha\\tcoluna");
+        assert_eq!(escapar("a\tb\nc\rd"), "a\\tb\\nc\\rd");
+    }
+
+    #[test]
+    fn mantem_texto_comum_e_remove_nul() {
+        assert_eq!(escapar("SÃO PAULO - ÇÃ"), "SÃO PAULO - ÇÃ");
+        assert_eq!(escapar("a\0b"), "ab");
+        assert_eq!(escapar(""), "");
+    }
+}
@@ -1,11 +1,13 @@
 use anyhow::{Context, Result};
 use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{Write, BufWriter};
 use std::path::Path;
 use std::sync::Arc;
 use std::time::{Instant, Duration};
-use indicatif::{ProgressBar, ProgressStyle, HumanBytes, MultiProgress};
+use indicatif::{ProgressBar, ProgressStyle, HumanBytes};
+use crate::progresso::{Evento, Progresso};
 use crate::utils;
 use crate::ui;
 use futures::future::join_all;
@@ -13,6 +15,37 @@ use tokio::sync::Semaphore;
 
 const URL_DADOS_ABERTOS: &str = "https://arquivos.receitafederal.gov.br/dados/cnpj/dados_abertos_cnpj/";
 
+/// Manifesto gravado na pasta do download; o processamento lê dele o mês de referência
+pub const ARQUIVO_MANIFESTO: &str = "manifesto.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifesto {
+    /// Nome da pasta de referência no site da Receita (ex: 2024-05)
+    pub referencia: String,
+    pub url: String,
+    pub baixado_em: String,
+    pub arquivos: Vec<ArquivoManifesto>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArquivoManifesto {
+    pub nome: String,
+    pub tamanho: u64,
+}
+
+/// Lê o manifesto de uma pasta de download, se existir
+pub fn ler_manifesto(dir: &str) -> Result<Option<Manifesto>> {
+    let caminho = Path::new(dir).join(ARQUIVO_MANIFESTO);
+    if !caminho.exists() {
+        return Ok(None);
+    }
+    let conteudo = fs::read_to_string(&caminho)
+        .with_context(|| format!("Falha ao ler {:?}", caminho))?;
+    let manifesto = serde_json::from_str(&conteudo)
+        .with_context(|| format!("Manifesto inválido: {:?}", caminho))?;
+    Ok(Some(manifesto))
+}
+
 // Função para parsear tamanho do formato do servidor (ex: "1.0K", "47M", "1.8G")
 fn parse_size(size_str: &str) -> u64 {
     let size_str = size_str.trim();
@@ -46,54 +79,27 @@ fn parse_size(size_str: &str) -> u64 {
     }
 }
 
-pub async fn download_files(output_dir: &str, auto_yes: bool) -> Result<()> {
-    ui::print_header("📥 Download de Arquivos da Receita Federal");
-    ui::print_info(&format!("Diretório de saída: {}", output_dir));
-    
-    utils::ensure_dir(output_dir)?;
-    
-    // Verifica se a pasta está vazia
-    if !utils::is_dir_empty(output_dir)? {
-        let files = utils::get_files_by_extension(output_dir, ".zip")?;
-        if !files.is_empty() {
-            ui::print_warning(&format!("A pasta {} contém {} arquivo(s) ZIP existente(s)!", output_dir, files.len()));
-            
-            let should_delete = if auto_yes {
-                true
-            } else {
-                ui::ask_confirmation_no("Deseja apagar os arquivos existentes?")?
-            };
-            
-            if should_delete {
-                for file in &files {
-                    fs::remove_file(file)?;
-                    ui::print_verbose(&format!("Removido: {:?}", file));
-                }
-                ui::print_success(&format!("{} arquivo(s) removido(s)", files.len()));
-            } else {
-                ui::print_info("Operação cancelada pelo usuário.");
-                return Ok(());
-            }
-        }
-    }
-
-    // Busca a pasta mais recente
-    ui::print_info("Conectando ao servidor da Receita Federal...");
+/// Cliente HTTP usado na listagem e nos downloads
+fn criar_cliente() -> Result<reqwest::Client> {
     // Cliente com timeout maior para downloads grandes (30 minutos)
-    let client = reqwest::Client::builder()
+    reqwest::Client::builder()
         .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
         .timeout(Duration::from_secs(1800)) // 30 minutos de timeout total para downloads grandes
         .connect_timeout(Duration::from_secs(60)) // 60 segundos para conectar
         .tcp_keepalive(Duration::from_secs(60))
         .build()
-        .context("Falha ao criar cliente HTTP")?;
-    
+        .context("Falha ao criar cliente HTTP")
+}
+
+/// Pasta de referência mais recente no site da Receita (ex: `2024-05/`)
+async fn buscar_ultima_referencia(client: &reqwest::Client, progresso: &Progresso) -> Result<String> {
     ui::print_verbose(&format!("Buscando pasta mais recente em: {}", URL_DADOS_ABERTOS));
     
     // Tenta conectar com retry
     let mut last_error: Option<reqwest::Error> = None;
     let mut response = None;
     for attempt in 1..=5 {
+        progresso.verificar()?;
         match client.get(URL_DADOS_ABERTOS)
             .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
             .header("Accept-Language", "pt-BR,pt;q=0.9,en-US;q=0.8,en;q=0.7")
@@ -150,8 +156,55 @@ pub async fn download_files(output_dir: &str, auto_yes: bool) -> Result<()> {
         .collect();
     
     folders.sort();
-    let ultima_referencia = folders.last()
-        .context("Não encontrou pastas na página de dados abertos")?;
+    folders.pop().context("Não encontrou pastas na página de dados abertos")
+}
+
+/// Mês de referência mais recente publicado pela Receita (ex: 2024-05), sem baixar nada
+pub async fn referencia_disponivel(progresso: &Progresso) -> Result<String> {
+    let client = criar_cliente()?;
+    let pasta = buscar_ultima_referencia(&client, progresso).await?;
+    Ok(pasta.trim_end_matches('/').to_string())
+}
+
+pub async fn download_files(output_dir: &str, auto_yes: bool, progresso: &Progresso) -> Result<()> {
+    ui::print_header("📥 Download de Arquivos da Receita Federal");
+    ui::print_info(&format!("Diretório de saída: {}", output_dir));
+    
+    utils::ensure_dir(output_dir)?;
+    
+    // Verifica se a pasta está vazia
+    if !utils::is_dir_empty(output_dir)? {
+        let files = utils::get_files_by_extension(output_dir, ".zip")?;
+        if !files.is_empty() {
+            ui::print_warning(&format!("A pasta {} contém {} arquivo(s) ZIP existente(s)!", output_dir, files.len()));
+            
+            let should_delete = if auto_yes {
+                true
+            } else {
+                ui::ask_confirmation_no("Deseja apagar os arquivos existentes?")?
+            };
+            
+            if should_delete {
+                for file in &files {
+                    fs::remove_file(file)?;
+                    ui::print_verbose(&format!("Removido: {:?}", file));
+                }
+                ui::print_success(&format!("{} arquivo(s) removido(s)", files.len()));
+            } else {
+                ui::print_info("Operação cancelada pelo usuário.");
+                return Ok(());
+            }
+        }
+    }
+
+    // Busca a pasta mais recente
+    ui::print_info("Conectando ao servidor da Receita Federal...");
+    let mut etapas = ui::Etapas::new(2);
+    etapas.iniciar(1, "Listando arquivos no site da Receita Federal");
+    progresso.fase(1, 2, "Listando arquivos no site da Receita Federal");
+    let client = criar_cliente()?;
+    let ultima_referencia = buscar_ultima_referencia(&client, progresso).await?;
+    let link_selector = Selector::parse("a").unwrap();
     
     let url = format!("{}{}", URL_DADOS_ABERTOS, ultima_referencia);
     ui::print_info(&format!("Pasta de referência: {}", ultima_referencia.trim_end_matches('/')));
@@ -162,6 +215,7 @@ pub async fn download_files(output_dir: &str, auto_yes: bool) -> Result<()> {
     let mut list_response: Option<reqwest::Response> = None;
     let mut list_error: Option<reqwest::Error> = None;
     for attempt in 1..=3 {
+        progresso.verificar()?;
         match client.get(&url)
             .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
             .header("Accept-Language", "pt-BR,pt;q=0.9,en-US;q=0.8,en;q=0.7")
@@ -243,7 +297,7 @@ pub async fn download_files(output_dir: &str, auto_yes: bool) -> Result<()> {
     ui::print_info(&format!("Tamanho total estimado: {}", HumanBytes(total_size)));
     ui::print_verbose("Arquivos ordenados do menor para o maior:");
     for (url, size) in &files_with_size {
-        let filename = url.split('/').last().unwrap_or("arquivo");
+        let filename = url.split('/').next_back().unwrap_or("arquivo");
         ui::print_verbose(&format!("  {}: {}", filename, HumanBytes(*size)));
     }
     
@@ -258,10 +312,30 @@ pub async fn download_files(output_dir: &str, auto_yes: bool) -> Result<()> {
         return Ok(());
     }
     
+    // Registra a pasta de origem para o processamento identificar a referência
+    let manifesto = Manifesto {
+        referencia: ultima_referencia.trim_end_matches('/').to_string(),
+        url: url.clone(),
+        baixado_em: chrono::Local::now().to_rfc3339(),
+        arquivos: files_with_size
+            .iter()
+            .map(|(file_url, size)| ArquivoManifesto {
+                nome: file_url.split('/').next_back().unwrap_or("arquivo").to_string(),
+                tamanho: *size,
+            })
+            .collect(),
+    };
+    let caminho_manifesto = Path::new(output_dir).join(ARQUIVO_MANIFESTO);
+    fs::write(&caminho_manifesto, serde_json::to_string_pretty(&manifesto)?)
+        .with_context(|| format!("Falha ao gravar {:?}", caminho_manifesto))?;
+    ui::print_verbose(&format!("Manifesto gravado em {:?}", caminho_manifesto));
+    
     // Extrai apenas as URLs ordenadas
     let file_urls: Vec<String> = files_with_size.into_iter().map(|(url, _)| url).collect();
     
     ui::print_info("Iniciando downloads paralelos (3 arquivos simultâneos)...");
+    etapas.iniciar(2, "Baixando arquivos");
+    progresso.fase(2, 2, "Baixando arquivos");
     ui::print_separator();
     
     // Download paralelo controlado: 3 arquivos simultâneos
@@ -269,7 +343,7 @@ pub async fn download_files(output_dir: &str, auto_yes: bool) -> Result<()> {
     const MAX_CONCURRENT: usize = 3;
     
     // Cria um MultiProgress para gerenciar múltiplas barras de progresso
-    let multi = MultiProgress::new();
+    let multi = ui::multi_progress();
     
     // Cria uma barra de progresso geral
     let pb_overall = multi.add(ProgressBar::new(total_files as u64));
@@ -287,13 +361,14 @@ pub async fn download_files(output_dir: &str, auto_yes: bool) -> Result<()> {
     
     // Cria uma task para cada arquivo - o semáforo garante que apenas 3 rodem simultaneamente
     for (idx, url) in file_urls.iter().enumerate() {
-        let filename = url.split('/').last().unwrap_or("file.zip").to_string();
+        let filename = url.split('/').next_back().unwrap_or("file.zip").to_string();
         let client_clone = client.clone();
         let url_clone = url.clone();
         let output_dir_clone = output_dir.to_string();
         let multi_clone = multi.clone();
         let pb_overall_clone = pb_overall.clone();
         let semaphore_clone = Arc::clone(&semaphore);
+        let progresso_clone = progresso.clone();
         let current_idx = idx + 1;
         let total_files_clone = total_files;
         
@@ -302,6 +377,8 @@ pub async fn download_files(output_dir: &str, auto_yes: bool) -> Result<()> {
             // Adquire permissão do semáforo (limita a 3 downloads simultâneos)
             let _permit = semaphore_clone.acquire().await
                 .map_err(|e| anyhow::anyhow!("Erro ao adquirir semáforo: {}", e))?;
+            progresso_clone.verificar()?;
+            progresso_clone.arquivo(&filename, current_idx, total_files_clone);
             
             let file_path = Path::new(&output_dir_clone).join(&filename);
             
@@ -309,7 +386,7 @@ pub async fn download_files(output_dir: &str, auto_yes: bool) -> Result<()> {
             let pb = multi_clone.add(ProgressBar::new(0));
             pb.set_style(
                 ProgressStyle::default_bar()
-                    .template(&format!("{{spinner:.green}} [{{elapsed_precise}}] [{{wide_bar:.cyan/blue}}] {{bytes}}/{{total_bytes}} | {{msg}}"))?
+                    .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} | {msg}")?
                     .progress_chars("#>-"),
             );
             pb.set_message(format!("{} ({}/{})", filename, current_idx, total_files_clone));
@@ -317,7 +394,7 @@ pub async fn download_files(output_dir: &str, auto_yes: bool) -> Result<()> {
             // Tenta baixar com retry (até 5 tentativas com timeout progressivo)
             let mut last_error = None;
             for attempt in 1..=5 {
-                match download_single_file_with_progress(&client_clone, &url_clone, &file_path, pb.clone(), attempt).await {
+                match download_single_file_with_progress(&client_clone, &url_clone, &file_path, pb.clone(), attempt, &progresso_clone).await {
                     Ok(_) => {
                         pb_overall_clone.inc(1);
                         let remaining = total_files_clone - pb_overall_clone.position() as usize;
@@ -325,6 +402,8 @@ pub async fn download_files(output_dir: &str, auto_yes: bool) -> Result<()> {
                         pb.finish_with_message(format!("✓ {} concluído", filename));
                         return Ok(());
                     }
+                    // Cancelamento não é falha de rede: não adianta tentar de novo
+                    Err(e) if progresso_clone.verificar().is_err() => return Err(e),
                     Err(e) => {
                         last_error = Some(e);
                         if attempt < 5 {
@@ -361,6 +440,9 @@ pub async fn download_files(output_dir: &str, auto_yes: bool) -> Result<()> {
     
     pb_overall.finish_with_message("Download concluído!");
     
+    // Downloads interrompidos por cancelamento não são erros de rede
+    progresso.verificar()?;
+    
     if !all_errors.is_empty() {
         ui::print_error(&format!("{} erro(s) durante o download:", all_errors.len()));
         for error in &all_errors {
@@ -382,6 +464,7 @@ async fn download_single_file_with_progress(
     file_path: &Path,
     pb: ProgressBar,
     attempt: usize,
+    progresso: &Progresso,
 ) -> Result<()> {
     // Cache de verificação: verifica apenas localmente primeiro
     // Evita HEAD requests desnecessários
@@ -392,17 +475,15 @@ async fn download_single_file_with_progress(
             if metadata.len() > 1024 {
                 // Para arquivos maiores, tenta verificar tamanho remoto
                 // mas não bloqueia se falhar (timeout ou erro)
-                if let Ok(head_response) = tokio::time::timeout(
+                if let Ok(Ok(resp)) = tokio::time::timeout(
                     Duration::from_secs(10),
                     client.head(url).send()
                 ).await {
-                    if let Ok(resp) = head_response {
-                        if let Some(remote_size) = resp.content_length() {
-                            if metadata.len() == remote_size {
-                                pb.finish_with_message(format!("✓ {} já existe e está completo", 
-                                    file_path.file_name().and_then(|n| n.to_str()).unwrap_or("arquivo")));
-                                return Ok(());
-                            }
+                    if let Some(remote_size) = resp.content_length() {
+                        if metadata.len() == remote_size {
+                            pb.finish_with_message(format!("✓ {} já existe e está completo", 
+                                file_path.file_name().and_then(|n| n.to_str()).unwrap_or("arquivo")));
+                            return Ok(());
                         }
                     }
                 }
@@ -410,17 +491,15 @@ async fn download_single_file_with_progress(
                 // Mas continua o download para garantir
             } else {
                 // Arquivo muito pequeno, sempre verifica
-                if let Ok(head_response) = tokio::time::timeout(
+                if let Ok(Ok(resp)) = tokio::time::timeout(
                     Duration::from_secs(10),
                     client.head(url).send()
                 ).await {
-                    if let Ok(resp) = head_response {
-                        if let Some(remote_size) = resp.content_length() {
-                            if metadata.len() == remote_size {
-                                pb.finish_with_message(format!("✓ {} já existe e está completo", 
-                                    file_path.file_name().and_then(|n| n.to_str()).unwrap_or("arquivo")));
-                                return Ok(());
-                            }
+                    if let Some(remote_size) = resp.content_length() {
+                        if metadata.len() == remote_size {
+                            pb.finish_with_message(format!("✓ {} já existe e está completo", 
+                                file_path.file_name().and_then(|n| n.to_str()).unwrap_or("arquivo")));
+                            return Ok(());
                         }
                     }
                 }
@@ -515,7 +594,7 @@ async fn download_single_file_with_progress(
     // Para arquivos menores, usa buffer proporcional
     let buffer_size = if total_size > 100 * 1024 * 1024 {
         // Arquivos grandes: buffer de 64MB a 2GB (limitado a 2GB)
-        std::cmp::min(2 * 1024 * 1024 * 1024, std::cmp::max(64 * 1024 * 1024, total_size / 32))
+        (total_size / 32).clamp(64 * 1024 * 1024, 2 * 1024 * 1024 * 1024)
     } else if total_size > 10 * 1024 * 1024 {
         // Arquivos médios: buffer de 8MB
         8 * 1024 * 1024
@@ -536,22 +615,29 @@ async fn download_single_file_with_progress(
     const PERCENT_STEP: u64 = 10;
     
     let mut stream = response.bytes_stream();
-    let mut last_chunk_time = Instant::now();
+    let mut last_chunk_time;
     
     use futures::StreamExt;
     while let Some(chunk_result) = stream.next().await {
         let chunk = chunk_result?;
         writer.write_all(&chunk)?;
+        progresso.verificar()?;
         
+        let percent_anterior = (downloaded * 100).checked_div(total_size).unwrap_or(0);
         downloaded += chunk.len() as u64;
         last_chunk_time = Instant::now();
         
         // Calcula porcentagem atual
-        let current_percent = if total_size > 0 {
-            (downloaded * 100) / total_size
-        } else {
-            0
-        };
+        let current_percent = (downloaded * 100).checked_div(total_size).unwrap_or(0);
+        
+        // Eventos a cada 1%, mais frequentes que a barra
+        if current_percent > percent_anterior {
+            progresso.emitir(Evento::Bytes {
+                arquivo: filename.to_string(),
+                atual: downloaded,
+                total: total_size,
+            });
+        }
         
         // Atualiza barra de progresso apenas quando muda 10% ou no final
         if current_percent >= last_percent + PERCENT_STEP || downloaded == total_size {
@@ -0,0 +1,287 @@
+//! Erros da API, respondidos no formato `application/problem+json` (RFC 9457).
+//!
+//! Cada erro tem um código estável em `codigo`, que os clientes podem usar em vez do
+//! texto. Erros internos só mostram uma mensagem genérica; o detalhe (que pode conter
+//! SQL) vai para o log do servidor junto com o ID da requisição.
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde::Serialize;
+use std::fmt;
+use utoipa::ToSchema;
+use crate::chaves::Recusa;
+use crate::jobs::{EstadoJob, TipoJob};
+use crate::ui;
+
+pub const CONTENT_TYPE: &str = "application/problem+json";
+
+/// Cabeçalho com o ID da requisição (`X-Request-ID`), aceito na entrada e devolvido
+/// em toda resposta
+pub const CABECALHO_ID: &str = "x-request-id";
+
+tokio::task_local! {
+    /// ID da requisição em andamento, definido pelo middleware do servidor
+    static ID_REQUISICAO: String;
+}
+
+/// Executa `f` com o ID da requisição definido (parte síncrona do atendimento)
+pub fn com_id<R>(id: String, f: impl FnOnce() -> R) -> R {
+    ID_REQUISICAO.sync_scope(id, f)
+}
+
+/// Executa `futuro` com o ID da requisição definido
+pub async fn com_id_async<F: std::future::Future>(id: String, futuro: F) -> F::Output {
+    ID_REQUISICAO.scope(id, futuro).await
+}
+
+fn id_atual() -> Option<String> {
+    ID_REQUISICAO.try_with(Clone::clone).ok()
+}
+
+/// Usa o ID enviado pelo cliente (ex: por um proxy) se for curto e simples; senão gera um novo
+pub fn id_requisicao(recebido: Option<&str>) -> String {
+    match recebido {
+        Some(id)
+            if !id.is_empty()
+                && id.len() <= 64
+                && id.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b)) =>
+        {
+            id.to_string()
+        }
+        _ => {
+            let mut bytes = [0u8; 8];
+            // Sem aleatoriedade disponível, o relógio basta para distinguir requisições no log
+            if getrandom::getrandom(&mut bytes).is_err() {
+                let nanos = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or_default();
+                bytes = nanos.to_be_bytes();
+            }
+            bytes.iter().map(|b| format!("{:02x}", b)).collect()
+        }
+    }
+}
+
+/// Mensagens da cadeia de um erro até a primeira que vem do driver do banco; dali
+/// para baixo as mensagens podem conter SQL ou detalhes internos do arquivo
+pub fn mensagem_sem_sql(erro: &anyhow::Error) -> String {
+    let mensagens: Vec<String> = erro
+        .chain()
+        .take_while(|causa| !causa.is::<rusqlite::Error>() && !eh_erro_duckdb(*causa))
+        .map(ToString::to_string)
+        .collect();
+    if mensagens.is_empty() {
+        "erro ao acessar o banco".to_string()
+    } else {
+        mensagens.join(": ")
+    }
+}
+
+#[cfg(feature = "duckdb")]
+fn eh_erro_duckdb(causa: &(dyn std::error::Error + 'static)) -> bool {
+    causa.is::<duckdb::Error>()
+}
+
+#[cfg(not(feature = "duckdb"))]
+fn eh_erro_duckdb(_causa: &(dyn std::error::Error + 'static)) -> bool {
+    false
+}
+
+#[derive(Debug)]
+pub enum ErroApi {
+    CnpjInvalido { recebido: String },
+    CnpjNaoEncontrado { cnpj: String },
+    JsonInvalido(String),
+    RotaNaoEncontrada,
+    ChaveAusente,
+    ChaveInvalida,
+    /// A rota exige uma chave de administração (`chaves criar --admin`)
+    SemPermissao,
+    LimiteExcedido { segundos: u64 },
+    CotaEsgotada { segundos: u64 },
+    TrabalhoEmAndamento { tipo: TipoJob, job: Box<EstadoJob> },
+    NenhumTrabalho { tipo: TipoJob },
+    /// O banco reaberto por `/api/admin/reload` não passou na validação
+    RecargaRecusada { motivo: String },
+    /// Falha inesperada; `erro` só aparece no log
+    Interno { contexto: &'static str, erro: anyhow::Error },
+}
+
+impl ErroApi {
+    pub fn interno(contexto: &'static str) -> impl FnOnce(anyhow::Error) -> Self {
+        move |erro| ErroApi::Interno { contexto, erro }
+    }
+
+    /// Código estável do erro, também usado no campo `type`
+    pub fn codigo(&self) -> &'static str {
+        match self {
+            ErroApi::CnpjInvalido { .. } => "cnpj_invalido",
+            ErroApi::CnpjNaoEncontrado { .. } => "cnpj_nao_encontrado",
+            ErroApi::JsonInvalido(_) => "json_invalido",
+            ErroApi::RotaNaoEncontrada => "rota_nao_encontrada",
+            ErroApi::ChaveAusente => "chave_ausente",
+            ErroApi::ChaveInvalida => "chave_invalida",
+            ErroApi::SemPermissao => "sem_permissao",
+            ErroApi::LimiteExcedido { .. } => "limite_excedido",
+            ErroApi::CotaEsgotada { .. } => "cota_esgotada",
+            ErroApi::TrabalhoEmAndamento { .. } => "trabalho_em_andamento",
+            ErroApi::NenhumTrabalho { .. } => "nenhum_trabalho",
+            ErroApi::RecargaRecusada { .. } => "recarga_recusada",
+            ErroApi::Interno { .. } => "erro_interno",
+        }
+    }
+
+    fn titulo(&self) -> &'static str {
+        match self {
+            ErroApi::CnpjInvalido { .. } => "CNPJ inválido",
+            ErroApi::CnpjNaoEncontrado { .. } => "CNPJ não encontrado",
+            ErroApi::JsonInvalido(_) => "JSON inválido",
+            ErroApi::RotaNaoEncontrada => "Rota não encontrada",
+            ErroApi::ChaveAusente => "Chave de acesso ausente",
+            ErroApi::ChaveInvalida => "Chave de acesso inválida",
+            ErroApi::SemPermissao => "Permissão insuficiente",
+            ErroApi::LimiteExcedido { .. } => "Limite de requisições excedido",
+            ErroApi::CotaEsgotada { .. } => "Cota diária esgotada",
+            ErroApi::TrabalhoEmAndamento { .. } => "Trabalho já em execução",
+            ErroApi::NenhumTrabalho { .. } => "Nenhum trabalho em execução",
+            ErroApi::RecargaRecusada { .. } => "Banco recusado",
+            ErroApi::Interno { .. } => "Erro interno",
+        }
+    }
+
+    fn detalhe(&self) -> String {
+        match self {
+            ErroApi::CnpjInvalido { recebido } => {
+                format!("O CNPJ deve ter 14 dígitos, com ou sem pontuação; recebido: '{}'", recebido)
+            }
+            ErroApi::CnpjNaoEncontrado { cnpj } => format!("O CNPJ {} não existe no banco em uso", cnpj),
+            ErroApi::JsonInvalido(motivo) => format!("O corpo da requisição não é um JSON válido: {}", motivo),
+            ErroApi::RotaNaoEncontrada => "Nenhum endpoint neste caminho e método; veja /docs/".to_string(),
+            ErroApi::ChaveAusente => {
+                "Envie a chave no cabeçalho Authorization: Bearer <chave> ou X-API-Key".to_string()
+            }
+            ErroApi::ChaveInvalida => "A chave de acesso não existe ou foi revogada".to_string(),
+            ErroApi::SemPermissao => {
+                "Esta rota inicia trabalhos ou troca o banco e exige uma chave de administração".to_string()
+            }
+            ErroApi::LimiteExcedido { segundos } => {
+                format!("Limite de requisições por minuto da chave excedido; tente de novo em {}s", segundos)
+            }
+            ErroApi::CotaEsgotada { segundos } => {
+                format!("A cota diária da chave acabou; ela volta em {}s, à meia-noite", segundos)
+            }
+            ErroApi::TrabalhoEmAndamento { tipo, .. } => {
+                format!("Já existe um trabalho de {} em execução", tipo.descricao())
+            }
+            ErroApi::NenhumTrabalho { tipo } => format!("Nenhum trabalho de {} em execução", tipo.descricao()),
+            ErroApi::RecargaRecusada { motivo } => {
+                format!("O banco novo não passou na validação e o atual continua ativo: {}", motivo)
+            }
+            ErroApi::Interno { .. } => "Erro inesperado no servidor; informe o request_id ao suporte".to_string(),
+        }
+    }
+
+    fn segundos_retry(&self) -> Option<u64> {
+        match self {
+            ErroApi::LimiteExcedido { segundos } | ErroApi::CotaEsgotada { segundos } => Some(*segundos),
+            _ => None,
+        }
+    }
+}
+
+impl From<Recusa> for ErroApi {
+    fn from(recusa: Recusa) -> Self {
+        match recusa {
+            Recusa::SemChave => ErroApi::ChaveAusente,
+            Recusa::ChaveInvalida => ErroApi::ChaveInvalida,
+            Recusa::SemPermissao => ErroApi::SemPermissao,
+            Recusa::LimiteExcedido { segundos } => ErroApi::LimiteExcedido { segundos },
+            Recusa::CotaEsgotada { segundos } => ErroApi::CotaEsgotada { segundos },
+        }
+    }
+}
+
+impl fmt::Display for ErroApi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.titulo(), self.detalhe())
+    }
+}
+
+/// Corpo das respostas de erro
+#[derive(Serialize, ToSchema)]
+pub struct Problema {
+    /// URI do tipo de erro, no formato `urn:cnpj-sqlite:erro:<codigo>`
+    #[serde(rename = "type")]
+    pub tipo: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    /// Código estável do erro (ex: `cnpj_nao_encontrado`)
+    pub codigo: String,
+    /// Mesmo valor do cabeçalho `X-Request-ID`
+    pub request_id: Option<String>,
+    /// Segundos até poder tentar de novo, também no cabeçalho `Retry-After` (só em `429`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
+    /// Trabalho já em execução (só em `trabalho_em_andamento`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job: Option<EstadoJob>,
+}
+
+impl ResponseError for ErroApi {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ErroApi::CnpjInvalido { .. } | ErroApi::JsonInvalido(_) => StatusCode::BAD_REQUEST,
+            ErroApi::CnpjNaoEncontrado { .. } | ErroApi::RotaNaoEncontrada | ErroApi::NenhumTrabalho { .. } => {
+                StatusCode::NOT_FOUND
+            }
+            ErroApi::ChaveAusente | ErroApi::ChaveInvalida => StatusCode::UNAUTHORIZED,
+            ErroApi::SemPermissao => StatusCode::FORBIDDEN,
+            ErroApi::LimiteExcedido { .. } | ErroApi::CotaEsgotada { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ErroApi::TrabalhoEmAndamento { .. } => StatusCode::CONFLICT,
+            ErroApi::RecargaRecusada { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            ErroApi::Interno { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let request_id = id_atual();
+        if let ErroApi::Interno { contexto, erro } = self {
+            ui::print_error(&format!(
+                "[{}] {}: {:#}",
+                request_id.as_deref().unwrap_or("-"),
+                contexto,
+                erro
+            ));
+        }
+
+        let problema = Problema {
+            tipo: format!("urn:cnpj-sqlite:erro:{}", self.codigo()),
+            title: self.titulo().to_string(),
+            status: status.as_u16(),
+            detail: self.detalhe(),
+            codigo: self.codigo().to_string(),
+            request_id,
+            retry_after: self.segundos_retry(),
+            job: match self {
+                ErroApi::TrabalhoEmAndamento { job, .. } => Some((**job).clone()),
+                _ => None,
+            },
+        };
+
+        let mut resposta = HttpResponse::build(status);
+        resposta.content_type(CONTENT_TYPE);
+        match self {
+            ErroApi::ChaveAusente | ErroApi::ChaveInvalida => {
+                resposta.insert_header(("WWW-Authenticate", "Bearer"));
+            }
+            ErroApi::LimiteExcedido { segundos } | ErroApi::CotaEsgotada { segundos } => {
+                resposta.insert_header(("Retry-After", segundos.to_string()));
+            }
+            _ => {}
+        }
+        resposta.body(serde_json::to_string(&problema).unwrap_or_default())
+    }
+}
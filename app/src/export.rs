@@ -0,0 +1,588 @@
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+use clap::ValueEnum;
+use indicatif::ProgressStyle;
+use parquet::basic::{Compression, LogicalType, Repetition, Type as PhysicalType};
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int32Type, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::Type;
+use rusqlite::types::ValueRef;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::ui;
+use crate::utils;
+
+/// Nome da visão desnormalizada de estabelecimentos com as descrições das tabelas de código
+pub const VISAO_ESTABELECIMENTO_COMPLETO: &str = "estabelecimento_completo";
+
+const SQL_VISAO_ESTABELECIMENTO_COMPLETO: &str = r#"
+    CREATE TEMP VIEW IF NOT EXISTS estabelecimento_completo AS
+    SELECT
+        t.cnpj, t.cnpj_basico, t.cnpj_ordem, t.cnpj_dv, t.matriz_filial,
+        te.razao_social, t.nome_fantasia,
+        te.natureza_juridica, tnat.descricao AS natureza_juridica_desc,
+        te.qualificacao_responsavel, tq.descricao AS qualificacao_responsavel_desc,
+        te.capital_social, te.porte_empresa, te.ente_federativo_responsavel,
+        t.situacao_cadastral, t.data_situacao_cadastral,
+        t.motivo_situacao_cadastral, tmot.descricao AS motivo_situacao_cadastral_desc,
+        t.nome_cidade_exterior, t.pais, tpa.descricao AS pais_desc,
+        t.data_inicio_atividades,
+        t.cnae_fiscal, tc.descricao AS cnae_fiscal_desc, t.cnae_fiscal_secundaria,
+        t.tipo_logradouro, t.logradouro, t.numero, t.complemento,
+        t.bairro, t.cep, t.uf, t.municipio, tmun.descricao AS municipio_desc,
+        t.ddd1, t.telefone1, t.ddd2, t.telefone2,
+        t.ddd_fax, t.fax, t.correio_eletronico,
+        t.situacao_especial, t.data_situacao_especial,
+        ts.opcao_simples, ts.data_opcao_simples, ts.data_exclusao_simples,
+        ts.opcao_mei, ts.data_opcao_mei, ts.data_exclusao_mei
+    FROM estabelecimento t
+    LEFT JOIN empresas te ON te.cnpj_basico = t.cnpj_basico
+    LEFT JOIN natureza_juridica tnat ON tnat.codigo = te.natureza_juridica
+    LEFT JOIN qualificacao_socio tq ON tq.codigo = te.qualificacao_responsavel
+    LEFT JOIN motivo tmot ON tmot.codigo = t.motivo_situacao_cadastral
+    LEFT JOIN pais tpa ON tpa.codigo = t.pais
+    LEFT JOIN cnae tc ON tc.codigo = t.cnae_fiscal
+    LEFT JOIN municipio tmun ON tmun.codigo = t.municipio
+    LEFT JOIN simples ts ON ts.cnpj_basico = t.cnpj_basico
+"#;
+
+/// Nome da partição usada para linhas sem UF (convenção Hive)
+const PARTICAO_VAZIA: &str = "__HIVE_DEFAULT_PARTITION__";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FormatoExportacao {
+    Parquet,
+    Csv,
+    Ndjson,
+}
+
+impl FormatoExportacao {
+    fn extensao(&self) -> &'static str {
+        match self {
+            FormatoExportacao::Parquet => "parquet",
+            FormatoExportacao::Csv => "csv",
+            FormatoExportacao::Ndjson => "ndjson",
+        }
+    }
+}
+
+pub struct OpcoesExportacao {
+    pub tabela: String,
+    pub formato: FormatoExportacao,
+    pub output: Option<String>,
+    pub colunas: Vec<String>,
+    pub particionar_uf: bool,
+    pub row_group_size: usize,
+}
+
+/// Tipo lógico de uma coluna exportada, derivado do tipo declarado no SQLite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TipoColuna {
+    Texto,
+    Inteiro,
+    Real,
+    /// Datas no formato AAAAMMDD da Receita, exportadas como DATE no Parquet
+    Data,
+}
+
+struct Coluna {
+    nome: String,
+    tipo: TipoColuna,
+}
+
+#[derive(Debug, Clone)]
+enum Valor {
+    Nulo,
+    Inteiro(i64),
+    Real(f64),
+    Texto(String),
+}
+
+impl Valor {
+    fn from_ref(value: ValueRef<'_>) -> Self {
+        match value {
+            ValueRef::Null => Valor::Nulo,
+            ValueRef::Integer(i) => Valor::Inteiro(i),
+            ValueRef::Real(f) => Valor::Real(f),
+            ValueRef::Text(t) | ValueRef::Blob(t) => Valor::Texto(String::from_utf8_lossy(t).into_owned()),
+        }
+    }
+
+    fn como_texto(&self) -> Option<String> {
+        match self {
+            Valor::Nulo => None,
+            Valor::Inteiro(i) => Some(i.to_string()),
+            Valor::Real(f) => Some(f.to_string()),
+            Valor::Texto(s) => Some(s.clone()),
+        }
+    }
+
+    fn como_inteiro(&self) -> Option<i64> {
+        match self {
+            Valor::Inteiro(i) => Some(*i),
+            Valor::Real(f) => Some(*f as i64),
+            Valor::Texto(s) => s.trim().parse().ok(),
+            Valor::Nulo => None,
+        }
+    }
+
+    fn como_real(&self) -> Option<f64> {
+        match self {
+            Valor::Inteiro(i) => Some(*i as f64),
+            Valor::Real(f) => Some(*f),
+            Valor::Texto(s) => s.trim().replace(',', ".").parse().ok(),
+            Valor::Nulo => None,
+        }
+    }
+
+    /// Converte datas AAAAMMDD em dias desde 1970-01-01; datas zeradas ou inválidas viram nulo
+    fn como_data(&self) -> Option<i32> {
+        let texto = self.como_texto()?;
+        let data = NaiveDate::parse_from_str(texto.trim(), "%Y%m%d").ok()?;
+        let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)?;
+        Some((data - epoch).num_days() as i32)
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            Valor::Nulo => serde_json::Value::Null,
+            Valor::Inteiro(i) => serde_json::Value::from(*i),
+            Valor::Real(f) => serde_json::Value::from(*f),
+            Valor::Texto(s) => serde_json::Value::from(s.as_str()),
+        }
+    }
+}
+
+pub fn export_table(db_path: &str, opcoes: &OpcoesExportacao) -> Result<()> {
+    ui::print_header("📤 Exportação de Dados");
+    ui::print_info(&format!("Hora de início: {}", Local::now().format("%Y-%m-%d %H:%M:%S")));
+    ui::print_info(&format!("Banco de dados: {}", db_path));
+    ui::print_info(&format!("Tabela: {}", opcoes.tabela));
+    ui::print_info(&format!("Formato: {}", opcoes.formato.extensao()));
+
+    if !Path::new(db_path).exists() {
+        anyhow::bail!("Banco de dados não encontrado: {}", db_path);
+    }
+
+    let conn = Connection::open(db_path)
+        .with_context(|| format!("Falha ao abrir banco de dados: {}", db_path))?;
+
+    if opcoes.tabela == VISAO_ESTABELECIMENTO_COMPLETO {
+        conn.execute(SQL_VISAO_ESTABELECIMENTO_COMPLETO, [])?;
+    } else {
+        validate_table(&conn, &opcoes.tabela)?;
+    }
+
+    let disponiveis = get_columns(&conn, &opcoes.tabela)?;
+    if opcoes.particionar_uf && !disponiveis.iter().any(|c| c.nome == "uf") {
+        anyhow::bail!("A tabela {} não possui a coluna uf para particionamento", opcoes.tabela);
+    }
+    let colunas = select_columns(disponiveis, &opcoes.colunas)?;
+
+    let mut selecionadas: Vec<String> = colunas.iter().map(|c| identificador(&c.nome)).collect();
+    if opcoes.particionar_uf {
+        // A UF vai como última coluna apenas para escolher a partição
+        selecionadas.push(identificador("uf"));
+    }
+    let sql = format!("SELECT {} FROM {}", selecionadas.join(", "), identificador(&opcoes.tabela));
+
+    // A visão tem exatamente uma linha por estabelecimento; contar na tabela evita os JOINs
+    let tabela_contagem = if opcoes.tabela == VISAO_ESTABELECIMENTO_COMPLETO {
+        "estabelecimento"
+    } else {
+        opcoes.tabela.as_str()
+    };
+    let total: u64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM {}", identificador(tabela_contagem)),
+        [],
+        |row| row.get::<_, i64>(0),
+    )? as u64;
+
+    let destino = PathBuf::from(opcoes.output.clone().unwrap_or_else(|| {
+        if opcoes.particionar_uf {
+            opcoes.tabela.clone()
+        } else {
+            format!("{}.{}", opcoes.tabela, opcoes.formato.extensao())
+        }
+    }));
+    ui::print_info(&format!("Destino: {:?}", destino));
+    ui::print_info(&format!("Colunas: {}", colunas.len()));
+    ui::print_separator();
+
+    let pb = ui::progress_bar(total);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("  Registros: [{bar:40.cyan/blue}] {pos}/{len} ({percent}%) | ETA: {eta}")?
+            .progress_chars("#>-"),
+    );
+
+    let colunas = Arc::new(colunas);
+    let mut saidas: HashMap<String, Saida> = HashMap::new();
+    // Linhas acumuladas em memória somando todas as partições; o limite é o de um row group
+    let mut pendentes = 0usize;
+    let mut stmt = conn.prepare(&sql)?;
+    let num_cols = colunas.len();
+    let mut rows = stmt.query([])?;
+    let mut count = 0u64;
+
+    while let Some(row) = rows.next()? {
+        let mut valores = Vec::with_capacity(num_cols);
+        for i in 0..num_cols {
+            valores.push(Valor::from_ref(row.get_ref(i)?));
+        }
+
+        let particao = if opcoes.particionar_uf {
+            let uf = Valor::from_ref(row.get_ref(num_cols)?)
+                .como_texto()
+                .map(|uf| nome_particao(uf.trim()))
+                .filter(|uf| !uf.is_empty())
+                .unwrap_or_else(|| PARTICAO_VAZIA.to_string());
+            format!("uf={}", uf)
+        } else {
+            String::new()
+        };
+
+        if !saidas.contains_key(&particao) {
+            let caminho = if opcoes.particionar_uf {
+                destino.join(&particao).join(format!("{}.{}", opcoes.tabela, opcoes.formato.extensao()))
+            } else {
+                destino.clone()
+            };
+            ui::print_verbose(&format!("Criando arquivo: {:?}", caminho));
+            let saida = Saida::new(&caminho, opcoes.formato, Arc::clone(&colunas), opcoes.row_group_size)?;
+            saidas.insert(particao.clone(), saida);
+        }
+        let saida = saidas.get_mut(&particao).expect("saída criada acima");
+        let antes = saida.linhas_pendentes();
+        saida.write_row(valores)?;
+        pendentes = pendentes - antes + saida.linhas_pendentes();
+
+        // Com uma partição por UF, cada uma guardaria até um row group inteiro; acima do
+        // limite, a partição com mais linhas é gravada para liberar a memória
+        if pendentes >= opcoes.row_group_size.max(1) {
+            if let Some(maior) = saidas.values_mut().max_by_key(|saida| saida.linhas_pendentes()) {
+                pendentes -= maior.linhas_pendentes();
+                maior.flush()?;
+            }
+        }
+
+        count += 1;
+        if count.is_multiple_of(10_000) {
+            pb.set_position(count);
+        }
+    }
+
+    let arquivos = saidas.len();
+    for (_, saida) in saidas {
+        saida.finish()?;
+    }
+
+    pb.finish_with_message("Exportação concluída!");
+    ui::print_separator();
+    ui::print_success(&format!("{} registro(s) exportado(s) em {} arquivo(s)", count, arquivos));
+    ui::print_info(&format!("Hora de término: {}", Local::now().format("%Y-%m-%d %H:%M:%S")));
+
+    Ok(())
+}
+
+/// Mantém só letras e números da UF, que vem dos dados e vira nome de pasta
+fn nome_particao(uf: &str) -> String {
+    uf.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Nome de tabela ou coluna entre aspas duplas, para uso direto no SQL
+fn identificador(nome: &str) -> String {
+    format!("\"{}\"", nome.replace('"', "\"\""))
+}
+
+fn validate_table(conn: &Connection, tabela: &str) -> Result<()> {
+    let existe: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type IN ('table', 'view') AND name = ?1",
+        [tabela],
+        |row| row.get(0),
+    )?;
+    if existe == 0 {
+        let mut stmt = conn.prepare(
+            "SELECT name FROM sqlite_master WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?;
+        let tabelas: Vec<String> = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        anyhow::bail!(
+            "Tabela {} não encontrada. Disponíveis: {}, {}",
+            tabela,
+            tabelas.join(", "),
+            VISAO_ESTABELECIMENTO_COMPLETO
+        );
+    }
+    Ok(())
+}
+
+fn get_columns(conn: &Connection, tabela: &str) -> Result<Vec<Coluna>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", identificador(tabela)))?;
+    let colunas = stmt
+        .query_map([], |row| {
+            let nome: String = row.get(1)?;
+            let tipo_declarado: String = row.get::<_, Option<String>>(2)?.unwrap_or_default();
+            Ok((nome, tipo_declarado))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?
+        .into_iter()
+        .map(|(nome, tipo_declarado)| {
+            let tipo_declarado = tipo_declarado.to_uppercase();
+            let tipo = if tipo_declarado.contains("INT") {
+                TipoColuna::Inteiro
+            } else if tipo_declarado.contains("REAL") || tipo_declarado.contains("FLOA") || tipo_declarado.contains("DOUB") {
+                TipoColuna::Real
+            } else if nome.starts_with("data_") {
+                TipoColuna::Data
+            } else {
+                TipoColuna::Texto
+            };
+            Coluna { nome, tipo }
+        })
+        .collect();
+    Ok(colunas)
+}
+
+fn select_columns(disponiveis: Vec<Coluna>, pedidas: &[String]) -> Result<Vec<Coluna>> {
+    if pedidas.is_empty() {
+        return Ok(disponiveis);
+    }
+
+    let mut por_nome: HashMap<String, Coluna> = disponiveis
+        .into_iter()
+        .map(|c| (c.nome.clone(), c))
+        .collect();
+    let mut selecionadas = Vec::with_capacity(pedidas.len());
+    for nome in pedidas {
+        let nome = nome.trim();
+        match por_nome.remove(nome) {
+            Some(coluna) => selecionadas.push(coluna),
+            None => anyhow::bail!("Coluna inexistente ou repetida: {}", nome),
+        }
+    }
+    Ok(selecionadas)
+}
+
+/// Arquivo de saída aberto para um formato específico
+enum Saida {
+    Parquet(SaidaParquet),
+    Csv(csv::Writer<BufWriter<fs::File>>),
+    Ndjson {
+        writer: BufWriter<fs::File>,
+        colunas: Arc<Vec<Coluna>>,
+    },
+}
+
+impl Saida {
+    fn new(caminho: &Path, formato: FormatoExportacao, colunas: Arc<Vec<Coluna>>, row_group_size: usize) -> Result<Self> {
+        if let Some(pasta) = caminho.parent().and_then(|p| p.to_str()).filter(|p| !p.is_empty()) {
+            utils::ensure_dir(pasta)?;
+        }
+        let file = fs::File::create(caminho)
+            .with_context(|| format!("Falha ao criar arquivo: {:?}", caminho))?;
+
+        match formato {
+            FormatoExportacao::Parquet => Ok(Saida::Parquet(SaidaParquet::new(file, colunas, row_group_size)?)),
+            FormatoExportacao::Csv => {
+                let mut writer = csv::WriterBuilder::new().from_writer(BufWriter::new(file));
+                writer.write_record(colunas.iter().map(|c| c.nome.as_str()))?;
+                Ok(Saida::Csv(writer))
+            }
+            FormatoExportacao::Ndjson => Ok(Saida::Ndjson {
+                writer: BufWriter::new(file),
+                colunas,
+            }),
+        }
+    }
+
+    fn write_row(&mut self, valores: Vec<Valor>) -> Result<()> {
+        match self {
+            Saida::Parquet(saida) => saida.write_row(valores),
+            Saida::Csv(writer) => {
+                writer.write_record(valores.iter().map(|v| v.como_texto().unwrap_or_default()))?;
+                Ok(())
+            }
+            Saida::Ndjson { writer, colunas } => {
+                // Monta o objeto manualmente para preservar a ordem das colunas
+                writer.write_all(b"{")?;
+                for (i, (coluna, valor)) in colunas.iter().zip(valores.iter()).enumerate() {
+                    if i > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    serde_json::to_writer(&mut *writer, &coluna.nome)?;
+                    writer.write_all(b":")?;
+                    serde_json::to_writer(&mut *writer, &valor.to_json())?;
+                }
+                writer.write_all(b"}\n")?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Linhas guardadas em memória à espera do próximo row group (só no Parquet)
+    fn linhas_pendentes(&self) -> usize {
+        match self {
+            Saida::Parquet(saida) => saida.linhas,
+            Saida::Csv(_) | Saida::Ndjson { .. } => 0,
+        }
+    }
+
+    /// Grava as linhas pendentes, fechando um row group menor que o configurado
+    fn flush(&mut self) -> Result<()> {
+        match self {
+            Saida::Parquet(saida) => saida.flush_row_group(),
+            Saida::Csv(_) | Saida::Ndjson { .. } => Ok(()),
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Saida::Parquet(saida) => saida.finish(),
+            Saida::Csv(mut writer) => {
+                writer.flush()?;
+                Ok(())
+            }
+            Saida::Ndjson { mut writer, .. } => {
+                writer.flush()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Valores de uma coluna acumulados até completar um row group
+enum BufferColuna {
+    Texto(Vec<ByteArray>),
+    Inteiro(Vec<i64>),
+    Real(Vec<f64>),
+    Data(Vec<i32>),
+}
+
+struct SaidaParquet {
+    writer: SerializedFileWriter<fs::File>,
+    colunas: Arc<Vec<Coluna>>,
+    buffers: Vec<BufferColuna>,
+    /// Níveis de definição (1 = valor presente, 0 = nulo) de cada coluna
+    definicoes: Vec<Vec<i16>>,
+    linhas: usize,
+    row_group_size: usize,
+}
+
+impl SaidaParquet {
+    fn new(file: fs::File, colunas: Arc<Vec<Coluna>>, row_group_size: usize) -> Result<Self> {
+        let mut campos = Vec::with_capacity(colunas.len());
+        for coluna in colunas.iter() {
+            let (fisico, logico) = match coluna.tipo {
+                TipoColuna::Texto => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+                TipoColuna::Inteiro => (PhysicalType::INT64, None),
+                TipoColuna::Real => (PhysicalType::DOUBLE, None),
+                TipoColuna::Data => (PhysicalType::INT32, Some(LogicalType::Date)),
+            };
+            let campo = Type::primitive_type_builder(&coluna.nome, fisico)
+                .with_repetition(Repetition::OPTIONAL)
+                .with_logical_type(logico)
+                .build()?;
+            campos.push(Arc::new(campo));
+        }
+        let schema = Type::group_type_builder("schema").with_fields(campos).build()?;
+
+        let row_group_size = row_group_size.max(1);
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_max_row_group_size(row_group_size)
+            .set_created_by(format!("cnpj-sqlite {}", env!("CARGO_PKG_VERSION")))
+            .build();
+        let writer = SerializedFileWriter::new(file, Arc::new(schema), Arc::new(props))?;
+
+        let mut saida = Self {
+            writer,
+            colunas,
+            buffers: Vec::new(),
+            definicoes: Vec::new(),
+            linhas: 0,
+            row_group_size,
+        };
+        saida.reset_buffers();
+        Ok(saida)
+    }
+
+    fn reset_buffers(&mut self) {
+        self.buffers = self
+            .colunas
+            .iter()
+            .map(|c| match c.tipo {
+                TipoColuna::Texto => BufferColuna::Texto(Vec::new()),
+                TipoColuna::Inteiro => BufferColuna::Inteiro(Vec::new()),
+                TipoColuna::Real => BufferColuna::Real(Vec::new()),
+                TipoColuna::Data => BufferColuna::Data(Vec::new()),
+            })
+            .collect();
+        self.definicoes = vec![Vec::with_capacity(self.row_group_size); self.colunas.len()];
+        self.linhas = 0;
+    }
+
+    fn write_row(&mut self, valores: Vec<Valor>) -> Result<()> {
+        for ((buffer, definicoes), valor) in self.buffers.iter_mut().zip(self.definicoes.iter_mut()).zip(valores) {
+            let presente = match buffer {
+                BufferColuna::Texto(v) => valor.como_texto().map(|s| v.push(ByteArray::from(s.into_bytes()))),
+                BufferColuna::Inteiro(v) => valor.como_inteiro().map(|i| v.push(i)),
+                BufferColuna::Real(v) => valor.como_real().map(|f| v.push(f)),
+                BufferColuna::Data(v) => valor.como_data().map(|d| v.push(d)),
+            };
+            definicoes.push(if presente.is_some() { 1 } else { 0 });
+        }
+        self.linhas += 1;
+
+        if self.linhas >= self.row_group_size {
+            self.flush_row_group()?;
+        }
+        Ok(())
+    }
+
+    fn flush_row_group(&mut self) -> Result<()> {
+        if self.linhas == 0 {
+            return Ok(());
+        }
+
+        let mut row_group = self.writer.next_row_group()?;
+        let mut idx = 0;
+        while let Some(mut coluna) = row_group.next_column()? {
+            let definicoes = &self.definicoes[idx];
+            match &self.buffers[idx] {
+                BufferColuna::Texto(v) => {
+                    coluna.typed::<ByteArrayType>().write_batch(v, Some(definicoes), None)?;
+                }
+                BufferColuna::Inteiro(v) => {
+                    coluna.typed::<Int64Type>().write_batch(v, Some(definicoes), None)?;
+                }
+                BufferColuna::Real(v) => {
+                    coluna.typed::<DoubleType>().write_batch(v, Some(definicoes), None)?;
+                }
+                BufferColuna::Data(v) => {
+                    coluna.typed::<Int32Type>().write_batch(v, Some(definicoes), None)?;
+                }
+            }
+            coluna.close()?;
+            idx += 1;
+        }
+        row_group.close()?;
+
+        self.reset_buffers();
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.flush_row_group()?;
+        self.writer.close()?;
+        Ok(())
+    }
+}
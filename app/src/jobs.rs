@@ -0,0 +1,193 @@
+//! Trabalhos de download e processamento iniciados pela API.
+//!
+//! Cada trabalho roda em uma thread própria e só pode haver um ativo de cada tipo.
+//! Os eventos de progresso chegam por um canal e são lidos por uma segunda thread,
+//! que mantém o estado consultado em `/api/progress`.
+
+use chrono::Local;
+use serde::Serialize;
+use utoipa::ToSchema;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use crate::progresso::{Cancelado, Evento, Progresso};
+use crate::ui;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TipoJob {
+    Download,
+    Process,
+}
+
+impl TipoJob {
+    pub fn descricao(self) -> &'static str {
+        match self {
+            TipoJob::Download => "download",
+            TipoJob::Process => "processamento",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SituacaoJob {
+    Executando,
+    Concluido,
+    Falhou,
+    Cancelado,
+}
+
+impl SituacaoJob {
+    pub fn descricao(self) -> &'static str {
+        match self {
+            SituacaoJob::Executando => "em execução",
+            SituacaoJob::Concluido => "concluído",
+            SituacaoJob::Falhou => "falhou",
+            SituacaoJob::Cancelado => "cancelado",
+        }
+    }
+}
+
+/// Último evento recebido de cada tipo
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct UltimosEventos {
+    pub fase: Option<Evento>,
+    pub arquivo: Option<Evento>,
+    pub bytes: Option<Evento>,
+    pub linhas: Option<Evento>,
+    pub mensagem: Option<Evento>,
+}
+
+impl UltimosEventos {
+    fn registrar(&mut self, evento: Evento) {
+        let campo = match evento {
+            Evento::Fase { .. } => &mut self.fase,
+            Evento::Arquivo { .. } => &mut self.arquivo,
+            Evento::Bytes { .. } => &mut self.bytes,
+            Evento::Linhas { .. } => &mut self.linhas,
+            Evento::Mensagem { .. } => &mut self.mensagem,
+        };
+        *campo = Some(evento);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EstadoJob {
+    pub id: u64,
+    pub tipo: TipoJob,
+    pub situacao: SituacaoJob,
+    pub iniciado_em: String,
+    pub terminado_em: Option<String>,
+    pub erro: Option<String>,
+    pub cancelamento_pedido: bool,
+    pub progresso: UltimosEventos,
+}
+
+struct Job {
+    estado: Arc<Mutex<EstadoJob>>,
+    cancelado: Arc<AtomicBool>,
+}
+
+/// Último trabalho de cada tipo, em execução ou já terminado
+#[derive(Default)]
+pub struct GerenciadorJobs {
+    jobs: Mutex<HashMap<TipoJob, Job>>,
+    proximo_id: AtomicU64,
+}
+
+impl GerenciadorJobs {
+    /// Inicia um trabalho em segundo plano. Se já houver um do mesmo tipo em execução,
+    /// nada é iniciado e o erro traz o estado desse trabalho
+    pub fn iniciar<F>(&self, tipo: TipoJob, trabalho: F) -> Result<EstadoJob, Box<EstadoJob>>
+    where
+        F: FnOnce(Progresso) -> anyhow::Result<()> + Send + 'static,
+    {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.get(&tipo) {
+            let estado = job.estado.lock().unwrap();
+            if estado.situacao == SituacaoJob::Executando {
+                return Err(Box::new(estado.clone()));
+            }
+        }
+
+        let estado = Arc::new(Mutex::new(EstadoJob {
+            id: self.proximo_id.fetch_add(1, Ordering::Relaxed) + 1,
+            tipo,
+            situacao: SituacaoJob::Executando,
+            iniciado_em: Local::now().to_rfc3339(),
+            terminado_em: None,
+            erro: None,
+            cancelamento_pedido: false,
+            progresso: UltimosEventos::default(),
+        }));
+        let cancelado = Arc::new(AtomicBool::new(false));
+        let (canal, eventos) = mpsc::channel();
+        let progresso = Progresso::new(canal, Arc::clone(&cancelado));
+
+        // O coletor termina sozinho quando o trabalho descarta o último Progresso
+        let estado_coletor = Arc::clone(&estado);
+        thread::spawn(move || {
+            for evento in eventos {
+                estado_coletor.lock().unwrap().progresso.registrar(evento);
+            }
+        });
+
+        let estado_trabalho = Arc::clone(&estado);
+        thread::spawn(move || {
+            let resultado = panic::catch_unwind(AssertUnwindSafe(|| trabalho(progresso)));
+            let mut estado = estado_trabalho.lock().unwrap();
+            estado.terminado_em = Some(Local::now().to_rfc3339());
+            estado.situacao = match resultado {
+                Ok(Ok(())) => SituacaoJob::Concluido,
+                Ok(Err(e)) if e.is::<Cancelado>() => SituacaoJob::Cancelado,
+                Ok(Err(e)) => {
+                    estado.erro = Some(format!("{:#}", e));
+                    SituacaoJob::Falhou
+                }
+                Err(_) => {
+                    estado.erro = Some("O trabalho terminou de forma inesperada".to_string());
+                    SituacaoJob::Falhou
+                }
+            };
+            match &estado.erro {
+                Some(erro) => ui::print_error(&format!(
+                    "Trabalho de {} #{} falhou: {}",
+                    tipo.descricao(),
+                    estado.id,
+                    erro
+                )),
+                None => ui::print_info(&format!(
+                    "Trabalho de {} #{} {}",
+                    tipo.descricao(),
+                    estado.id,
+                    estado.situacao.descricao()
+                )),
+            }
+        });
+
+        let inicial = estado.lock().unwrap().clone();
+        jobs.insert(tipo, Job { estado, cancelado });
+        Ok(inicial)
+    }
+
+    /// Pede o cancelamento do trabalho em execução; `None` se não houver nenhum
+    pub fn cancelar(&self, tipo: TipoJob) -> Option<EstadoJob> {
+        let jobs = self.jobs.lock().unwrap();
+        let job = jobs.get(&tipo)?;
+        let mut estado = job.estado.lock().unwrap();
+        if estado.situacao != SituacaoJob::Executando {
+            return None;
+        }
+        job.cancelado.store(true, Ordering::Relaxed);
+        estado.cancelamento_pedido = true;
+        Some(estado.clone())
+    }
+
+    pub fn estado(&self, tipo: TipoJob) -> Option<EstadoJob> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.get(&tipo).map(|job| job.estado.lock().unwrap().clone())
+    }
+}
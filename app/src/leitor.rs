@@ -0,0 +1,458 @@
+//! Leitura tipada dos arquivos CSV da Receita Federal: campos separados por `;`, entre
+//! aspas, sem cabeçalho e em Latin-1.
+//!
+//! `ReceitaReader<T>` lê um arquivo descompactado, uma entrada de um ZIP baixado da
+//! Receita (sem descompactá-lo no disco) ou qualquer `Read`, e devolve um registro `T`
+//! por linha, sem carregar o arquivo na memória. Uma linha com problema vira um
+//! `ErroLeitura` com a origem, o número da linha e, quando dá para saber, a coluna.
+//!
+//! ```no_run
+//! use cnpj_sqlite::leitor::ReceitaReader;
+//! use cnpj_sqlite::models::Estabelecimento;
+//!
+//! let leitor = ReceitaReader::<Estabelecimento>::abrir_zip("dados-publicos-zip/Estabelecimentos0.zip", None)?;
+//! for estabelecimento in leitor {
+//!     let estabelecimento = estabelecimento?;
+//!     println!("{} {}", estabelecimento.cnpj(), estabelecimento.uf);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{Context, Result};
+use csv::{StringRecord, StringRecordsIntoIter};
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use zip::ZipArchive;
+use crate::database::{COLUNAS_EMPRESAS, COLUNAS_ESTABELECIMENTO, COLUNAS_SIMPLES, COLUNAS_SOCIOS};
+use crate::models::{CodigoDescricao, Empresa, Estabelecimento, Simples, Socio};
+use crate::utils;
+
+/// Colunas das tabelas de código
+pub const COLUNAS_CODIGO: &[&str] = &["codigo", "descricao"];
+
+/// Tamanho dos blocos descompactados de uma entrada de ZIP
+const TAMANHO_BLOCO: usize = 256 * 1024;
+
+/// Registro de um tipo de arquivo da Receita; os campos da struct seguem a ordem das colunas
+pub trait RegistroReceita: DeserializeOwned {
+    /// Colunas do arquivo, na ordem; as mesmas da tabela no banco
+    const COLUNAS: &'static [&'static str];
+
+    /// Valores na ordem de `COLUNAS`, para gravação no banco
+    fn em_campos(self) -> Vec<String>;
+}
+
+/// Linha que não pôde ser lida
+#[derive(Debug)]
+pub struct ErroLeitura {
+    /// Arquivo (ou `arquivo.zip:entrada`) de onde veio a linha
+    pub origem: String,
+    /// Linha do arquivo, a partir de 1; 0 quando o erro não é de uma linha
+    pub linha: u64,
+    /// Coluna com problema, quando dá para saber
+    pub coluna: Option<&'static str>,
+    pub motivo: String,
+}
+
+impl fmt::Display for ErroLeitura {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.origem)?;
+        if self.linha > 0 {
+            write!(f, ", linha {}", self.linha)?;
+        }
+        if let Some(coluna) = self.coluna {
+            write!(f, ", coluna {}", coluna)?;
+        }
+        write!(f, ": {}", self.motivo)
+    }
+}
+
+impl std::error::Error for ErroLeitura {}
+
+/// Registros de um arquivo da Receita, na ordem em que aparecem
+pub struct ReceitaReader<T> {
+    linhas: StringRecordsIntoIter<Box<dyn Read + Send>>,
+    origem: String,
+    tipo: PhantomData<T>,
+}
+
+impl<T: RegistroReceita> ReceitaReader<T> {
+    /// Lê um conteúdo em Latin-1; `origem` aparece nas mensagens de erro
+    pub fn new<R: Read + Send + 'static>(leitor: R, origem: impl Into<String>) -> Self {
+        let linhas = csv::ReaderBuilder::new()
+            .delimiter(b';')
+            .has_headers(false)
+            // A quantidade de campos é conferida em `next`, com uma mensagem melhor
+            .flexible(true)
+            .from_reader(utils::latin1_reader(leitor))
+            .into_records();
+        Self { linhas, origem: origem.into(), tipo: PhantomData }
+    }
+
+    /// Arquivo já descompactado
+    pub fn abrir(caminho: impl AsRef<Path>) -> Result<Self> {
+        let caminho = caminho.as_ref();
+        let arquivo = File::open(caminho).with_context(|| format!("Falha ao abrir arquivo: {:?}", caminho))?;
+        Ok(Self::new(arquivo, caminho.display().to_string()))
+    }
+
+    /// Entrada de um ZIP, descompactada enquanto é lida. Sem `entrada`, lê a primeira:
+    /// os ZIPs da Receita têm um arquivo cada
+    pub fn abrir_zip(caminho: impl AsRef<Path>, entrada: Option<&str>) -> Result<Self> {
+        let caminho = caminho.as_ref();
+        let arquivo = File::open(caminho).with_context(|| format!("Falha ao abrir arquivo: {:?}", caminho))?;
+        let mut zip = ZipArchive::new(arquivo).with_context(|| format!("Arquivo ZIP inválido: {:?}", caminho))?;
+        let nome = match entrada {
+            Some(nome) => {
+                zip.by_name(nome).with_context(|| format!("{:?} não tem a entrada {}", caminho, nome))?;
+                nome.to_string()
+            }
+            None if zip.is_empty() => anyhow::bail!("Arquivo ZIP vazio: {:?}", caminho),
+            None => zip.by_index(0)?.name().to_string(),
+        };
+        let origem = format!("{}:{}", caminho.display(), nome);
+        Ok(Self::new(EntradaZip::new(zip, nome), origem))
+    }
+
+    /// Arquivo (ou `arquivo.zip:entrada`) sendo lido
+    pub fn origem(&self) -> &str {
+        &self.origem
+    }
+
+    fn erro(&self, linha: u64, coluna: Option<&'static str>, motivo: impl Into<String>) -> ErroLeitura {
+        ErroLeitura { origem: self.origem.clone(), linha, coluna, motivo: motivo.into() }
+    }
+
+    fn converter(&self, campos: StringRecord) -> Result<T, ErroLeitura> {
+        let linha = campos.position().map(|p| p.line()).unwrap_or_default();
+        if campos.len() != T::COLUNAS.len() {
+            return Err(self.erro(
+                linha,
+                None,
+                format!("{} campos, esperados {} ({})", campos.len(), T::COLUNAS.len(), T::COLUNAS.join(", ")),
+            ));
+        }
+        campos.deserialize(None).map_err(|e| {
+            let coluna = match e.kind() {
+                csv::ErrorKind::Deserialize { err, .. } => {
+                    err.field().and_then(|i| T::COLUNAS.get(i as usize).copied())
+                }
+                _ => None,
+            };
+            self.erro(linha, coluna, e.to_string())
+        })
+    }
+}
+
+impl<T: RegistroReceita> Iterator for ReceitaReader<T> {
+    type Item = Result<T, ErroLeitura>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let linha = self.linhas.next()?;
+        Some(match linha {
+            Ok(campos) => self.converter(campos),
+            Err(e) => {
+                let linha = e.position().map(|p| p.line()).unwrap_or_default();
+                Err(self.erro(linha, None, e.to_string()))
+            }
+        })
+    }
+}
+
+/// Conteúdo de uma entrada de ZIP, descompactado por uma thread à parte. O `ZipFile` do
+/// crate zip empresta o `ZipArchive`, então não dá para guardar os dois no mesmo leitor;
+/// a thread é dona do arquivo e manda os blocos por um canal limitado
+struct EntradaZip {
+    blocos: Receiver<io::Result<Vec<u8>>>,
+    atual: Vec<u8>,
+    posicao: usize,
+}
+
+impl EntradaZip {
+    fn new(mut zip: ZipArchive<File>, nome: String) -> Self {
+        let (envio, blocos) = mpsc::sync_channel(4);
+        thread::spawn(move || {
+            let mut entrada = match zip.by_name(&nome) {
+                Ok(entrada) => entrada,
+                Err(e) => {
+                    let _ = envio.send(Err(io::Error::other(e)));
+                    return;
+                }
+            };
+            loop {
+                let mut bloco = vec![0; TAMANHO_BLOCO];
+                match entrada.read(&mut bloco) {
+                    Ok(0) => return,
+                    Ok(lidos) => {
+                        bloco.truncate(lidos);
+                        // Falha quando o leitor foi descartado: não há mais o que fazer
+                        if envio.send(Ok(bloco)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        let _ = envio.send(Err(e));
+                        return;
+                    }
+                }
+            }
+        });
+        Self { blocos, atual: Vec::new(), posicao: 0 }
+    }
+}
+
+impl Read for EntradaZip {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.posicao >= self.atual.len() {
+            match self.blocos.recv() {
+                Ok(bloco) => {
+                    self.atual = bloco?;
+                    self.posicao = 0;
+                }
+                // A thread terminou: fim da entrada
+                Err(_) => return Ok(0),
+            }
+        }
+        let quantidade = buf.len().min(self.atual.len() - self.posicao);
+        buf[..quantidade].copy_from_slice(&self.atual[self.posicao..self.posicao + quantidade]);
+        self.posicao += quantidade;
+        Ok(quantidade)
+    }
+}
+
+/// Arquivos `*.EMPRECSV`
+pub fn ler_empresas(caminho: impl AsRef<Path>) -> Result<ReceitaReader<Empresa>> {
+    ReceitaReader::abrir(caminho)
+}
+
+/// Arquivos `*.ESTABELE`
+pub fn ler_estabelecimentos(caminho: impl AsRef<Path>) -> Result<ReceitaReader<Estabelecimento>> {
+    ReceitaReader::abrir(caminho)
+}
+
+/// Arquivos `*.SOCIOCSV`
+pub fn ler_socios(caminho: impl AsRef<Path>) -> Result<ReceitaReader<Socio>> {
+    ReceitaReader::abrir(caminho)
+}
+
+/// Arquivo do Simples Nacional e do MEI
+pub fn ler_simples(caminho: impl AsRef<Path>) -> Result<ReceitaReader<Simples>> {
+    ReceitaReader::abrir(caminho)
+}
+
+/// Tabelas de código (`*.CNAECSV`, `*.MOTICSV`, `*.MUNICCSV`, `*.NATJUCSV`, `*.PAISCSV`
+/// e `*.QUALSCSV`)
+pub fn ler_codigos(caminho: impl AsRef<Path>) -> Result<ReceitaReader<CodigoDescricao>> {
+    ReceitaReader::abrir(caminho)
+}
+
+impl RegistroReceita for Empresa {
+    const COLUNAS: &'static [&'static str] = COLUNAS_EMPRESAS;
+
+    fn em_campos(self) -> Vec<String> {
+        vec![
+            self.cnpj_basico,
+            self.razao_social,
+            self.natureza_juridica,
+            self.qualificacao_responsavel,
+            self.capital_social_str,
+            self.porte_empresa,
+            self.ente_federativo_responsavel,
+        ]
+    }
+}
+
+impl RegistroReceita for Estabelecimento {
+    const COLUNAS: &'static [&'static str] = COLUNAS_ESTABELECIMENTO;
+
+    fn em_campos(self) -> Vec<String> {
+        vec![
+            self.cnpj_basico,
+            self.cnpj_ordem,
+            self.cnpj_dv,
+            self.matriz_filial,
+            self.nome_fantasia,
+            self.situacao_cadastral,
+            self.data_situacao_cadastral,
+            self.motivo_situacao_cadastral,
+            self.nome_cidade_exterior,
+            self.pais,
+            self.data_inicio_atividades,
+            self.cnae_fiscal,
+            self.cnae_fiscal_secundaria,
+            self.tipo_logradouro,
+            self.logradouro,
+            self.numero,
+            self.complemento,
+            self.bairro,
+            self.cep,
+            self.uf,
+            self.municipio,
+            self.ddd1,
+            self.telefone1,
+            self.ddd2,
+            self.telefone2,
+            self.ddd_fax,
+            self.fax,
+            self.correio_eletronico,
+            self.situacao_especial,
+            self.data_situacao_especial,
+        ]
+    }
+}
+
+impl RegistroReceita for Socio {
+    const COLUNAS: &'static [&'static str] = COLUNAS_SOCIOS;
+
+    fn em_campos(self) -> Vec<String> {
+        vec![
+            self.cnpj_basico,
+            self.identificador_de_socio,
+            self.nome_socio,
+            self.cnpj_cpf_socio,
+            self.qualificacao_socio,
+            self.data_entrada_sociedade,
+            self.pais,
+            self.representante_legal,
+            self.nome_representante,
+            self.qualificacao_representante_legal,
+            self.faixa_etaria,
+        ]
+    }
+}
+
+impl RegistroReceita for Simples {
+    const COLUNAS: &'static [&'static str] = COLUNAS_SIMPLES;
+
+    fn em_campos(self) -> Vec<String> {
+        vec![
+            self.cnpj_basico,
+            self.opcao_simples,
+            self.data_opcao_simples,
+            self.data_exclusao_simples,
+            self.opcao_mei,
+            self.data_opcao_mei,
+            self.data_exclusao_mei,
+        ]
+    }
+}
+
+impl RegistroReceita for CodigoDescricao {
+    const COLUNAS: &'static [&'static str] = COLUNAS_CODIGO;
+
+    fn em_campos(self) -> Vec<String> {
+        vec![
+            self.codigo,
+            self.descricao,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::io::{Cursor, Write};
+    use zip::write::{FileOptions, ZipWriter};
+
+    /// Empresa com o capital social tipado, para conferir a coluna apontada no erro
+    #[derive(Debug, Deserialize)]
+    struct EmpresaCapital {
+        cnpj_basico: String,
+        razao_social: String,
+        natureza_juridica: String,
+        qualificacao_responsavel: String,
+        capital_social_str: f64,
+        porte_empresa: String,
+        ente_federativo_responsavel: String,
+    }
+
+    impl RegistroReceita for EmpresaCapital {
+        const COLUNAS: &'static [&'static str] = COLUNAS_EMPRESAS;
+
+        fn em_campos(self) -> Vec<String> {
+            vec![
+                self.cnpj_basico,
+                self.razao_social,
+                self.natureza_juridica,
+                self.qualificacao_responsavel,
+                self.capital_social_str.to_string(),
+                self.porte_empresa,
+                self.ente_federativo_responsavel,
+            ]
+        }
+    }
+
+    fn ler<T: RegistroReceita>(conteudo: &[u8]) -> Vec<Result<T, ErroLeitura>> {
+        ReceitaReader::<T>::new(Cursor::new(conteudo.to_vec()), "teste.csv").collect()
+    }
+
+    #[test]
+    fn linha_com_campos_a_menos_informa_a_linha() {
+        let linhas = ler::<Empresa>(
+            b"\"11222333\";\"EMPRESA A\";\"2062\";\"49\";\"1000,00\";\"05\";\"\"\n\
+              \"44555666\";\"EMPRESA B\";\"2062\"\n",
+        );
+        assert!(linhas[0].is_ok());
+        let erro = linhas[1].as_ref().unwrap_err();
+        assert_eq!(erro.linha, 2);
+        assert_eq!(erro.coluna, None);
+        let mensagem = erro.to_string();
+        assert!(mensagem.starts_with("teste.csv, linha 2: 3 campos, esperados 7"), "{}", mensagem);
+    }
+
+    #[test]
+    fn valor_invalido_informa_a_coluna() {
+        let linhas = ler::<EmpresaCapital>(
+            b"\"11222333\";\"EMPRESA A\";\"2062\";\"49\";\"mil reais\";\"05\";\"\"\n",
+        );
+        let erro = linhas[0].as_ref().unwrap_err();
+        assert_eq!(erro.linha, 1);
+        assert_eq!(erro.coluna, Some("capital_social_str"));
+        assert!(erro.to_string().contains(", coluna capital_social_str: "), "{}", erro);
+    }
+
+    #[test]
+    fn decodifica_latin1() {
+        let linhas = ler::<CodigoDescricao>(b"\"3550308\";\"S\xc3O PAULO\"\n\"0001\";\"A\xc7\xdaCAR\"\n");
+        let descricoes: Vec<String> = linhas.into_iter().map(|l| l.unwrap().descricao).collect();
+        assert_eq!(descricoes, ["SÃO PAULO", "AÇÚCAR"]);
+    }
+
+    fn criar_zip(caminho: &Path, entradas: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(File::create(caminho).unwrap());
+        for (nome, conteudo) in entradas {
+            zip.start_file(*nome, FileOptions::default()).unwrap();
+            zip.write_all(conteudo).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn abrir_zip_le_a_primeira_entrada_ou_a_pedida() {
+        let pasta = tempfile::tempdir().unwrap();
+        let caminho = pasta.path().join("Cnaes.zip");
+        criar_zip(&caminho, &[
+            ("F.K03200$Z.D40511.CNAECSV", b"\"0111301\";\"Cultivo de arroz\"\n"),
+            ("F.K03200$Z.D40511.MOTICSV", b"\"01\";\"EXTIN\xc7\xc3O\"\n\"02\";\"INCORPORA\xc7\xc3O\"\n"),
+        ]);
+
+        let leitor = ReceitaReader::<CodigoDescricao>::abrir_zip(&caminho, None).unwrap();
+        assert!(leitor.origem().ends_with("Cnaes.zip:F.K03200$Z.D40511.CNAECSV"));
+        let codigos: Vec<String> = leitor.map(|l| l.unwrap().codigo).collect();
+        assert_eq!(codigos, ["0111301"]);
+
+        let leitor = ReceitaReader::<CodigoDescricao>::abrir_zip(&caminho, Some("F.K03200$Z.D40511.MOTICSV")).unwrap();
+        let descricoes: Vec<String> = leitor.map(|l| l.unwrap().descricao).collect();
+        assert_eq!(descricoes, ["EXTINÇÃO", "INCORPORAÇÃO"]);
+
+        let erro = ReceitaReader::<CodigoDescricao>::abrir_zip(&caminho, Some("NAO_EXISTE")).err().unwrap();
+        assert!(erro.to_string().contains("não tem a entrada NAO_EXISTE"), "{}", erro);
+    }
+}
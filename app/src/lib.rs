@@ -0,0 +1,60 @@
+//! Conversor de dados públicos de CNPJ da Receita Federal para SQLite.
+//!
+//! A biblioteca reúne o que o binário `cnpj-sqlite` usa, para que outros programas em
+//! Rust possam ler os arquivos da Receita, gravar e consultar o banco sem passar pela
+//! linha de comando:
+//!
+//! - [`leitor`] e [`models`]: leitura tipada dos CSVs da Receita
+//! - [`cnpj`]: limpeza, dígitos verificadores e formatação de CNPJ
+//! - [`database`]: escrita do banco (`Database` para SQLite, `Target` para escolher o
+//!   destino) e o esquema das tabelas
+//! - [`consultas`]: consulta de um CNPJ, com as mesmas structs das respostas da API
+//! - [`amostra`]: ZIPs sintéticos no formato da Receita, para testes
+//! - [`recorte`]: banco menor com só os estabelecimentos de uma UF, município, CNAE...
+//! - [`comparacao`]: alterações entre dois bancos processados (CNPJs novos, situação...)
+//!
+//! Os demais módulos são os subcomandos (`download`, `process`, `atualizar`, `server`...).
+//!
+//! ```no_run
+//! use cnpj_sqlite::{cnpj, consultas};
+//!
+//! let fonte = consultas::abrir_fonte("dados-publicos/cnpj.db")?;
+//! let numero = cnpj::limpar("00.000.000/0001-91").expect("CNPJ com 14 dígitos");
+//! if let Some(dados) = consultas::buscar_cnpj(&*fonte, &numero)? {
+//!     println!("{} {:?}", cnpj::formatar(&dados.cnpj), dados.empresa.map(|e| e.razao_social));
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod amostra;
+pub mod api;
+pub mod atualizacao;
+pub mod chaves;
+pub mod cnae_secundaria;
+pub mod cnpj;
+pub mod comparacao;
+pub mod config;
+pub mod consulta;
+pub mod consultas;
+pub mod database;
+pub mod download;
+pub mod export;
+pub mod leitor;
+pub mod migrations;
+pub mod models;
+pub mod process;
+pub mod progresso;
+pub mod recorte;
+pub mod relatorio;
+pub mod ui;
+pub mod utils;
+
+mod cache;
+mod database_duckdb;
+mod database_postgres;
+mod erros;
+mod jobs;
+mod metricas;
+mod openapi;
+mod recarga;
+mod referencia;
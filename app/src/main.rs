@@ -1,14 +1,11 @@
-mod download;
-mod process;
-mod cnae_secundaria;
-mod database;
-mod models;
-mod utils;
-mod api;
-mod ui;
+//! Linha de comando `cnpj-sqlite`: lê as opções e chama a biblioteca `cnpj_sqlite`.
 
 use clap::{Parser, Subcommand};
 use anyhow::Result;
+use cnpj_sqlite::{
+    amostra, api, atualizacao, chaves, cnae_secundaria, comparacao, config, consulta, download, export, migrations,
+    process, progresso, recorte, relatorio, ui,
+};
 
 #[derive(Parser)]
 #[command(name = "cnpj-sqlite")]
@@ -26,6 +23,18 @@ struct Cli {
     #[arg(short, long, global = true)]
     verbose: bool,
     
+    /// Arquivo de configuração (padrão: $CNPJ_SQLITE_CONFIG ou cnpj-sqlite.toml, se existir)
+    #[arg(long, global = true)]
+    config: Option<String>,
+    
+    /// Formato das mensagens: texto (colorido, com barras de progresso) ou json (um evento por linha, no stderr)
+    #[arg(long, global = true, value_enum, default_value_t = ui::FormatoLog::Texto)]
+    log_format: ui::FormatoLog,
+    
+    /// Grava também as mensagens neste arquivo (acrescentadas ao final), no formato de --log-format
+    #[arg(long, global = true)]
+    log_file: Option<String>,
+    
     #[command(subcommand)]
     command: Commands,
 }
@@ -34,65 +43,425 @@ struct Cli {
 enum Commands {
     /// Baixa os arquivos ZIP do site da Receita Federal
     Download {
-        /// Pasta para salvar os arquivos ZIP
-        #[arg(short, long, default_value = "dados-publicos-zip")]
-        output: String,
+        /// Pasta para salvar os arquivos ZIP [config: download.output; padrão: dados-publicos-zip]
+        #[arg(short, long)]
+        output: Option<String>,
     },
     /// Processa os arquivos CSV e gera o banco SQLite
     Process {
-        /// Pasta com os arquivos ZIP
-        #[arg(short, long, default_value = "dados-publicos-zip")]
-        input: String,
-        /// Pasta para descompactar e gerar o banco
-        #[arg(short, long, default_value = "dados-publicos")]
-        output: String,
-        /// Apagar arquivos descompactados após uso (padrão: true)
-        #[arg(short, long, default_value = "true")]
-        cleanup: String,
+        /// Pasta com os arquivos ZIP [config: process.input; padrão: dados-publicos-zip]
+        #[arg(short, long)]
+        input: Option<String>,
+        /// Pasta para descompactar e gerar o banco [config: process.output; padrão: dados-publicos]
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Apagar arquivos descompactados após uso [config: process.cleanup; padrão: true]
+        #[arg(short, long)]
+        cleanup: Option<bool>,
+        /// Banco de destino: caminho SQLite (padrão: <output>/cnpj.db), arquivo .duckdb ou URL postgres://
+        /// [config: process.target]
+        #[arg(short, long)]
+        target: Option<String>,
+        /// Mês de referência dos dados (AAAA-MM). Padrão: detectado pelo manifesto do
+        /// download, pelo nome da pasta ou pelos nomes e datas dos arquivos
+        #[arg(short, long)]
+        referencia: Option<String>,
+        /// Executar VACUUM ao final (reduz o arquivo, mas exige espaço livre igual ao tamanho do banco)
+        /// [config: process.vacuum]
+        #[arg(long)]
+        vacuum: bool,
+        /// Não executar VACUUM, mesmo com process.vacuum = true na configuração
+        #[arg(long, conflicts_with = "vacuum")]
+        no_vacuum: bool,
+    },
+    /// Executa download, verificação, processamento, CNAE secundário e otimização em
+    /// sequência, sem perguntas, e troca o banco só no final
+    #[command(visible_alias = "pipeline")]
+    Atualizar {
+        /// Pasta dos arquivos ZIP [config: process.input; padrão: dados-publicos-zip]
+        #[arg(short, long)]
+        input: Option<String>,
+        /// Pasta do banco cnpj.db [config: process.output; padrão: dados-publicos]
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Etapas a pular, separadas por vírgula
+        #[arg(long, value_enum, value_delimiter = ',')]
+        pular: Vec<atualizacao::Etapa>,
+        /// Etapas a executar mesmo já estando em dia, separadas por vírgula
+        #[arg(long, value_enum, value_delimiter = ',')]
+        forcar: Vec<atualizacao::EtapaForcavel>,
+        /// Usar método com menos memória no CNAE secundário
+        #[arg(long)]
+        low_memory: bool,
     },
     /// Cria tabela normalizada de CNAEs secundários
     CnaeSecundaria {
-        /// Caminho do banco SQLite
-        #[arg(short, long, default_value = "dados-publicos/cnpj.db")]
-        database: String,
+        /// Caminho do banco SQLite (ou arquivo .duckdb) [config: server.database; padrão: banco gerado por process]
+        #[arg(short, long)]
+        database: Option<String>,
         /// Usar método com menos memória (Dask-like)
         #[arg(short, long, default_value = "false")]
         low_memory: bool,
     },
+    /// Exporta uma tabela do banco para Parquet, CSV ou NDJSON
+    Export {
+        /// Caminho do banco SQLite [config: server.database; padrão: banco gerado por process]
+        #[arg(short, long)]
+        database: Option<String>,
+        /// Tabela a exportar (ou "estabelecimento_completo" para a visão com descrições)
+        #[arg(short, long, default_value = export::VISAO_ESTABELECIMENTO_COMPLETO)]
+        table: String,
+        /// Formato de saída
+        #[arg(short, long, value_enum, default_value = "parquet")]
+        format: export::FormatoExportacao,
+        /// Arquivo de saída (ou pasta, se particionado por UF)
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Colunas a exportar, separadas por vírgula (padrão: todas)
+        #[arg(short, long, value_delimiter = ',')]
+        columns: Vec<String>,
+        /// Gera um arquivo por UF em subpastas uf=XX
+        #[arg(long)]
+        partition_by_uf: bool,
+        /// Quantidade de linhas por row group no Parquet
+        #[arg(long, default_value = "1000000")]
+        row_group_size: usize,
+    },
     /// Inicia servidor web API para consulta de CNPJ
     Server {
-        /// Caminho do banco SQLite
-        #[arg(short, long, default_value = "dados-publicos/cnpj.db")]
-        database: String,
-        /// Porta do servidor
-        #[arg(short, long, default_value = "8080")]
-        port: u16,
-        /// Endereço do servidor
-        #[arg(short, long, default_value = "127.0.0.1")]
-        host: String,
+        /// Caminho do banco SQLite (ou arquivo .duckdb) [config: server.database; padrão: banco gerado por process]
+        #[arg(short, long)]
+        database: Option<String>,
+        /// Porta do servidor [config: server.port; padrão: 8080]
+        #[arg(short, long)]
+        port: Option<u16>,
+        /// Endereço do servidor [config: server.host; padrão: 127.0.0.1]
+        #[arg(short = 'H', long)]
+        host: Option<String>,
+        /// Recarrega o banco automaticamente quando o arquivo for substituído [config: server.watch]
+        #[arg(long)]
+        watch: bool,
+        /// Não recarregar automaticamente, mesmo com server.watch = true na configuração
+        #[arg(long, conflicts_with = "watch")]
+        no_watch: bool,
+        /// Intervalo, em segundos, entre as verificações do arquivo com --watch [config: server.watch_interval; padrão: 30]
+        #[arg(long)]
+        watch_interval: Option<u64>,
+        /// Arquivo de chaves de acesso; quando informado, as requisições exigem uma chave [config: server.chaves]
+        #[arg(long)]
+        chaves: Option<String>,
+        /// Quantidade de respostas de CNPJ guardadas em memória, 0 desativa o cache [config: server.cache_size; padrão: 10000]
+        #[arg(long)]
+        cache_size: Option<usize>,
+    },
+    /// Consulta um CNPJ (ou um arquivo de CNPJs) direto no banco, sem o servidor
+    Consulta {
+        /// CNPJ a consultar, com ou sem pontuação
+        #[arg(required_unless_present = "arquivo", conflicts_with = "arquivo")]
+        cnpj: Option<String>,
+        /// Caminho do banco SQLite (ou arquivo .duckdb) [config: server.database; padrão: banco gerado por process]
+        #[arg(short, long)]
+        database: Option<String>,
+        /// Formato de saída de um CNPJ
+        #[arg(short, long, value_enum, default_value = "tabela")]
+        formato: consulta::FormatoConsulta,
+        /// Arquivo com um CNPJ por linha (ou CSV com o CNPJ na primeira coluna) para consulta em lote
+        #[arg(short, long)]
+        arquivo: Option<String>,
+        /// CSV de saída da consulta em lote (padrão: saída padrão)
+        #[arg(short, long, requires = "arquivo")]
+        output: Option<String>,
+    },
+    /// Gera o relatório de qualidade dos dados (HTML e JSON)
+    Relatorio {
+        /// Caminho do banco SQLite [config: server.database; padrão: banco gerado por process]
+        #[arg(short, long)]
+        database: Option<String>,
+        /// Pasta onde salvar os arquivos (padrão: pasta do banco)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Atualiza o esquema de um banco gerado por uma versão anterior
+    Migrate {
+        /// Caminho do banco SQLite [config: server.database; padrão: banco gerado por process]
+        #[arg(short, long)]
+        database: Option<String>,
+    },
+    /// Mostra a configuração efetiva (arquivo, variáveis de ambiente e padrões)
+    Config {
+        #[command(subcommand)]
+        acao: AcaoConfig,
+    },
+    /// Gera um banco menor só com os estabelecimentos filtrados e as empresas, sócios e
+    /// Simples ligados a eles; as tabelas de código vão inteiras
+    Recortar {
+        /// Banco de origem [config: server.database; padrão: banco gerado por process]
+        #[arg(short, long, conflicts_with = "input")]
+        database: Option<String>,
+        /// Pasta com os ZIPs da Receita, para recortar direto deles, sem processar a base inteira
+        #[arg(short, long)]
+        input: Option<String>,
+        /// Banco SQLite a criar
+        #[arg(short, long)]
+        output: String,
+        /// UFs, separadas por vírgula (ex: SC,PR)
+        #[arg(long, value_delimiter = ',')]
+        uf: Vec<String>,
+        /// Municípios, por código da Receita ou nome, separados por vírgula
+        #[arg(long, value_delimiter = ',')]
+        municipio: Vec<String>,
+        /// CNAEs ou prefixos de CNAE (ex: 86 para saúde), principal ou secundário
+        #[arg(long, value_delimiter = ',')]
+        cnae: Vec<String>,
+        /// Considera só o CNAE principal no filtro --cnae
+        #[arg(long, requires = "cnae")]
+        apenas_cnae_principal: bool,
+        /// Situações cadastrais, pelo nome ou código (ex: ativa ou 02)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        situacao: Vec<recorte::Situacao>,
+        /// Arquivo com um CNPJ (ou CNPJ básico, para a empresa inteira) por linha
+        #[arg(long)]
+        cnpjs: Option<String>,
+        /// Mês de referência dos dados (AAAA-MM), com --input. Padrão: detectado pelos arquivos
+        #[arg(short, long, requires = "input")]
+        referencia: Option<String>,
+    },
+    /// Compara dois bancos processados: CNPJs novos e removidos, situação, endereço, sócios e Simples
+    Comparar {
+        /// Banco da referência anterior
+        antigo: String,
+        /// Banco da referência nova
+        novo: String,
+        /// Arquivo com uma linha por alteração (CSV ou JSON)
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Formato do arquivo de alterações (padrão: pela extensão de --output)
+        #[arg(short, long, value_enum, requires = "output")]
+        formato: Option<comparacao::FormatoComparacao>,
+    },
+    /// Gera ZIPs sintéticos no formato da Receita, para testes e demonstrações
+    GerarAmostra {
+        /// Pasta onde salvar os arquivos ZIP
+        #[arg(short, long, default_value = "amostra-zip")]
+        output: String,
+        /// Quantidade de empresas
+        #[arg(short = 'n', long, default_value = "1000")]
+        empresas: usize,
+        /// Quantidade de ZIPs de empresas, estabelecimentos e sócios (1 a 10)
+        #[arg(long, default_value = "10")]
+        partes: usize,
+        /// Mês de referência dos dados (AAAA-MM). Padrão: mês atual
+        #[arg(short, long)]
+        referencia: Option<String>,
+        /// Semente do gerador; a mesma semente gera a mesma amostra
+        #[arg(short, long, default_value = "1")]
+        semente: u64,
+    },
+    /// Gerencia as chaves de acesso à API
+    Chaves {
+        /// Arquivo SQLite com as chaves, criado se não existir [config: server.chaves; padrão: chaves.db]
+        #[arg(short, long)]
+        arquivo: Option<String>,
+        #[command(subcommand)]
+        acao: AcaoChaves,
+    },
+}
+
+#[derive(Subcommand)]
+enum AcaoConfig {
+    /// Mostra o valor efetivo e a origem de cada opção, em TOML
+    Show,
+}
+
+#[derive(Subcommand)]
+enum AcaoChaves {
+    /// Cria uma chave e a mostra uma única vez
+    Criar {
+        /// Nome para identificar quem usa a chave
+        #[arg(short, long)]
+        nome: String,
+        /// Requisições por minuto (também é o tamanho máximo de uma rajada)
+        #[arg(long, default_value = "60")]
+        por_minuto: u32,
+        /// Requisições por dia (padrão: sem limite)
+        #[arg(long)]
+        cota_diaria: Option<u32>,
+        /// Permite iniciar e cancelar download e processamento e recarregar o banco
+        #[arg(long)]
+        admin: bool,
+    },
+    /// Lista as chaves, seus limites e o uso de hoje
+    Listar,
+    /// Revoga uma chave; o servidor passa a recusá-la imediatamente
+    Revogar {
+        /// Nome da chave
+        nome: String,
     },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     let cli = Cli::parse();
     
-    // Inicializa o módulo de UI com as configurações globais
-    ui::init(cli.quiet, cli.verbose);
+    // Inicializa o log com as configurações globais
+    let opcoes_log = ui::OpcoesLog {
+        quiet: cli.quiet,
+        verbose: cli.verbose,
+        formato: cli.log_format,
+        arquivo: cli.log_file.clone(),
+    };
+    if let Err(e) = ui::init(&opcoes_log) {
+        eprintln!("Error: {:?}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) = executar(cli).await {
+        ui::print_erro_fatal(&e);
+        std::process::exit(1);
+    }
+}
+
+async fn executar(cli: Cli) -> Result<()> {
+    let carregada = config::ConfigCarregada::carregar(cli.config.as_deref())?;
+    let config = &carregada.config;
 
     match cli.command {
         Commands::Download { output } => {
-            download::download_files(&output, cli.yes).await?;
+            let output = output.unwrap_or_else(|| config.download.output.clone());
+            download::download_files(&output, cli.yes, &progresso::Progresso::default()).await?;
         }
-        Commands::Process { input, output, cleanup } => {
-            let should_cleanup = cleanup.parse::<bool>().unwrap_or(true);
-            process::process_files(&input, &output, should_cleanup, cli.yes)?;
+        Commands::Process { input, output, cleanup, target, referencia, vacuum, no_vacuum } => {
+            let opcoes = process::OpcoesProcessamento {
+                input_dir: input.unwrap_or_else(|| config.process.input.clone()),
+                output_dir: output.unwrap_or_else(|| config.process.output.clone()),
+                target: target.or_else(|| config.process.target.clone()),
+                referencia,
+                cleanup: cleanup.unwrap_or(config.process.cleanup),
+                vacuum: if vacuum || no_vacuum { vacuum } else { config.process.vacuum },
+                auto_yes: cli.yes,
+            };
+            // O cliente PostgreSQL síncrono cria seu próprio runtime, então o
+            // processamento roda fora das threads do tokio
+            tokio::task::spawn_blocking(move || {
+                process::process_files(&opcoes, &progresso::Progresso::default())
+            })
+            .await??;
+        }
+        Commands::Atualizar { input, output, pular, forcar, low_memory } => {
+            let opcoes = atualizacao::OpcoesAtualizacao {
+                input_dir: input.unwrap_or_else(|| config.process.input.clone()),
+                output_dir: output.unwrap_or_else(|| config.process.output.clone()),
+                pular,
+                forcar,
+                low_memory,
+            };
+            atualizacao::atualizar(&opcoes).await?;
         }
         Commands::CnaeSecundaria { database, low_memory } => {
+            let database = database.unwrap_or_else(|| carregada.banco());
             cnae_secundaria::create_cnae_secundaria_table(&database, low_memory)?;
         }
-        Commands::Server { database, port, host } => {
-            api::start_server(&database, &host, port).await?;
+        Commands::Export { database, table, format, output, columns, partition_by_uf, row_group_size } => {
+            let database = database.unwrap_or_else(|| carregada.banco());
+            let opcoes = export::OpcoesExportacao {
+                tabela: table,
+                formato: format,
+                output,
+                colunas: columns,
+                particionar_uf: partition_by_uf,
+                row_group_size,
+            };
+            export::export_table(&database, &opcoes)?;
+        }
+        Commands::Server { database, port, host, watch, no_watch, watch_interval, chaves, cache_size } => {
+            let database = database.unwrap_or_else(|| carregada.banco());
+            let intervalo = watch_interval.unwrap_or(config.server.watch_interval);
+            let vigiar = (if watch || no_watch { watch } else { config.server.watch })
+                .then(|| std::time::Duration::from_secs(intervalo.max(1)));
+            let opcoes = api::OpcoesServidor {
+                host: host.unwrap_or_else(|| config.server.host.clone()),
+                port: port.unwrap_or(config.server.port),
+                vigiar,
+                arquivo_chaves: chaves.or_else(|| config.server.chaves.clone()),
+                tamanho_cache: cache_size.unwrap_or(config.server.cache_size),
+                padroes: config.clone(),
+            };
+            api::start_server(&database, &opcoes).await?;
+        }
+        Commands::Consulta { cnpj, database, formato, arquivo, output } => {
+            let database = database.unwrap_or_else(|| carregada.banco());
+            match (cnpj, arquivo) {
+                (_, Some(arquivo)) => consulta::consultar_lote(&database, &arquivo, output.as_deref())?,
+                (Some(cnpj), None) => consulta::consultar(&database, &cnpj, formato)?,
+                (None, None) => unreachable!("clap exige o CNPJ ou --arquivo"),
+            }
+        }
+        Commands::Relatorio { database, output } => {
+            let database = database.unwrap_or_else(|| carregada.banco());
+            relatorio::gerar_relatorio(&database, output.as_deref())?;
+        }
+        Commands::Migrate { database } => {
+            let database = database.unwrap_or_else(|| carregada.banco());
+            migrations::migrate(&database)?;
+        }
+        Commands::Config { acao: AcaoConfig::Show } => {
+            print!("{}", carregada.mostrar()?);
+        }
+        Commands::Recortar {
+            database,
+            input,
+            output,
+            uf,
+            municipio,
+            cnae,
+            apenas_cnae_principal,
+            situacao,
+            cnpjs,
+            referencia,
+        } => {
+            let origem = match input {
+                Some(input) => recorte::OrigemRecorte::Zips(input),
+                None => recorte::OrigemRecorte::Banco(database.unwrap_or_else(|| carregada.banco())),
+            };
+            let opcoes = recorte::OpcoesRecorte {
+                origem,
+                output,
+                filtro: recorte::FiltroRecorte {
+                    ufs: uf,
+                    municipios: municipio,
+                    cnaes: cnae,
+                    apenas_cnae_principal,
+                    situacoes: situacao,
+                    arquivo_cnpjs: cnpjs,
+                },
+                referencia,
+            };
+            recorte::recortar(&opcoes)?;
+        }
+        Commands::Comparar { antigo, novo, output, formato } => {
+            let opcoes = comparacao::OpcoesComparacao { antigo, novo, output, formato };
+            comparacao::comparar(&opcoes)?;
+        }
+        Commands::GerarAmostra { output, empresas, partes, referencia, semente } => {
+            let opcoes = amostra::OpcoesAmostra {
+                output_dir: output,
+                empresas,
+                partes,
+                referencia: referencia.unwrap_or_else(|| chrono::Local::now().format("%Y-%m").to_string()),
+                semente,
+            };
+            amostra::gerar(&opcoes)?;
+        }
+        Commands::Chaves { arquivo, acao } => {
+            let arquivo = arquivo
+                .or_else(|| config.server.chaves.clone())
+                .unwrap_or_else(|| "chaves.db".to_string());
+            match acao {
+                AcaoChaves::Criar { nome, por_minuto, cota_diaria, admin } => {
+                    chaves::criar(&arquivo, &nome, por_minuto, cota_diaria, admin)?;
+                }
+                AcaoChaves::Listar => chaves::listar(&arquivo)?,
+                AcaoChaves::Revogar { nome } => chaves::revogar(&arquivo, &nome)?,
+            }
         }
     }
 
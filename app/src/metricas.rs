@@ -0,0 +1,159 @@
+//! Métricas do servidor da API no formato texto do Prometheus, expostas em `/metrics`.
+
+use prometheus::{
+    exponential_buckets, Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge,
+    Opts, Registry, TextEncoder,
+};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use crate::consultas::{
+    ArquivoOrigem, EmpresaCompleta, EstabelecimentoCompleto, FonteConsulta, SimplesDados,
+    SocioCompleto,
+};
+
+pub struct Metricas {
+    registry: Registry,
+    requisicoes: IntCounterVec,
+    duracao_requisicao: HistogramVec,
+    duracao_consulta: HistogramVec,
+    espera_conexao: Histogram,
+    referencia: IntGauge,
+    cache: IntCounterVec,
+}
+
+static METRICAS: LazyLock<Metricas> = LazyLock::new(|| {
+    let registry = Registry::new_custom(Some("cnpj".to_string()), None)
+        .expect("prefixo de métricas válido");
+    // Consultas por chave levam de décimos de milissegundo a alguns segundos
+    let buckets_consulta = exponential_buckets(0.0001, 4.0, 9).expect("buckets válidos");
+
+    let requisicoes = IntCounterVec::new(
+        Opts::new("api_requisicoes_total", "Requisições atendidas, por rota, método e status"),
+        &["rota", "metodo", "status"],
+    )
+    .unwrap();
+    let duracao_requisicao = HistogramVec::new(
+        HistogramOpts::new("api_requisicao_duracao_segundos", "Tempo de resposta, por rota, método e status"),
+        &["rota", "metodo", "status"],
+    )
+    .unwrap();
+    let duracao_consulta = HistogramVec::new(
+        HistogramOpts::new("banco_consulta_duracao_segundos", "Tempo de cada função de consulta ao banco")
+            .buckets(buckets_consulta.clone()),
+        &["funcao"],
+    )
+    .unwrap();
+    let espera_conexao = Histogram::with_opts(
+        HistogramOpts::new("banco_espera_conexao_segundos", "Espera pela conexão com o banco, que atende uma consulta por vez")
+            .buckets(buckets_consulta),
+    )
+    .unwrap();
+    let referencia = IntGauge::new(
+        "banco_referencia_aaaamm",
+        "Mês de referência do banco em uso, como AAAAMM (ex: 202405)",
+    )
+    .unwrap();
+    let cache = IntCounterVec::new(
+        Opts::new("cache_consultas_total", "Consultas de CNPJ ao cache de respostas, por resultado"),
+        &["resultado"],
+    )
+    .unwrap();
+
+    registry.register(Box::new(requisicoes.clone())).unwrap();
+    registry.register(Box::new(duracao_requisicao.clone())).unwrap();
+    registry.register(Box::new(duracao_consulta.clone())).unwrap();
+    registry.register(Box::new(espera_conexao.clone())).unwrap();
+    registry.register(Box::new(referencia.clone())).unwrap();
+    registry.register(Box::new(cache.clone())).unwrap();
+
+    Metricas { registry, requisicoes, duracao_requisicao, duracao_consulta, espera_conexao, referencia, cache }
+});
+
+pub fn registrar_requisicao(rota: &str, metodo: &str, status: u16, duracao: Duration) {
+    let status = status.to_string();
+    let rotulos = [rota, metodo, status.as_str()];
+    METRICAS.requisicoes.with_label_values(&rotulos).inc();
+    METRICAS.duracao_requisicao.with_label_values(&rotulos).observe(duracao.as_secs_f64());
+}
+
+pub fn registrar_espera_conexao(duracao: Duration) {
+    METRICAS.espera_conexao.observe(duracao.as_secs_f64());
+}
+
+pub fn registrar_cache(acerto: bool) {
+    let resultado = if acerto { "acerto" } else { "falha" };
+    METRICAS.cache.with_label_values(&[resultado]).inc();
+}
+
+/// Atualiza o mês de referência (`AAAA-MM`) do banco em uso
+pub fn registrar_referencia(mes: &str) {
+    if let Ok(valor) = mes.replace('-', "").parse() {
+        METRICAS.referencia.set(valor);
+    }
+}
+
+/// Todas as métricas no formato texto do Prometheus
+pub fn exportar() -> String {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&METRICAS.registry.gather(), &mut buffer)
+        .expect("codificação das métricas em memória");
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Fonte de consulta que mede o tempo de cada função da fonte interna
+pub struct FonteMedida {
+    interna: Box<dyn FonteConsulta>,
+}
+
+impl FonteMedida {
+    pub fn new(interna: Box<dyn FonteConsulta>) -> Self {
+        Self { interna }
+    }
+}
+
+fn medir<T>(funcao: &str, consulta: impl FnOnce() -> T) -> T {
+    let inicio = Instant::now();
+    let resultado = consulta();
+    METRICAS
+        .duracao_consulta
+        .with_label_values(&[funcao])
+        .observe(inicio.elapsed().as_secs_f64());
+    resultado
+}
+
+impl FonteConsulta for FonteMedida {
+    fn buscar_estabelecimento(&self, cnpj: &str) -> anyhow::Result<Option<EstabelecimentoCompleto>> {
+        medir("buscar_estabelecimento", || self.interna.buscar_estabelecimento(cnpj))
+    }
+
+    fn buscar_empresa(&self, cnpj_basico: &str) -> anyhow::Result<Option<EmpresaCompleta>> {
+        medir("buscar_empresa", || self.interna.buscar_empresa(cnpj_basico))
+    }
+
+    fn buscar_socios(&self, cnpj_basico: &str) -> anyhow::Result<Vec<SocioCompleto>> {
+        medir("buscar_socios", || self.interna.buscar_socios(cnpj_basico))
+    }
+
+    fn buscar_simples(&self, cnpj_basico: &str) -> anyhow::Result<Option<SimplesDados>> {
+        medir("buscar_simples", || self.interna.buscar_simples(cnpj_basico))
+    }
+
+    fn ping(&self) -> anyhow::Result<()> {
+        medir("ping", || self.interna.ping())
+    }
+
+    fn ler_referencia(&self) -> anyhow::Result<Vec<(String, String)>> {
+        medir("ler_referencia", || self.interna.ler_referencia())
+    }
+
+    fn ler_arquivos_origem(&self) -> anyhow::Result<Vec<ArquivoOrigem>> {
+        medir("ler_arquivos_origem", || self.interna.ler_arquivos_origem())
+    }
+
+    fn versao_esquema(&self) -> anyhow::Result<i64> {
+        medir("versao_esquema", || self.interna.versao_esquema())
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use rusqlite::{params, Connection, OptionalExtension};
+use crate::database;
+use crate::ui;
+
+/// Versão de esquema que este binário gera e espera encontrar
+pub const SCHEMA_VERSION: i64 = 6;
+
+/// Tabela com uma linha por migração aplicada
+pub const TABELA_VERSAO: &str = "_schema_version";
+
+pub struct Migracao {
+    pub versao: i64,
+    pub descricao: &'static str,
+    aplicar: fn(&Connection) -> Result<()>,
+}
+
+/// Migrações em ordem crescente de versão. Um banco recém-processado já nasce com o
+/// layout da última versão; estas funções só rodam em arquivos antigos via `migrate`
+pub const MIGRACOES: &[Migracao] = &[
+    Migracao {
+        versao: 1,
+        descricao: "Controle de versão do esquema",
+        // Bancos anteriores ao controle de versão já têm o layout da versão 1
+        aplicar: |_| Ok(()),
+    },
+    Migracao {
+        versao: 2,
+        descricao: "Estatísticas do planejador (ANALYZE)",
+        aplicar: |conn| {
+            conn.execute_batch("ANALYZE")?;
+            Ok(())
+        },
+    },
+    Migracao {
+        versao: 3,
+        descricao: "Sócios consultados por CNPJ básico",
+        // Sócios sem matriz descartados por versões antigas só voltam reprocessando a base
+        aplicar: |conn| {
+            conn.execute_batch(
+                "CREATE INDEX IF NOT EXISTS idx_socios_cnpj_basico ON socios(cnpj_basico)",
+            )?;
+            Ok(())
+        },
+    },
+    Migracao {
+        versao: 4,
+        descricao: "Tabelas _arquivos e _qualidade",
+        // A contagem por arquivo só existe em bancos processados a partir desta versão
+        aplicar: |conn| {
+            conn.execute_batch(database::SQL_TABELAS_CONTROLE)?;
+            Ok(())
+        },
+    },
+    Migracao {
+        versao: 5,
+        descricao: "Referência em formato ISO (AAAA-MM)",
+        // Converte o antigo DD/MM/AAAA; valores sem data válida ficam como estão
+        aplicar: |conn| {
+            conn.execute_batch(
+                r#"
+                UPDATE _referencia
+                SET valor = substr(valor, 7, 4) || '-' || substr(valor, 4, 2)
+                WHERE referencia = 'CNPJ'
+                  AND valor GLOB '[0-9][0-9]/[0-9][0-9]/[0-9][0-9][0-9][0-9]'
+                "#,
+            )?;
+            Ok(())
+        },
+    },
+    Migracao {
+        versao: 6,
+        descricao: "Metadados do processamento e tabela _arquivos_origem",
+        // Bancos antigos ficam sem os metadados novos em _referencia e sem checksums
+        aplicar: |conn| {
+            conn.execute_batch(database::SQL_TABELA_ORIGEM)?;
+            Ok(())
+        },
+    },
+];
+
+const SQL_CRIAR_TABELA_VERSAO: &str = "CREATE TABLE IF NOT EXISTS _schema_version (\
+     versao INTEGER PRIMARY KEY, descricao TEXT NOT NULL, aplicada_em TEXT NOT NULL)";
+
+/// SQL que cria `_schema_version` marcando todas as migrações como aplicadas.
+/// Usado ao final do processamento em qualquer banco de destino
+pub fn sql_registro_versao_atual() -> String {
+    let aplicada_em = Local::now().to_rfc3339();
+    let mut sql = format!("{};\n", SQL_CRIAR_TABELA_VERSAO);
+    for migracao in MIGRACOES {
+        sql.push_str(&format!(
+            "INSERT INTO {} (versao, descricao, aplicada_em) VALUES ({}, '{}', '{}');\n",
+            TABELA_VERSAO,
+            migracao.versao,
+            migracao.descricao.replace('\'', "''"),
+            aplicada_em
+        ));
+    }
+    sql
+}
+
+pub fn tabela_existe(conn: &Connection, tabela: &str) -> Result<bool> {
+    let existe = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            params![tabela],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    Ok(existe)
+}
+
+/// Versão do esquema de um banco SQLite. Bancos gerados antes do controle de versão
+/// retornam 0; arquivos sem a tabela `estabelecimento` não são bancos de CNPJ
+pub fn versao_atual(conn: &Connection) -> Result<i64> {
+    if !tabela_existe(conn, TABELA_VERSAO)? {
+        if !tabela_existe(conn, "estabelecimento")? {
+            anyhow::bail!("O arquivo não contém um banco de CNPJ processado (tabela estabelecimento ausente)");
+        }
+        return Ok(0);
+    }
+
+    let versao: Option<i64> = conn.query_row(
+        &format!("SELECT MAX(versao) FROM {}", TABELA_VERSAO),
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(versao.unwrap_or(0))
+}
+
+/// Falha se o banco não está exatamente na versão esperada pelo binário
+pub fn verificar_versao(conn: &Connection, db_path: &str) -> Result<()> {
+    conferir_versao(versao_atual(conn)?, db_path)
+}
+
+/// Compara a versão lida de um banco (SQLite ou DuckDB) com a esperada pelo binário.
+/// O `migrate` só atualiza SQLite; bancos DuckDB antigos precisam ser reprocessados
+pub fn conferir_versao(versao: i64, db_path: &str) -> Result<()> {
+    if versao < SCHEMA_VERSION && database::is_duckdb_path(db_path) {
+        anyhow::bail!(
+            "O banco {} está na versão de esquema {}, mas este binário espera a versão {}. \
+             Processe a base novamente para gerar um banco DuckDB atualizado",
+            db_path, versao, SCHEMA_VERSION
+        );
+    }
+    if versao < SCHEMA_VERSION {
+        anyhow::bail!(
+            "O banco {} está na versão de esquema {}, mas este binário espera a versão {}. \
+             Atualize com: cnpj-sqlite migrate --database {}",
+            db_path, versao, SCHEMA_VERSION, db_path
+        );
+    }
+    if versao > SCHEMA_VERSION {
+        anyhow::bail!(
+            "O banco {} está na versão de esquema {}, mais nova que a versão {} deste binário. \
+             Atualize o cnpj-sqlite.",
+            db_path, versao, SCHEMA_VERSION
+        );
+    }
+    Ok(())
+}
+
+/// Aplica em ordem as migrações pendentes de um banco SQLite
+pub fn migrate(db_path: &str) -> Result<()> {
+    ui::print_header("🗄️  Migração do Esquema do Banco");
+
+    if !std::path::Path::new(db_path).exists() {
+        anyhow::bail!("Banco de dados não encontrado: {}", db_path);
+    }
+    let mut conn = Connection::open(db_path)
+        .with_context(|| format!("Falha ao abrir banco de dados: {}", db_path))?;
+
+    let versao = versao_atual(&conn)?;
+    ui::print_info(&format!("Versão atual do esquema: {}", versao));
+    ui::print_info(&format!("Versão esperada por este binário: {}", SCHEMA_VERSION));
+
+    if versao > SCHEMA_VERSION {
+        anyhow::bail!("O banco é mais novo que este binário; atualize o cnpj-sqlite");
+    }
+
+    let pendentes: Vec<&Migracao> = MIGRACOES.iter().filter(|m| m.versao > versao).collect();
+    if pendentes.is_empty() {
+        ui::print_success("O banco já está na versão mais recente");
+        return Ok(());
+    }
+
+    conn.execute_batch(SQL_CRIAR_TABELA_VERSAO)?;
+    for migracao in pendentes {
+        ui::print_info(&format!("Aplicando migração {}: {}", migracao.versao, migracao.descricao));
+        let tx = conn.transaction()?;
+        (migracao.aplicar)(&tx)
+            .with_context(|| format!("Falha na migração {}", migracao.versao))?;
+        tx.execute(
+            &format!("INSERT INTO {} (versao, descricao, aplicada_em) VALUES (?1, ?2, ?3)", TABELA_VERSAO),
+            params![migracao.versao, migracao.descricao, Local::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+    }
+
+    ui::print_success(&format!("Banco migrado para a versão {}", SCHEMA_VERSION));
+    Ok(())
+}
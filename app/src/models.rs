@@ -1,5 +1,12 @@
+//! Registros dos arquivos CSV da Receita Federal, um campo por coluna e na mesma ordem
+//! do arquivo. Os valores ficam como texto, do jeito que vêm da Receita (datas `AAAAMMDD`,
+//! capital social com vírgula decimal); são lidos por `leitor::ReceitaReader`.
 
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+
+
+/// Linha de um arquivo `*.EMPRECSV` (tabela `empresas`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Empresa {
     pub cnpj_basico: String,
     pub razao_social: String,
@@ -10,7 +17,8 @@ pub struct Empresa {
     pub ente_federativo_responsavel: String,
 }
 
-#[derive(Debug, Clone)]
+/// Linha de um arquivo `*.ESTABELE` (tabela `estabelecimento`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Estabelecimento {
     pub cnpj_basico: String,
     pub cnpj_ordem: String,
@@ -44,7 +52,8 @@ pub struct Estabelecimento {
     pub data_situacao_especial: String,
 }
 
-#[derive(Debug, Clone)]
+/// Linha de um arquivo `*.SOCIOCSV` (tabela `socios`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Socio {
     pub cnpj_basico: String,
     pub identificador_de_socio: String,
@@ -59,7 +68,8 @@ pub struct Socio {
     pub faixa_etaria: String,
 }
 
-#[derive(Debug, Clone)]
+/// Linha do arquivo do Simples Nacional e do MEI (tabela `simples`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Simples {
     pub cnpj_basico: String,
     pub opcao_simples: String,
@@ -70,9 +80,17 @@ pub struct Simples {
     pub data_exclusao_mei: String,
 }
 
-#[derive(Debug, Clone)]
+/// Linha das tabelas de código: CNAEs, motivos, municípios, naturezas jurídicas, países
+/// e qualificações de sócios
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodigoDescricao {
     pub codigo: String,
     pub descricao: String,
 }
 
+impl Estabelecimento {
+    /// CNPJ completo, com 14 dígitos
+    pub fn cnpj(&self) -> String {
+        format!("{}{}{}", self.cnpj_basico, self.cnpj_ordem, self.cnpj_dv)
+    }
+}
@@ -0,0 +1,58 @@
+//! Especificação OpenAPI 3 da API, gerada a partir das rotas e structs de `api.rs`
+//! e servida em `/openapi.json`, com a documentação interativa em `/docs/`.
+
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use crate::api;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "API CNPJ",
+        description = "Consulta aos dados públicos de CNPJ da Receita Federal.\n\n\
+            As chaves de acesso só são exigidas quando o servidor é iniciado com `--chaves`; \
+            `/health`, `/metrics` e esta documentação ficam sempre livres."
+    ),
+    paths(
+        api::consultar_cnpj,
+        api::health_check,
+        api::database_status,
+        api::iniciar_download,
+        api::cancelar_download,
+        api::iniciar_processamento,
+        api::cancelar_processamento,
+        api::progresso,
+        api::recarregar_banco,
+        api::metrics,
+    ),
+    modifiers(&ChavesDeAcesso),
+    security(("bearer" = []), ("api_key" = [])),
+    tags(
+        (name = "consulta", description = "Consulta de CNPJ"),
+        (name = "banco", description = "Banco de dados em uso"),
+        (name = "trabalhos", description = "Download e processamento em segundo plano"),
+        (name = "monitoramento", description = "Saúde e métricas do servidor"),
+    )
+)]
+struct ApiDoc;
+
+/// Formas de enviar a chave de acesso: `Authorization: Bearer` ou `X-API-Key`
+struct ChavesDeAcesso;
+
+impl Modify for ChavesDeAcesso {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let componentes = openapi.components.get_or_insert_with(Default::default);
+        componentes.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        componentes.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+    }
+}
+
+pub fn documento() -> utoipa::openapi::OpenApi {
+    ApiDoc::openapi()
+}
@@ -1,17 +1,111 @@
 use anyhow::Result;
-use rusqlite::params;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::time::Instant;
 use zip::ZipArchive;
 use chrono::Local;
-use indicatif::{ProgressBar, ProgressStyle, MultiProgress};
-use crate::database::Database;
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle, MultiProgress};
+use crate::database::{self, DatabaseWriter, Target};
+use crate::leitor::{ReceitaReader, RegistroReceita};
+use crate::models::{CodigoDescricao, Empresa, Estabelecimento, Simples, Socio};
+use crate::progresso::{Evento, Progresso};
+use crate::referencia;
+use crate::relatorio;
 use crate::utils;
 use crate::ui;
 
-pub fn process_files(input_dir: &str, output_dir: &str, cleanup: bool, auto_yes: bool) -> Result<()> {
-    ui::print_header("⚙️  Processamento de Arquivos CSV → SQLite");
+/// Parâmetros do processamento, vindos do subcomando `process` ou de `/api/process/start`
+#[derive(Debug, Clone)]
+pub struct OpcoesProcessamento {
+    pub input_dir: String,
+    pub output_dir: String,
+    /// Banco de destino; `None` usa `<output_dir>/cnpj.db`
+    pub target: Option<String>,
+    /// Mês de referência informado; `None` detecta pelos arquivos
+    pub referencia: Option<String>,
+    pub cleanup: bool,
+    pub vacuum: bool,
+    pub auto_yes: bool,
+}
+
+/// Carga de um arquivo com o leitor tipado do registro da tabela
+type CarregarCsv = fn(&mut dyn DatabaseWriter, &Path, &str, &MultiProgress, &Progresso) -> Result<u64>;
+
+/// Arquivos de uma tabela principal: trecho do nome, tabela de destino, colunas e carga
+pub(crate) struct ArquivosTabela {
+    pub(crate) padrao: &'static str,
+    pub(crate) tabela: &'static str,
+    colunas: &'static [&'static str],
+    carregar: CarregarCsv,
+}
+
+const TOTAL_ETAPAS: usize = 5;
+
+/// Arquivos das tabelas de código: trecho do nome e tabela de destino
+pub(crate) const ARQUIVOS_CODIGO: &[(&str, &str)] = &[
+    (".CNAECSV", "cnae"),
+    (".MOTICSV", "motivo"),
+    (".MUNICCSV", "municipio"),
+    (".NATJUCSV", "natureza_juridica"),
+    (".PAISCSV", "pais"),
+    (".QUALSCSV", "qualificacao_socio"),
+];
+
+pub(crate) const ARQUIVOS_TABELAS: &[ArquivosTabela] = &[
+    ArquivosTabela {
+        padrao: ".EMPRECSV",
+        tabela: "empresas",
+        colunas: Empresa::COLUNAS,
+        carregar: load_csv_to_database::<Empresa>,
+    },
+    ArquivosTabela {
+        padrao: ".ESTABELE",
+        tabela: "estabelecimento",
+        colunas: Estabelecimento::COLUNAS,
+        carregar: load_csv_to_database::<Estabelecimento>,
+    },
+    ArquivosTabela {
+        padrao: ".SOCIOCSV",
+        tabela: "socios_original",
+        colunas: Socio::COLUNAS,
+        carregar: load_csv_to_database::<Socio>,
+    },
+    ArquivosTabela {
+        padrao: ".SIMPLES.CSV",
+        tabela: "simples",
+        colunas: Simples::COLUNAS,
+        carregar: load_csv_to_database::<Simples>,
+    },
+];
+
+/// Apaga o banco de destino ao sair de escopo, a menos que o processamento tenha chegado
+/// ao fim. Um banco pela metade (cancelado ou com erro) faria o próximo processamento
+/// falhar em `Target::ensure_empty`
+struct BancoParcial<'a> {
+    target: &'a Target,
+    concluido: bool,
+}
+
+impl Drop for BancoParcial<'_> {
+    fn drop(&mut self) {
+        if !self.concluido {
+            self.target.remove_files();
+        }
+    }
+}
+
+/// Registra a etapa no log e a envia como evento de progresso
+fn etapa(etapas: &mut ui::Etapas, progresso: &Progresso, passo: usize, descricao: &str) {
+    etapas.iniciar(passo, descricao);
+    progresso.fase(passo, TOTAL_ETAPAS, descricao);
+}
+
+pub fn process_files(opcoes: &OpcoesProcessamento, progresso: &Progresso) -> Result<()> {
+    let input_dir = opcoes.input_dir.as_str();
+    let output_dir = opcoes.output_dir.as_str();
+    let (cleanup, vacuum) = (opcoes.cleanup, opcoes.vacuum);
+    
+    ui::print_header("⚙️  Processamento de Arquivos CSV → Banco de Dados");
     ui::print_info(&format!("Hora de início: {}", Local::now().format("%Y-%m-%d %H:%M:%S")));
     ui::print_info(&format!("Diretório de entrada: {}", input_dir));
     ui::print_info(&format!("Diretório de saída: {}", output_dir));
@@ -19,12 +113,18 @@ pub fn process_files(input_dir: &str, output_dir: &str, cleanup: bool, auto_yes:
     
     utils::ensure_dir(output_dir)?;
     
-    let db_path = Path::new(output_dir).join("cnpj.db");
-    if db_path.exists() {
-        ui::print_error(&format!("O arquivo {:?} já existe!", db_path));
-        ui::print_info("Apague o arquivo existente e execute novamente, ou use um diretório de saída diferente.");
-        anyhow::bail!("Banco de dados já existe: {:?}", db_path);
+    let target = match &opcoes.target {
+        Some(target) => Target::parse(target),
+        None => Target::Sqlite(Path::new(output_dir).join("cnpj.db")),
+    };
+    ui::print_info(&format!("Destino: {}", target));
+    if let Err(e) = target.ensure_empty() {
+        ui::print_error(&e.to_string());
+        ui::print_info("Apague o banco existente e execute novamente, ou use um destino diferente.");
+        return Err(e);
     }
+    // O destino não existia: tudo o que houver nele a partir daqui foi criado agora
+    let mut parcial = BancoParcial { target: &target, concluido: false };
     
     // Descompacta arquivos ZIP
     let zip_files = utils::get_files_by_extension(input_dir, ".zip")?;
@@ -37,7 +137,7 @@ pub fn process_files(input_dir: &str, output_dir: &str, cleanup: bool, auto_yes:
         ui::print_warning(&format!("A pasta {} deveria conter 37 arquivos ZIP, mas contém {}.", input_dir, zip_files.len()));
         ui::print_warning("É recomendável prosseguir apenas com todos os arquivos, senão a base ficará incompleta.");
         
-        let should_continue = if auto_yes {
+        let should_continue = if opcoes.auto_yes {
             true
         } else {
             ui::ask_confirmation_no("Deseja prosseguir assim mesmo?")?
@@ -51,72 +151,112 @@ pub fn process_files(input_dir: &str, output_dir: &str, cleanup: bool, auto_yes:
         ui::print_success(&format!("{} arquivo(s) ZIP encontrado(s)", zip_files.len()));
     }
     
+    // Detecta o mês de referência antes de qualquer trabalho pesado
+    let referencia = referencia::detectar(input_dir, opcoes.referencia.as_deref(), &zip_files)?;
+    ui::print_info(&format!("Mês de referência: {} ({})", referencia.mes, referencia.origem));
+    let inicio = Instant::now();
+    let mut etapas = ui::Etapas::new(TOTAL_ETAPAS);
+    
     // Barra de progresso para descompactação
     ui::print_separator();
-    ui::print_step(1, 4, "Descompactando arquivos ZIP");
+    etapa(&mut etapas, progresso, 1, "Descompactando arquivos ZIP e calculando SHA-256");
     
-    let mp = MultiProgress::new();
+    let mp = ui::multi_progress();
     let pb_extract = mp.add(ProgressBar::new(zip_files.len() as u64));
     pb_extract.set_style(
         ProgressStyle::default_bar()
             .template("  [{bar:40.cyan/blue}] {pos}/{len} ({percent}%) | {msg}")?
             .progress_chars("#>-"),
     );
+    // Tamanho e SHA-256 de cada ZIP, gravados em _arquivos_origem
+    let mut arquivos_origem: Vec<Vec<String>> = Vec::with_capacity(zip_files.len());
     for (idx, zip_file) in zip_files.iter().enumerate() {
         let filename = zip_file.file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("arquivo");
         pb_extract.set_message(format!("{} ({}/{})", filename, idx + 1, zip_files.len()));
+        progresso.verificar()?;
+        progresso.arquivo(filename, idx + 1, zip_files.len());
+        let tamanho = fs::metadata(zip_file)?.len();
+        let sha256 = utils::sha256_arquivo(zip_file)?;
+        arquivos_origem.push(vec![filename.to_string(), tamanho.to_string(), sha256]);
         extract_zip(zip_file, output_dir)?;
         pb_extract.inc(1);
     }
     pb_extract.finish_with_message("Descompactação concluída!");
     
-    // Detecta data de referência
-    let data_referencia = detect_data_referencia(output_dir)?;
-    ui::print_info(&format!("Data de referência detectada: {}", data_referencia));
     
     // Cria banco de dados
-    ui::print_step(2, 4, "Criando estrutura do banco de dados");
-    let mut db = Database::new(db_path.to_str().unwrap())?;
+    etapa(&mut etapas, progresso, 2, "Criando estrutura do banco de dados");
+    let mut db = target.open()?;
     db.create_tables()?;
+    db.insert_rows("_arquivos_origem", database::COLUNAS_ARQUIVOS_ORIGEM, &arquivos_origem)?;
     ui::print_success("Estrutura do banco criada com sucesso");
     
+    // Linhas carregadas de cada arquivo, gravadas em _arquivos para o relatório de qualidade
+    let mut arquivos: Vec<Vec<String>> = Vec::new();
+    
     // Carrega tabelas de código (pequenas)
-    ui::print_step(3, 4, "Carregando tabelas de referência");
-    load_codigo_tables(&mut db, output_dir, cleanup, &mp)?;
+    etapa(&mut etapas, progresso, 3, "Carregando tabelas de referência");
+    load_codigo_tables(db.as_mut(), output_dir, cleanup, &mp, &mut arquivos, progresso)?;
     
     // Carrega tabelas grandes
-    ui::print_step(4, 4, "Carregando tabelas principais");
-    load_large_tables(&mut db, output_dir, cleanup, &mp)?;
+    etapa(&mut etapas, progresso, 4, "Carregando tabelas principais");
+    for arquivos_tabela in ARQUIVOS_TABELAS {
+        arquivos.extend(carregar_tabela(
+            db.as_mut(),
+            output_dir,
+            arquivos_tabela,
+            cleanup,
+            &mp,
+            progresso,
+        )?);
+    }
+    db.insert_rows("_arquivos", database::COLUNAS_ARQUIVOS, &arquivos)?;
     
-    // Finaliza processamento
+    // Finaliza processamento; os índices só são criados depois de toda a carga
+    progresso.verificar()?;
     ui::print_info("Finalizando processamento (criando índices, ajustando dados)...");
-    db.finalize_processing(&data_referencia)?;
-    
-    // Estatísticas finais
-    let conn = db.get_connection();
-    let empresas: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM empresas",
-        [],
-        |row| row.get(0),
-    )?;
+    progresso.emitir(Evento::Mensagem { texto: "Criando índices e ajustando dados".to_string() });
+    for table in database::TABELAS_CODIGO {
+        db.create_index(table, "codigo")?;
+    }
+    db.finalize_processing(&referencia.mes)?;
     
-    let estabelecimentos: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM estabelecimento",
-        [],
-        |row| row.get(0),
-    )?;
+    // Contagens por tabela, para as estatísticas finais e para _referencia
+    let mut contagens: Vec<(&str, i64)> = Vec::new();
+    for table in database::TABELAS_PRINCIPAIS.iter().chain(database::TABELAS_CODIGO) {
+        contagens.push((table, db.count_rows(table)?));
+    }
+    let contagem = |table: &str| {
+        contagens.iter().find(|(t, _)| *t == table).map_or(0, |(_, qtde)| *qtde)
+    };
+    let empresas = contagem("empresas");
+    let estabelecimentos = contagem("estabelecimento");
+    let socios = contagem("socios");
     
-    let socios: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM socios",
-        [],
-        |row| row.get(0),
+    // Otimiza o banco para consulta
+    if vacuum {
+        etapa(&mut etapas, progresso, 5, "Otimizando banco de dados (ANALYZE e VACUUM)");
+    } else {
+        etapa(&mut etapas, progresso, 5, "Otimizando banco de dados (ANALYZE)");
+    }
+    let tamanho_antes = target.file_size();
+    db.optimize(vacuum)?;
+    // Metadados gravados por último para que a duração inclua a otimização
+    db.insert_rows(
+        "_referencia",
+        database::COLUNAS_REFERENCIA,
+        &referencia::metadados(&referencia, &contagens, inicio.elapsed()),
     )?;
+    // Fecha a conexão para que o WAL seja consolidado no arquivo antes de medi-lo
+    drop(db);
+    parcial.concluido = true;
+    let tamanho_depois = target.file_size();
     
     ui::print_separator();
     ui::print_success("Processamento concluído!");
-    ui::print_info(&format!("Arquivo criado: {:?}", db_path));
+    ui::print_info(&format!("Banco criado: {}", target));
     ui::print_info(&format!("Hora de término: {}", Local::now().format("%Y-%m-%d %H:%M:%S")));
     
     ui::print_statistics(&[
@@ -125,6 +265,23 @@ pub fn process_files(input_dir: &str, output_dir: &str, cleanup: bool, auto_yes:
         ("Sócios", socios as u64),
     ]);
     
+    if let (Some(antes), Some(depois)) = (tamanho_antes, tamanho_depois) {
+        ui::print_info(&format!(
+            "Tamanho do banco: {} antes da otimização, {} depois",
+            HumanBytes(antes),
+            HumanBytes(depois)
+        ));
+    }
+    
+    // Relatório de qualidade (consultas específicas do SQLite)
+    match &target {
+        Target::Sqlite(path) => {
+            let path = path.to_string_lossy();
+            relatorio::gerar_relatorio(&path, Some(output_dir))?;
+        }
+        _ => ui::print_info("Relatório de qualidade disponível apenas para bancos SQLite"),
+    }
+    
     Ok(())
 }
 
@@ -150,27 +307,15 @@ fn extract_zip(zip_path: &Path, output_dir: &str) -> Result<()> {
     Ok(())
 }
 
-fn detect_data_referencia(output_dir: &str) -> Result<String> {
-    let files = utils::get_files_by_extension(output_dir, ".EMPRECSV")?;
-    if let Some(first_file) = files.first() {
-        if let Some(filename) = first_file.file_name().and_then(|n| n.to_str()) {
-            if let Some(date) = utils::parse_date_from_filename(filename) {
-                return Ok(date);
-            }
-        }
-    }
-    Ok("xx/xx/2024".to_string())
-}
-
-fn load_codigo_tables(db: &mut Database, output_dir: &str, cleanup: bool, mp: &MultiProgress) -> Result<()> {
-    let tables = vec![
-        (".CNAECSV", "cnae"),
-        (".MOTICSV", "motivo"),
-        (".MUNICCSV", "municipio"),
-        (".NATJUCSV", "natureza_juridica"),
-        (".PAISCSV", "pais"),
-        (".QUALSCSV", "qualificacao_socio"),
-    ];
+fn load_codigo_tables(
+    db: &mut dyn DatabaseWriter,
+    output_dir: &str,
+    cleanup: bool,
+    mp: &MultiProgress,
+    arquivos: &mut Vec<Vec<String>>,
+    progresso: &Progresso,
+) -> Result<()> {
+    let tables = ARQUIVOS_CODIGO;
     
     let pb = mp.add(ProgressBar::new(tables.len() as u64));
     pb.set_style(
@@ -179,11 +324,14 @@ fn load_codigo_tables(db: &mut Database, output_dir: &str, cleanup: bool, mp: &M
             .progress_chars("#>-"),
     );
     
-    for (_idx, (ext, table_name)) in tables.iter().enumerate() {
+    for (idx, (ext, table_name)) in tables.iter().enumerate() {
+        progresso.verificar()?;
         let files = utils::get_files_by_extension(output_dir, ext)?;
         if let Some(file) = files.first() {
             pb.set_message(format!("Carregando: {}", table_name));
-            load_codigo_table(db, file, table_name)?;
+            progresso.arquivo(&file.file_name().unwrap_or_default().to_string_lossy(), idx + 1, tables.len());
+            let linhas = load_codigo_table(db, file, table_name)?;
+            arquivos.push(registro_arquivo(file, table_name, linhas));
             
             if cleanup {
                 fs::remove_file(file)?;
@@ -196,118 +344,28 @@ fn load_codigo_tables(db: &mut Database, output_dir: &str, cleanup: bool, mp: &M
     Ok(())
 }
 
-fn load_codigo_table(db: &mut Database, file_path: &Path, table_name: &str) -> Result<()> {
-    // Usa reader com encoding Latin1, igual aos outros arquivos
-    let reader = utils::create_latin1_reader(file_path)?;
-    let mut rdr = csv::ReaderBuilder::new()
-        .delimiter(b';')
-        .has_headers(false)
-        .from_reader(reader);
-    
-    let tx = db.begin_transaction()?;
-    
-    for result in rdr.records() {
-        let record = result?;
-        if record.len() >= 2 {
-            let codigo = record.get(0).unwrap_or("").to_string();
-            let descricao = record.get(1).unwrap_or("").to_string();
-            
-            let sql = format!("INSERT OR REPLACE INTO {} (codigo, descricao) VALUES (?1, ?2)", table_name);
-            tx.execute(&sql, params![codigo, descricao])?;
-        }
-    }
+fn load_codigo_table(db: &mut dyn DatabaseWriter, file_path: &Path, table_name: &str) -> Result<u64> {
+    let rows = ReceitaReader::<CodigoDescricao>::abrir(file_path)?
+        .map(|registro| registro.map(|r| (r.codigo, r.descricao)))
+        .collect::<Result<Vec<_>, _>>()?;
     
-    tx.commit()?;
+    db.insert_codigo_descricao_batch(table_name, &rows)?;
     
-    // Cria índice
-    db.create_index(table_name, "codigo")?;
-    
-    Ok(())
+    Ok(rows.len() as u64)
 }
 
-fn load_large_tables(db: &mut Database, output_dir: &str, cleanup: bool, mp: &MultiProgress) -> Result<()> {
-    // Empresas
-    load_table_with_polars(
-        db,
-        output_dir,
-        ".EMPRECSV",
-        "empresas",
-        &[
-            "cnpj_basico", "razao_social", "natureza_juridica",
-            "qualificacao_responsavel", "capital_social_str",
-            "porte_empresa", "ente_federativo_responsavel",
-        ],
-        cleanup,
-        mp,
-    )?;
-    
-    // Estabelecimento
-    load_table_with_polars(
-        db,
-        output_dir,
-        ".ESTABELE",
-        "estabelecimento",
-        &[
-            "cnpj_basico", "cnpj_ordem", "cnpj_dv", "matriz_filial",
-            "nome_fantasia", "situacao_cadastral", "data_situacao_cadastral",
-            "motivo_situacao_cadastral", "nome_cidade_exterior", "pais",
-            "data_inicio_atividades", "cnae_fiscal", "cnae_fiscal_secundaria",
-            "tipo_logradouro", "logradouro", "numero", "complemento",
-            "bairro", "cep", "uf", "municipio", "ddd1", "telefone1",
-            "ddd2", "telefone2", "ddd_fax", "fax", "correio_eletronico",
-            "situacao_especial", "data_situacao_especial",
-        ],
-        cleanup,
-        mp,
-    )?;
-    
-    // Sócios
-    load_table_with_polars(
-        db,
-        output_dir,
-        ".SOCIOCSV",
-        "socios_original",
-        &[
-            "cnpj_basico", "identificador_de_socio", "nome_socio",
-            "cnpj_cpf_socio", "qualificacao_socio", "data_entrada_sociedade",
-            "pais", "representante_legal", "nome_representante",
-            "qualificacao_representante_legal", "faixa_etaria",
-        ],
-        cleanup,
-        mp,
-    )?;
-    
-    // Simples
-    load_table_with_polars(
-        db,
-        output_dir,
-        ".SIMPLES.CSV",
-        "simples",
-        &[
-            "cnpj_basico", "opcao_simples", "data_opcao_simples",
-            "data_exclusao_simples", "opcao_mei", "data_opcao_mei",
-            "data_exclusao_mei",
-        ],
-        cleanup,
-        mp,
-    )?;
-    
-    Ok(())
-}
-
-fn load_table_with_polars(
-    db: &mut Database,
+fn carregar_tabela(
+    db: &mut dyn DatabaseWriter,
     output_dir: &str,
-    pattern: &str,
-    table_name: &str,
-    columns: &[&str],
+    arquivos_tabela: &ArquivosTabela,
     cleanup: bool,
     mp: &MultiProgress,
-) -> Result<()> {
-    let files = utils::get_files_by_extension(output_dir, pattern)?;
-    let files: Vec<PathBuf> = files.into_iter().filter(|f| {
-        f.to_string_lossy().contains(pattern.trim_start_matches('.'))
-    }).collect();
+    progresso: &Progresso,
+) -> Result<Vec<Vec<String>>> {
+    let (pattern, table_name, columns) =
+        (arquivos_tabela.padrao, arquivos_tabela.tabela, arquivos_tabela.colunas);
+    // O trecho pode não estar no fim do nome: o do Simples é F.K03200$W.SIMPLES.CSV.D40511
+    let files = utils::get_files_by_extension(output_dir, &format!("{}*", pattern))?;
     
     let pb_table = mp.add(ProgressBar::new(files.len() as u64));
     pb_table.set_style(
@@ -316,14 +374,30 @@ fn load_table_with_polars(
             .progress_chars("#>-"),
     );
     
+    let mut arquivos = Vec::with_capacity(files.len());
     for (idx, file) in files.iter().enumerate() {
         let filename = file.file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("arquivo");
         pb_table.set_message(format!("{} ({}/{})", filename, idx + 1, files.len()));
+        progresso.verificar()?;
+        progresso.arquivo(filename, idx + 1, files.len());
         
-        // Usa csv crate para leitura mais simples e eficiente
-        load_csv_to_sqlite(db, file, table_name, columns, mp)?;
+        // Destinos com leitor de CSV próprio (DuckDB) leem o arquivo direto;
+        // os demais recebem os registros lidos por ReceitaReader
+        let inicio = Instant::now();
+        let linhas = match db.load_csv_native(table_name, columns, file)? {
+            Some(linhas) => {
+                progresso.emitir(Evento::Linhas {
+                    tabela: table_name.to_string(),
+                    linhas,
+                    por_segundo: linhas as f64 / inicio.elapsed().as_secs_f64().max(0.001),
+                });
+                linhas
+            }
+            None => (arquivos_tabela.carregar)(db, file, table_name, mp, progresso)?,
+        };
+        arquivos.push(registro_arquivo(file, table_name, linhas));
         
         if cleanup {
             fs::remove_file(file)?;
@@ -333,35 +407,22 @@ fn load_table_with_polars(
     }
     
     pb_table.finish_with_message(format!("{} concluída!", table_name));
-    Ok(())
+    Ok(arquivos)
 }
 
-fn load_csv_to_sqlite(
-    db: &mut Database,
+fn load_csv_to_database<T: RegistroReceita>(
+    db: &mut dyn DatabaseWriter,
     file_path: &Path,
     table_name: &str,
-    columns: &[&str],
     mp: &MultiProgress,
-) -> Result<()> {
+    progresso: &Progresso,
+) -> Result<u64> {
     // Estima total de linhas pelo tamanho do arquivo (aproximado)
     let file_size = fs::metadata(file_path)?.len();
     let estimated_lines = (file_size / 200) as u64; // Estimativa: ~200 bytes por linha
     
-    let reader = utils::create_latin1_reader(file_path)?;
-    let mut rdr = csv::ReaderBuilder::new()
-        .delimiter(b';')
-        .has_headers(false)
-        .from_reader(reader);
-    
-    let num_cols = columns.len();
-    let placeholders: Vec<String> = (0..num_cols)
-        .map(|i| format!("?{}", i + 1))
-        .collect();
-    let sql = format!(
-        "INSERT INTO {} VALUES ({})",
-        table_name,
-        placeholders.join(", ")
-    );
+    let registros = ReceitaReader::<T>::abrir(file_path)?;
+    let columns = T::COLUNAS;
     
     let pb = mp.add(ProgressBar::new(estimated_lines));
     pb.set_style(
@@ -376,55 +437,37 @@ fn load_csv_to_sqlite(
     let start_time = Instant::now();
     let mut last_update = Instant::now();
     
-    for result in rdr.records() {
-        let record = result?;
-        
-        let mut params: Vec<String> = Vec::new();
-        for i in 0..num_cols {
-            let value = record.get(i).unwrap_or("");
-            params.push(value.to_string());
-        }
-        
-        current_chunk.push(params);
+    for registro in registros {
+        current_chunk.push(registro?.em_campos());
         count += 1;
         
         // Atualiza barra de progresso a cada 10k registros ou a cada segundo
-        if count % 10_000 == 0 || last_update.elapsed().as_secs() >= 1 {
+        if count.is_multiple_of(10_000) || last_update.elapsed().as_secs() >= 1 {
             pb.set_position(count);
             let elapsed = start_time.elapsed().as_secs_f64();
             if elapsed > 0.0 {
                 let speed = count as f64 / elapsed;
                 pb.set_message(format!("{:.0} registros/s", speed));
+                progresso.emitir(Evento::Linhas {
+                    tabela: table_name.to_string(),
+                    linhas: count,
+                    por_segundo: speed,
+                });
             }
             last_update = Instant::now();
         }
         
         if current_chunk.len() >= chunk_size {
+            progresso.verificar()?;
             // Insere chunk
-            let mut tx = db.begin_transaction()?;
-            {
-                let mut stmt = tx.prepare(&sql)?;
-                for params in &current_chunk {
-                    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p as &dyn rusqlite::ToSql).collect();
-                    stmt.execute(&param_refs[..])?;
-                }
-            }
-            tx.commit()?;
+            db.insert_rows(table_name, columns, &current_chunk)?;
             current_chunk.clear();
         }
     }
     
     // Insere chunk final
     if !current_chunk.is_empty() {
-        let mut tx = db.begin_transaction()?;
-        {
-            let mut stmt = tx.prepare(&sql)?;
-            for params in &current_chunk {
-                let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p as &dyn rusqlite::ToSql).collect();
-                stmt.execute(&param_refs[..])?;
-            }
-        }
-        tx.commit()?;
+        db.insert_rows(table_name, columns, &current_chunk)?;
     }
     
     pb.set_position(count);
@@ -435,8 +478,15 @@ fn load_csv_to_sqlite(
         0.0
     };
     pb.finish_with_message(format!("{} registros | {:.0} registros/s", count, avg_speed));
+    progresso.emitir(Evento::Linhas { tabela: table_name.to_string(), linhas: count, por_segundo: avg_speed });
     
-    Ok(())
+    Ok(count)
 }
 
-
+/// Linha da tabela _arquivos: nome do arquivo, tabela de destino e linhas carregadas
+fn registro_arquivo(file: &Path, table_name: &str, linhas: u64) -> Vec<String> {
+    let arquivo = file.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("arquivo");
+    vec![arquivo.to_string(), table_name.to_string(), linhas.to_string()]
+}
@@ -0,0 +1,80 @@
+//! Eventos de progresso do download e do processamento.
+//!
+//! Na linha de comando o progresso aparece nas barras do indicatif e, com
+//! `--log-format json` ou `--log-file`, nos eventos de log; quando os trabalhos rodam pelo
+//! servidor, os mesmos pontos também enviam eventos por um canal, lidos pelo gerenciador
+//! de jobs da API.
+
+use serde::Serialize;
+use utoipa::ToSchema;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use crate::ui;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "tipo", rename_all = "snake_case")]
+pub enum Evento {
+    /// Início de uma etapa do trabalho
+    Fase { passo: usize, total: usize, descricao: String },
+    /// Arquivo em andamento (índice a partir de 1)
+    Arquivo { nome: String, indice: usize, total: usize },
+    /// Bytes baixados de um arquivo
+    Bytes { arquivo: String, atual: u64, total: u64 },
+    /// Linhas carregadas de uma tabela
+    Linhas { tabela: String, linhas: u64, por_segundo: f64 },
+    Mensagem { texto: String },
+}
+
+/// Erro devolvido por um trabalho interrompido por `Progresso::cancelar`
+#[derive(Debug)]
+pub struct Cancelado;
+
+impl fmt::Display for Cancelado {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Operação cancelada")
+    }
+}
+
+impl std::error::Error for Cancelado {}
+
+/// Canal de eventos e sinal de cancelamento de um trabalho. O valor padrão não
+/// envia eventos e nunca é cancelado, que é o caso da linha de comando
+#[derive(Clone, Default)]
+pub struct Progresso {
+    canal: Option<Sender<Evento>>,
+    cancelado: Arc<AtomicBool>,
+}
+
+impl Progresso {
+    pub fn new(canal: Sender<Evento>, cancelado: Arc<AtomicBool>) -> Self {
+        Self { canal: Some(canal), cancelado }
+    }
+
+    /// Registra o evento no log e o envia pelo canal; se ninguém está ouvindo, o envio
+    /// é descartado
+    pub fn emitir(&self, evento: Evento) {
+        ui::registrar_progresso(&evento);
+        if let Some(canal) = &self.canal {
+            let _ = canal.send(evento);
+        }
+    }
+
+    pub fn fase(&self, passo: usize, total: usize, descricao: &str) {
+        self.emitir(Evento::Fase { passo, total, descricao: descricao.to_string() });
+    }
+
+    pub fn arquivo(&self, nome: &str, indice: usize, total: usize) {
+        self.emitir(Evento::Arquivo { nome: nome.to_string(), indice, total });
+    }
+
+    /// Falha com `Cancelado` se o trabalho foi cancelado. Chamado entre arquivos e lotes,
+    /// então o cancelamento é atendido no próximo ponto de verificação
+    pub fn verificar(&self) -> anyhow::Result<()> {
+        if self.cancelado.load(Ordering::Relaxed) {
+            return Err(Cancelado.into());
+        }
+        Ok(())
+    }
+}
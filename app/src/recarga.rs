@@ -0,0 +1,406 @@
+//! Troca do banco consultado pela API sem reiniciar o servidor.
+//!
+//! As consultas pegam uma referência ao banco ativo no início e a usam até o fim,
+//! então uma troca só afeta as consultas seguintes; o banco anterior é fechado quando
+//! a última consulta que o usa termina.
+//!
+//! Um banco novo deixado ao lado do ativo com o sufixo `.pronto` (por `atualizar` ou pelo
+//! processamento iniciado pela API) é publicado pela própria recarga: as consultas passam
+//! a usar o `.pronto` direto, o banco anterior é fechado quando as consultas dele
+//! terminam e só então os arquivos são trocados. Renomear por cima de um banco aberto
+//! deixaria os `-wal`/`-shm` antigos ao lado do arquivo novo (e falha no Windows).
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use crate::consultas::{
+    self, ArquivoOrigem, EmpresaCompleta, EstabelecimentoCompleto, FonteConsulta, SimplesDados, SocioCompleto,
+};
+use crate::cache::{self, CacheRespostas};
+use crate::metricas::{self, FonteMedida};
+use crate::migrations;
+use crate::referencia;
+use crate::ui;
+
+/// Sufixo do banco pronto para ser publicado pelo servidor na próxima recarga
+pub const SUFIXO_PRONTO: &str = ".pronto";
+
+/// Sufixo do banco substituído enquanto a publicação não termina
+const SUFIXO_ANTERIOR: &str = ".anterior";
+
+/// Arquivos auxiliares que existem enquanto algum processo tem o banco aberto
+/// (`-wal` e `-shm` do SQLite, `.wal` do DuckDB)
+const SUFIXOS_EM_USO: &[&str] = &["-wal", "-shm", ".wal"];
+
+/// Tempo máximo de espera pelas consultas em andamento antes de fechar o banco anterior
+const ESPERA_CONSULTAS: Duration = Duration::from_secs(30);
+
+fn com_sufixo(arquivo: &str, sufixo: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}", arquivo, sufixo))
+}
+
+/// Caminho do banco pronto para publicação ao lado de `arquivo`
+pub fn caminho_pronto(arquivo: &str) -> PathBuf {
+    com_sufixo(arquivo, SUFIXO_PRONTO)
+}
+
+/// `true` se há `-wal`/`-shm` ao lado do arquivo, ou seja, se ele está aberto por algum
+/// processo (ou foi fechado sem consolidar o WAL)
+pub fn aberto_por_outro_processo(arquivo: &str) -> bool {
+    SUFIXOS_EM_USO.iter().any(|sufixo| com_sufixo(arquivo, sufixo).exists())
+}
+
+/// Ocupa o lugar da conexão do banco anterior depois que ele é fechado para a troca dos
+/// arquivos; nessa hora ele já não é o banco ativo e nenhuma consulta chega a usá-lo
+struct BancoFechado;
+
+impl BancoFechado {
+    fn erro<T>() -> anyhow::Result<T> {
+        anyhow::bail!("O banco foi fechado para a troca dos arquivos")
+    }
+}
+
+impl FonteConsulta for BancoFechado {
+    fn buscar_estabelecimento(&self, _cnpj: &str) -> anyhow::Result<Option<EstabelecimentoCompleto>> {
+        Self::erro()
+    }
+    fn buscar_empresa(&self, _cnpj_basico: &str) -> anyhow::Result<Option<EmpresaCompleta>> {
+        Self::erro()
+    }
+    fn buscar_socios(&self, _cnpj_basico: &str) -> anyhow::Result<Vec<SocioCompleto>> {
+        Self::erro()
+    }
+    fn buscar_simples(&self, _cnpj_basico: &str) -> anyhow::Result<Option<SimplesDados>> {
+        Self::erro()
+    }
+    fn ping(&self) -> anyhow::Result<()> {
+        Self::erro()
+    }
+    fn ler_referencia(&self) -> anyhow::Result<Vec<(String, String)>> {
+        Self::erro()
+    }
+    fn ler_arquivos_origem(&self) -> anyhow::Result<Vec<ArquivoOrigem>> {
+        Self::erro()
+    }
+    fn versao_esquema(&self) -> anyhow::Result<i64> {
+        Self::erro()
+    }
+}
+
+pub struct BancoAtivo {
+    fonte: Mutex<Box<dyn FonteConsulta>>,
+    pub caminho: String,
+    /// Mês de referência (AAAA-MM) lido de `_referencia`
+    pub referencia: String,
+    pub carregado_em: String,
+    /// Data de geração dos dados, usada no Last-Modified
+    pub modificado_em: SystemTime,
+    /// Respostas prontas deste banco; uma recarga começa com o cache vazio
+    pub cache: CacheRespostas,
+    /// Data de modificação e tamanho do arquivo quando foi aberto
+    assinatura: Option<(SystemTime, u64)>,
+}
+
+impl BancoAtivo {
+    /// Caminho do arquivo em disco, sem o prefixo `duckdb://`
+    pub fn arquivo(&self) -> &str {
+        self.caminho.trim_start_matches("duckdb://")
+    }
+
+    /// Conexão para uma consulta; o tempo de espera pela trava entra nas métricas
+    pub fn conexao(&self) -> MutexGuard<'_, Box<dyn FonteConsulta>> {
+        let inicio = Instant::now();
+        let conexao = self.fonte.lock().unwrap();
+        metricas::registrar_espera_conexao(inicio.elapsed());
+        conexao
+    }
+}
+
+/// Abre um banco para a API e confere se ele pode ser servido: responde a consultas,
+/// está na versão de esquema deste binário e tem um mês de referência válido.
+/// `tamanho_cache` é a quantidade de respostas guardadas em memória
+pub fn abrir_banco(caminho: &str, tamanho_cache: usize) -> Result<BancoAtivo> {
+    let assinatura = assinatura(caminho.trim_start_matches("duckdb://"));
+    let fonte = consultas::abrir_fonte_servidor(caminho)?;
+    fonte.ping().context("O banco não respondeu a uma consulta de teste")?;
+
+    let versao = fonte.versao_esquema().context("Não foi possível ler a versão do esquema do banco")?;
+    if versao != migrations::SCHEMA_VERSION {
+        anyhow::bail!(
+            "O banco {} está na versão de esquema {}, mas este binário espera a versão {}",
+            caminho, versao, migrations::SCHEMA_VERSION
+        );
+    }
+
+    let pares = fonte.ler_referencia().context("Tabela _referencia ausente ou ilegível")?;
+    let mes = pares
+        .iter()
+        .find(|(chave, _)| chave == "CNPJ")
+        .map(|(_, valor)| valor.as_str())
+        .context("A tabela _referencia não tem o mês de referência (chave CNPJ)")?;
+    let referencia = referencia::validar_iso(mes)
+        .with_context(|| format!("Mês de referência inválido em _referencia: '{}'", mes))?;
+    let gerado_em = pares
+        .iter()
+        .find(|(chave, _)| chave == referencia::CHAVE_GERADO_EM)
+        .map(|(_, valor)| valor.as_str());
+    let modificado_em = cache::ultima_modificacao(gerado_em, &referencia);
+
+    Ok(BancoAtivo {
+        fonte: Mutex::new(Box::new(FonteMedida::new(fonte))),
+        caminho: caminho.to_string(),
+        referencia,
+        carregado_em: Local::now().to_rfc3339(),
+        modificado_em,
+        cache: CacheRespostas::new(tamanho_cache),
+        assinatura,
+    })
+}
+
+/// Banco ativo da API e troca por um banco novo
+pub struct Recarga {
+    atual: RwLock<Arc<BancoAtivo>>,
+    tamanho_cache: usize,
+    /// Impede duas trocas simultâneas; as consultas não passam por aqui
+    trava: Mutex<()>,
+}
+
+impl Recarga {
+    /// `tamanho_cache` vale também para os bancos abertos nas recargas
+    pub fn new(banco: BancoAtivo, tamanho_cache: usize) -> Self {
+        metricas::registrar_referencia(&banco.referencia);
+        Self { atual: RwLock::new(Arc::new(banco)), tamanho_cache, trava: Mutex::new(()) }
+    }
+
+    pub fn atual(&self) -> Arc<BancoAtivo> {
+        Arc::clone(&self.atual.read().unwrap())
+    }
+
+    /// Reabre e valida o arquivo do banco ativo e passa a usá-lo, publicando antes o banco
+    /// `.pronto`, se houver. O caminho é sempre o que o servidor recebeu ao iniciar; se a
+    /// validação falhar, o banco ativo continua o mesmo. Retorna o banco anterior e o novo
+    pub fn recarregar(&self) -> Result<(Arc<BancoAtivo>, Arc<BancoAtivo>)> {
+        let _trava = self.trava.lock().unwrap();
+        let caminho = self.atual().caminho.clone();
+
+        let (anterior, novo) = if caminho_pronto(caminho.trim_start_matches("duckdb://")).exists() {
+            self.publicar(&caminho)?
+        } else {
+            // O banco novo é aberto fora do RwLock para não atrasar as consultas
+            let novo = Arc::new(abrir_banco(&caminho, self.tamanho_cache)?);
+            (self.trocar(Arc::clone(&novo)), novo)
+        };
+        metricas::registrar_referencia(&novo.referencia);
+
+        ui::print_success(&format!(
+            "Banco recarregado: {} (referência {}), substituindo {} (referência {})",
+            novo.caminho, novo.referencia, anterior.caminho, anterior.referencia
+        ));
+        Ok((anterior, novo))
+    }
+}
+
+impl Recarga {
+    /// Troca o banco ativo sob a trava de escrita, que fica tomada só durante a troca do
+    /// `Arc`. O banco anterior fecha a conexão quando a última consulta que o usa termina
+    fn trocar(&self, novo: Arc<BancoAtivo>) -> Arc<BancoAtivo> {
+        std::mem::replace(&mut *self.atual.write().unwrap(), novo)
+    }
+
+    /// Troca o arquivo do banco ativo pelo `.pronto`. As consultas passam primeiro para o
+    /// `.pronto`, aberto no lugar onde está; fora da trava, a publicação espera as
+    /// consultas do banco anterior terminarem, fecha-o, troca os arquivos e passa as
+    /// consultas para o banco no caminho definitivo. Se a troca dos arquivos falhar, o
+    /// `.pronto` continua em uso e a próxima recarga tenta de novo
+    fn publicar(&self, caminho: &str) -> Result<(Arc<BancoAtivo>, Arc<BancoAtivo>)> {
+        let arquivo = caminho.trim_start_matches("duckdb://");
+        let pronto = caminho_pronto(arquivo);
+        let mut provisorio = abrir_banco(&format!("{}{}", caminho, SUFIXO_PRONTO), self.tamanho_cache)
+            .with_context(|| format!("O banco pronto {:?} não passou na validação", pronto))?;
+        // Depois da troca, o arquivo é o mesmo, só muda de nome
+        provisorio.caminho = caminho.to_string();
+        let provisorio = Arc::new(provisorio);
+        let mut anterior = self.trocar(Arc::clone(&provisorio));
+        metricas::registrar_referencia(&provisorio.referencia);
+
+        let inicio = Instant::now();
+        while Arc::strong_count(&anterior) > 1 {
+            if inicio.elapsed() > ESPERA_CONSULTAS {
+                anyhow::bail!(
+                    "O banco novo já está em uso, mas consultas ao anterior continuavam em andamento \
+                     após {}s; os arquivos são trocados na próxima recarga",
+                    ESPERA_CONSULTAS.as_secs()
+                );
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        // Fecha a conexão do banco anterior; o resto dele vai na resposta
+        let fechado = Arc::get_mut(&mut anterior).expect("só a publicação tem referência ao banco anterior");
+        drop(std::mem::replace(&mut fechado.fonte, Mutex::new(Box::new(BancoFechado))));
+
+        let novo = Arc::new(self.trocar_arquivos(caminho, &pronto)?);
+        // A conexão do `.pronto` fecha quando as consultas que ainda a usam terminam
+        drop(self.trocar(Arc::clone(&novo)));
+        let _ = fs::remove_file(com_sufixo(arquivo, SUFIXO_ANTERIOR));
+        Ok((anterior, novo))
+    }
+
+    /// Põe o banco pronto no lugar do anterior (já fechado) e o abre, desfazendo a troca se
+    /// ele não abrir. O `.pronto` continua aberto pelas consultas durante o rename, o que o
+    /// Unix permite: a conexão segue o arquivo, e os `-wal`/`-shm` dela têm o nome antigo
+    fn trocar_arquivos(&self, caminho: &str, pronto: &Path) -> Result<BancoAtivo> {
+        let arquivo = caminho.trim_start_matches("duckdb://");
+        if aberto_por_outro_processo(arquivo) {
+            anyhow::bail!("O banco {} está aberto por outro processo; feche-o e tente de novo", arquivo);
+        }
+        let anterior = com_sufixo(arquivo, SUFIXO_ANTERIOR);
+        fs::rename(arquivo, &anterior)
+            .with_context(|| format!("Falha ao mover {} para {:?}", arquivo, anterior))?;
+        if let Err(e) = fs::rename(pronto, arquivo) {
+            let _ = fs::rename(&anterior, arquivo);
+            return Err(e).with_context(|| format!("Falha ao mover {:?} para {}", pronto, arquivo));
+        }
+        abrir_banco(caminho, self.tamanho_cache).inspect_err(|_| {
+            let _ = fs::rename(arquivo, pronto);
+            let _ = fs::rename(&anterior, arquivo);
+        })
+    }
+}
+
+/// Data de modificação e tamanho, usados para perceber que o arquivo foi trocado
+fn assinatura(arquivo: &str) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(arquivo).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Verifica periodicamente o arquivo do banco ativo e recarrega quando ele muda.
+/// A troca só acontece depois que o arquivo fica igual em duas verificações seguidas,
+/// para não abrir um banco ainda sendo copiado. Um banco `.pronto` é publicado na
+/// primeira verificação, pois chega completo, por rename
+pub fn vigiar(recarga: Arc<Recarga>, intervalo: Duration) {
+    thread::spawn(move || {
+        let mut candidata = None;
+        // Arquivo que já falhou na validação não é tentado de novo até mudar outra vez
+        let mut rejeitada = None;
+        loop {
+            thread::sleep(intervalo);
+            // A referência ao banco não pode continuar viva durante a recarga, que espera
+            // as consultas em andamento antes de publicar um banco pronto
+            let (arquivo, assinatura_ativa) = {
+                let banco = recarga.atual();
+                (banco.arquivo().to_string(), banco.assinatura)
+            };
+            let atual = match assinatura(&caminho_pronto(&arquivo).to_string_lossy()) {
+                Some(pronto) if Some(pronto) == rejeitada => continue,
+                Some(pronto) => Some(pronto),
+                None => {
+                    let atual = assinatura(&arquivo);
+                    if atual.is_none() || atual == assinatura_ativa || atual == rejeitada {
+                        candidata = None;
+                        continue;
+                    }
+                    if atual != candidata {
+                        candidata = atual;
+                        continue;
+                    }
+                    atual
+                }
+            };
+
+            candidata = None;
+            if let Err(e) = recarga.recarregar() {
+                ui::print_error(&format!("Novo banco ignorado, o anterior continua ativo: {:#}", e));
+                rejeitada = atual;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    /// Banco mínimo que passa na validação de `abrir_banco`
+    fn criar_banco(caminho: &Path, referencia: &str) {
+        let conn = Connection::open(caminho).unwrap();
+        conn.execute_batch(&format!(
+            "CREATE TABLE estabelecimento (cnpj TEXT);
+             CREATE TABLE _referencia (referencia TEXT, valor TEXT);
+             INSERT INTO _referencia VALUES ('CNPJ', '{}');
+             {}",
+            referencia,
+            migrations::sql_registro_versao_atual()
+        ))
+        .unwrap();
+    }
+
+    fn iniciar(caminho: &Path) -> Recarga {
+        Recarga::new(abrir_banco(caminho.to_str().unwrap(), 10).unwrap(), 10)
+    }
+
+    #[test]
+    fn recarga_nao_espera_consultas_em_andamento() {
+        let pasta = tempfile::tempdir().unwrap();
+        let caminho = pasta.path().join("cnpj.db");
+        criar_banco(&caminho, "2024-04");
+        let recarga = iniciar(&caminho);
+
+        let em_andamento = recarga.atual();
+        let conexao = em_andamento.conexao();
+        Connection::open(&caminho)
+            .unwrap()
+            .execute("UPDATE _referencia SET valor = '2024-05' WHERE referencia = 'CNPJ'", [])
+            .unwrap();
+
+        let (anterior, novo) = recarga.recarregar().unwrap();
+        assert_eq!(anterior.referencia, "2024-04");
+        assert_eq!(novo.referencia, "2024-05");
+        assert_eq!(recarga.atual().referencia, "2024-05");
+        // A consulta em andamento termina no banco em que começou
+        conexao.ping().unwrap();
+    }
+
+    #[test]
+    fn publicacao_do_pronto_responde_durante_a_espera() {
+        let pasta = tempfile::tempdir().unwrap();
+        let caminho = pasta.path().join("cnpj.db");
+        let arquivo = caminho.to_str().unwrap();
+        criar_banco(&caminho, "2024-04");
+        let recarga = iniciar(&caminho);
+        criar_banco(&caminho_pronto(arquivo), "2024-05");
+
+        let em_andamento = recarga.atual();
+        thread::scope(|escopo| {
+            let publicacao = escopo.spawn(|| recarga.recarregar());
+
+            // Enquanto a publicação espera a consulta ao banco anterior, as consultas
+            // novas são atendidas pelo banco pronto, sem esperar trava nenhuma
+            let inicio = Instant::now();
+            while recarga.atual().referencia != "2024-05" {
+                assert!(inicio.elapsed() < Duration::from_secs(5), "o banco pronto não entrou em uso");
+                thread::sleep(Duration::from_millis(10));
+            }
+            recarga.atual().conexao().ping().unwrap();
+            thread::sleep(Duration::from_millis(100));
+            assert!(!publicacao.is_finished());
+            assert!(caminho_pronto(arquivo).exists());
+
+            em_andamento.conexao().ping().unwrap();
+            drop(em_andamento);
+            let (anterior, novo) = publicacao.join().unwrap().unwrap();
+            assert_eq!(anterior.referencia, "2024-04");
+            assert_eq!(novo.referencia, "2024-05");
+            assert_eq!(novo.caminho, arquivo);
+        });
+
+        assert!(!caminho_pronto(arquivo).exists());
+        assert!(!com_sufixo(arquivo, SUFIXO_ANTERIOR).exists());
+        assert_eq!(abrir_banco(arquivo, 0).unwrap().referencia, "2024-05");
+        recarga.atual().conexao().ping().unwrap();
+    }
+}
@@ -0,0 +1,659 @@
+//! Subcomando `recortar`: gera um banco SQLite menor e completo em si, só com os
+//! estabelecimentos que passam pelos filtros (UF, município, CNAE, situação cadastral ou
+//! lista de CNPJs) e com as empresas, sócios e Simples ligados a eles. As tabelas de
+//! código vão inteiras.
+//!
+//! A origem pode ser um banco já processado, copiado por SQL com `ATTACH`, ou a pasta dos
+//! ZIPs da Receita, lidos com `ReceitaReader` sem descompactar nem processar a base toda.
+//! Os dois caminhos usam o mesmo filtro e geram o mesmo esquema do `process`.
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use clap::ValueEnum;
+use indicatif::HumanBytes;
+use rusqlite::{params, Connection};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use zip::ZipArchive;
+use crate::cnae_secundaria;
+use crate::cnpj;
+use crate::consulta;
+use crate::database::{self, Database, DatabaseWriter, Target};
+use crate::leitor::{ReceitaReader, RegistroReceita};
+use crate::migrations;
+use crate::models::{CodigoDescricao, Empresa, Estabelecimento, Simples, Socio};
+use crate::process;
+use crate::referencia;
+use crate::ui;
+use crate::utils;
+
+/// Registros gravados por vez na carga a partir dos ZIPs
+const TAMANHO_LOTE: usize = 50_000;
+
+/// Chave de `_referencia` com a descrição dos filtros aplicados
+pub const CHAVE_RECORTE: &str = "recorte";
+
+/// Situação cadastral; aceita o nome ou o código da Receita
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Situacao {
+    #[value(alias = "01")]
+    Nula,
+    #[value(alias = "02")]
+    Ativa,
+    #[value(alias = "03")]
+    Suspensa,
+    #[value(alias = "04")]
+    Inapta,
+    #[value(alias = "08")]
+    Baixada,
+}
+
+impl Situacao {
+    /// Situação de um código da Receita (ex: `08` → `Baixada`)
+    pub fn do_codigo(codigo: &str) -> Option<Self> {
+        Self::value_variants().iter().copied().find(|situacao| situacao.codigo() == codigo)
+    }
+
+    /// Nome usado na linha de comando e nos relatórios (ex: `baixada`)
+    pub fn nome(self) -> &'static str {
+        match self {
+            Situacao::Nula => "nula",
+            Situacao::Ativa => "ativa",
+            Situacao::Suspensa => "suspensa",
+            Situacao::Inapta => "inapta",
+            Situacao::Baixada => "baixada",
+        }
+    }
+
+    pub fn codigo(self) -> &'static str {
+        match self {
+            Situacao::Nula => "01",
+            Situacao::Ativa => "02",
+            Situacao::Suspensa => "03",
+            Situacao::Inapta => "04",
+            Situacao::Baixada => "08",
+        }
+    }
+}
+
+/// Filtros do recorte. Critérios diferentes se somam (E); os valores de um mesmo
+/// critério são alternativas (OU)
+#[derive(Debug, Clone, Default)]
+pub struct FiltroRecorte {
+    pub ufs: Vec<String>,
+    /// Códigos da Receita (ex: 8105) ou nomes (ex: FLORIANOPOLIS)
+    pub municipios: Vec<String>,
+    /// CNAEs completos (ex: 8630503) ou prefixos (ex: 86 para a divisão de saúde)
+    pub cnaes: Vec<String>,
+    /// Com `cnaes`, ignora os CNAEs secundários
+    pub apenas_cnae_principal: bool,
+    pub situacoes: Vec<Situacao>,
+    /// Arquivo com um CNPJ (14 dígitos) ou CNPJ básico (8 dígitos, a empresa inteira) por linha
+    pub arquivo_cnpjs: Option<String>,
+}
+
+impl FiltroRecorte {
+    fn vazio(&self) -> bool {
+        self.ufs.is_empty()
+            && self.municipios.is_empty()
+            && self.cnaes.is_empty()
+            && self.situacoes.is_empty()
+            && self.arquivo_cnpjs.is_none()
+    }
+
+    /// Descrição gravada em `_referencia` (ex: `uf=SC; cnae=86 (principal ou secundário)`)
+    pub fn descrever(&self) -> String {
+        let mut partes = Vec::new();
+        if !self.ufs.is_empty() {
+            partes.push(format!("uf={}", self.ufs.join(",")));
+        }
+        if !self.municipios.is_empty() {
+            partes.push(format!("municipio={}", self.municipios.join(",")));
+        }
+        if !self.cnaes.is_empty() {
+            let alcance = if self.apenas_cnae_principal { "principal" } else { "principal ou secundário" };
+            partes.push(format!("cnae={} ({})", self.cnaes.join(","), alcance));
+        }
+        if !self.situacoes.is_empty() {
+            let mut nomes: Vec<&str> = Vec::new();
+            for situacao in &self.situacoes {
+                // `ativa` e `02` são a mesma situação
+                if !nomes.contains(&situacao.nome()) {
+                    nomes.push(situacao.nome());
+                }
+            }
+            partes.push(format!("situacao={}", nomes.join(",")));
+        }
+        if let Some(arquivo) = &self.arquivo_cnpjs {
+            partes.push(format!("cnpjs={}", arquivo));
+        }
+        partes.join("; ")
+    }
+}
+
+/// De onde vêm os dados
+#[derive(Debug, Clone)]
+pub enum OrigemRecorte {
+    /// Banco SQLite gerado pelo `process`
+    Banco(String),
+    /// Pasta com os ZIPs da Receita
+    Zips(String),
+}
+
+/// Parâmetros do subcomando `recortar`
+#[derive(Debug, Clone)]
+pub struct OpcoesRecorte {
+    pub origem: OrigemRecorte,
+    /// Banco SQLite a criar; não pode existir
+    pub output: String,
+    pub filtro: FiltroRecorte,
+    /// Mês de referência, só para a origem em ZIPs; `None` detecta pelos arquivos
+    pub referencia: Option<String>,
+}
+
+/// Filtro pronto para uso: valores normalizados e municípios por nome já convertidos em códigos
+struct Criterios {
+    ufs: HashSet<String>,
+    municipios: HashSet<String>,
+    cnaes: Vec<String>,
+    apenas_cnae_principal: bool,
+    situacoes: HashSet<&'static str>,
+    cnpjs: HashSet<String>,
+    basicos: HashSet<String>,
+    com_lista: bool,
+}
+
+/// Campos do estabelecimento usados pelos filtros
+struct CamposFiltro<'a> {
+    cnpj_basico: &'a str,
+    cnpj: &'a str,
+    uf: &'a str,
+    municipio: &'a str,
+    cnae_fiscal: &'a str,
+    cnae_fiscal_secundaria: &'a str,
+    situacao_cadastral: &'a str,
+}
+
+impl Criterios {
+    /// `municipios` é a tabela de código (código, nome), usada para os municípios por nome
+    fn novo(filtro: &FiltroRecorte, municipios: &[(String, String)]) -> Result<Self> {
+        let mut codigos_municipio = HashSet::new();
+        for valor in &filtro.municipios {
+            let valor = valor.trim();
+            if !valor.is_empty() && valor.bytes().all(|b| b.is_ascii_digit()) {
+                codigos_municipio.insert(valor.to_string());
+                continue;
+            }
+            let nome = normalizar_nome(valor);
+            let encontrados: Vec<&String> = municipios
+                .iter()
+                .filter(|(_, descricao)| normalizar_nome(descricao) == nome)
+                .map(|(codigo, _)| codigo)
+                .collect();
+            if encontrados.is_empty() {
+                anyhow::bail!("Município não encontrado na tabela municipio: {}", valor);
+            }
+            if encontrados.len() > 1 {
+                ui::print_warning(&format!(
+                    "Há {} municípios chamados {}; use --uf para escolher um deles",
+                    encontrados.len(),
+                    valor
+                ));
+            }
+            codigos_municipio.extend(encontrados.into_iter().cloned());
+        }
+
+        let (mut cnpjs, mut basicos) = (HashSet::new(), HashSet::new());
+        if let Some(arquivo) = &filtro.arquivo_cnpjs {
+            let mut invalidos = 0;
+            for valor in consulta::ler_cnpjs(arquivo)? {
+                let digitos = valor.replace(['.', '/', '-'], "");
+                if digitos.len() == 8 && digitos.bytes().all(|b| b.is_ascii_digit()) {
+                    basicos.insert(digitos);
+                } else if let Some(limpo) = cnpj::limpar(&valor) {
+                    cnpjs.insert(limpo);
+                } else {
+                    invalidos += 1;
+                }
+            }
+            if invalidos > 0 {
+                ui::print_warning(&format!("{} linha(s) de {} sem CNPJ válido foram ignoradas", invalidos, arquivo));
+            }
+        }
+
+        Ok(Self {
+            ufs: filtro.ufs.iter().map(|uf| uf.trim().to_uppercase()).collect(),
+            municipios: codigos_municipio,
+            cnaes: filtro
+                .cnaes
+                .iter()
+                .map(|cnae| cnae.chars().filter(char::is_ascii_digit).collect::<String>())
+                .filter(|cnae| !cnae.is_empty())
+                .collect(),
+            apenas_cnae_principal: filtro.apenas_cnae_principal,
+            situacoes: filtro.situacoes.iter().map(|s| s.codigo()).collect(),
+            cnpjs,
+            basicos,
+            com_lista: filtro.arquivo_cnpjs.is_some(),
+        })
+    }
+
+    fn aceita(&self, campos: &CamposFiltro) -> bool {
+        if !self.ufs.is_empty() && !self.ufs.contains(campos.uf) {
+            return false;
+        }
+        if !self.municipios.is_empty() && !self.municipios.contains(campos.municipio) {
+            return false;
+        }
+        if !self.situacoes.is_empty() && !self.situacoes.contains(campos.situacao_cadastral) {
+            return false;
+        }
+        if self.com_lista && !self.cnpjs.contains(campos.cnpj) && !self.basicos.contains(campos.cnpj_basico) {
+            return false;
+        }
+        if !self.cnaes.is_empty() {
+            let principal = self.cnaes.iter().any(|cnae| campos.cnae_fiscal.starts_with(cnae.as_str()));
+            let secundario = !self.apenas_cnae_principal
+                && campos
+                    .cnae_fiscal_secundaria
+                    .split(',')
+                    .any(|codigo| self.cnaes.iter().any(|cnae| codigo.trim().starts_with(cnae.as_str())));
+            if !principal && !secundario {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Nome em maiúsculas e sem acentos, como os da tabela municipio
+fn normalizar_nome(nome: &str) -> String {
+    nome.trim()
+        .to_uppercase()
+        .chars()
+        .map(|c| match c {
+            'Á' | 'À' | 'Â' | 'Ã' | 'Ä' => 'A',
+            'É' | 'È' | 'Ê' | 'Ë' => 'E',
+            'Í' | 'Ì' | 'Î' | 'Ï' => 'I',
+            'Ó' | 'Ò' | 'Ô' | 'Õ' | 'Ö' => 'O',
+            'Ú' | 'Ù' | 'Û' | 'Ü' => 'U',
+            'Ç' => 'C',
+            outro => outro,
+        })
+        .collect()
+}
+
+/// Gera o recorte e mostra as contagens do banco criado
+pub fn recortar(opcoes: &OpcoesRecorte) -> Result<()> {
+    if opcoes.filtro.vazio() {
+        anyhow::bail!("Informe ao menos um filtro (--uf, --municipio, --cnae, --situacao ou --cnpjs)");
+    }
+    let destino = Target::Sqlite(PathBuf::from(&opcoes.output));
+    destino.ensure_empty()?;
+    if let Some(pasta) = Path::new(&opcoes.output).parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(pasta).with_context(|| format!("Falha ao criar diretório: {:?}", pasta))?;
+    }
+
+    ui::print_header("✂️  Recorte da Base de CNPJ");
+    ui::print_info(&format!("Hora de início: {}", Local::now().format("%Y-%m-%d %H:%M:%S")));
+    match &opcoes.origem {
+        OrigemRecorte::Banco(banco) => ui::print_info(&format!("Origem: banco {}", banco)),
+        OrigemRecorte::Zips(pasta) => ui::print_info(&format!("Origem: arquivos ZIP em {}", pasta)),
+    }
+    ui::print_info(&format!("Destino: {}", opcoes.output));
+    ui::print_info(&format!("Filtros: {}", opcoes.filtro.descrever()));
+    let inicio = Instant::now();
+
+    let resultado = match &opcoes.origem {
+        OrigemRecorte::Banco(banco) => recortar_banco(banco, &opcoes.output, &opcoes.filtro),
+        OrigemRecorte::Zips(pasta) => {
+            recortar_zips(pasta, &opcoes.output, &opcoes.filtro, opcoes.referencia.as_deref(), inicio)
+        }
+    };
+    // Um recorte pela metade não é completo em si: melhor não deixar o arquivo
+    if let Err(e) = resultado {
+        Target::Sqlite(PathBuf::from(&opcoes.output)).remove_files();
+        return Err(e);
+    }
+
+    let conn = Connection::open(&opcoes.output)?;
+    let contar = |tabela: &str| -> Result<u64> {
+        let qtde: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", tabela), [], |row| row.get(0))?;
+        Ok(qtde as u64)
+    };
+    let estatisticas = [
+        ("Estabelecimentos", contar("estabelecimento")?),
+        ("Empresas", contar("empresas")?),
+        ("Sócios", contar("socios")?),
+        ("Simples", contar("simples")?),
+    ];
+    drop(conn);
+
+    ui::print_separator();
+    ui::print_success(&format!("Recorte concluído em {:.1}s", inicio.elapsed().as_secs_f64()));
+    ui::print_statistics(&estatisticas);
+    if let Some(tamanho) = destino.file_size() {
+        ui::print_info(&format!("Tamanho do banco: {}", HumanBytes(tamanho)));
+    }
+    if estatisticas[0].1 == 0 {
+        ui::print_warning("Nenhum estabelecimento passou pelos filtros");
+    }
+    Ok(())
+}
+
+/// Como cada tabela do banco de origem vai para o recorte
+enum Copia {
+    Inteira,
+    /// Só as linhas dos estabelecimentos selecionados (coluna `cnpj`)
+    PorCnpj,
+    /// Só as linhas das empresas dos estabelecimentos selecionados (coluna `cnpj_basico`)
+    PorCnpjBasico,
+    /// Metadados da base inteira que não valem para o recorte: só a estrutura
+    SoEstrutura,
+}
+
+fn copia_da_tabela(tabela: &str) -> Option<Copia> {
+    match tabela {
+        _ if database::TABELAS_CODIGO.contains(&tabela) => Some(Copia::Inteira),
+        migrations::TABELA_VERSAO | "_arquivos_origem" | "_referencia" => Some(Copia::Inteira),
+        "estabelecimento" | "cnae_secundaria" => Some(Copia::PorCnpj),
+        "empresas" | "socios" | "simples" => Some(Copia::PorCnpjBasico),
+        "_arquivos" | "_qualidade" => Some(Copia::SoEstrutura),
+        _ => None,
+    }
+}
+
+fn recortar_banco(origem: &str, destino: &str, filtro: &FiltroRecorte) -> Result<()> {
+    if !Path::new(origem).exists() {
+        anyhow::bail!("Banco não encontrado: {}", origem);
+    }
+    {
+        let conn = Connection::open(origem).with_context(|| format!("Falha ao abrir banco: {}", origem))?;
+        migrations::verificar_versao(&conn, origem)?;
+    }
+    let mut etapas = ui::Etapas::new(3);
+
+    let mut db = Database::new(destino)?;
+    db.configure_bulk_load()?;
+    db.execute_with_params("ATTACH DATABASE ?1 AS origem", &[&origem])?;
+
+    etapas.iniciar(1, "Selecionando estabelecimentos");
+    let municipios = {
+        let conn = db.get_connection();
+        let mut stmt = conn.prepare("SELECT codigo, descricao FROM origem.municipio")?;
+        let linhas = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        linhas.collect::<rusqlite::Result<Vec<(String, String)>>>()?
+    };
+    let criterios = Criterios::novo(filtro, &municipios)?;
+    db.execute("CREATE TEMP TABLE selecionados (cnpj TEXT PRIMARY KEY, cnpj_basico TEXT)")?;
+    let total: i64 = db.get_connection().query_row("SELECT COUNT(*) FROM origem.estabelecimento", [], |row| row.get(0))?;
+    let pb = ui::progress_bar(total as u64);
+    {
+        let tx = db.begin_transaction()?;
+        {
+            let mut consulta = tx.prepare(
+                "SELECT cnpj, cnpj_basico, uf, municipio, cnae_fiscal, cnae_fiscal_secundaria, situacao_cadastral \
+                 FROM origem.estabelecimento",
+            )?;
+            let mut inserir = tx.prepare("INSERT OR IGNORE INTO temp.selecionados (cnpj, cnpj_basico) VALUES (?1, ?2)")?;
+            let mut linhas = consulta.query([])?;
+            let mut lidas = 0u64;
+            while let Some(linha) = linhas.next()? {
+                let texto = |i: usize| -> rusqlite::Result<String> {
+                    Ok(linha.get::<_, Option<String>>(i)?.unwrap_or_default())
+                };
+                let (cnpj, cnpj_basico) = (texto(0)?, texto(1)?);
+                let campos = CamposFiltro {
+                    cnpj: &cnpj,
+                    cnpj_basico: &cnpj_basico,
+                    uf: &texto(2)?,
+                    municipio: &texto(3)?,
+                    cnae_fiscal: &texto(4)?,
+                    cnae_fiscal_secundaria: &texto(5)?,
+                    situacao_cadastral: &texto(6)?,
+                };
+                if criterios.aceita(&campos) {
+                    inserir.execute(params![cnpj, cnpj_basico])?;
+                }
+                lidas += 1;
+                if lidas.is_multiple_of(100_000) {
+                    pb.set_position(lidas);
+                }
+            }
+        }
+        tx.commit()?;
+    }
+    pb.finish_and_clear();
+    db.execute("CREATE INDEX temp.idx_selecionados_cnpj_basico ON selecionados (cnpj_basico)")?;
+    let selecionados: i64 = db.get_connection().query_row("SELECT COUNT(*) FROM temp.selecionados", [], |row| row.get(0))?;
+    ui::print_info(&format!("{} de {} estabelecimentos selecionados", selecionados, total));
+
+    etapas.iniciar(2, "Copiando tabelas");
+    let tabelas: Vec<(String, String)> = {
+        let conn = db.get_connection();
+        let mut stmt = conn.prepare(
+            "SELECT name, sql FROM origem.sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?;
+        let linhas = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        linhas.collect::<rusqlite::Result<_>>()?
+    };
+    let mut copiadas = Vec::new();
+    for (tabela, sql) in &tabelas {
+        let Some(copia) = copia_da_tabela(tabela) else {
+            ui::print_verbose(&format!("Tabela {} não faz parte do esquema e não foi copiada", tabela));
+            continue;
+        };
+        db.execute(sql)?;
+        let condicao = match copia {
+            Copia::Inteira => "",
+            Copia::PorCnpj => " WHERE cnpj IN (SELECT cnpj FROM temp.selecionados)",
+            Copia::PorCnpjBasico => " WHERE cnpj_basico IN (SELECT cnpj_basico FROM temp.selecionados)",
+            Copia::SoEstrutura => {
+                copiadas.push(tabela.clone());
+                continue;
+            }
+        };
+        ui::print_verbose(&format!("Copiando {}", tabela));
+        db.execute(&format!("INSERT INTO main.{0} SELECT * FROM origem.{0}{1}", tabela, condicao))?;
+        copiadas.push(tabela.clone());
+    }
+
+    etapas.iniciar(3, "Criando índices e atualizando metadados");
+    let indices: Vec<String> = {
+        let conn = db.get_connection();
+        let mut stmt = conn.prepare(
+            "SELECT tbl_name, sql FROM origem.sqlite_master WHERE type IN ('index', 'view') AND sql IS NOT NULL",
+        )?;
+        let linhas = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        linhas
+            .filter_map(|linha| linha.ok())
+            .filter(|(tabela, _)| copiadas.contains(tabela))
+            .map(|(_, sql)| sql)
+            .collect()
+    };
+    for sql in &indices {
+        db.execute(sql)?;
+    }
+
+    // Contagens e órfãos passam a ser os do recorte; o resto (mês, origem, versões) continua valendo
+    db.execute_with_params(
+        "DELETE FROM _referencia WHERE referencia IN ('cnpj_qtde', 'socios_orfaos', ?1) OR referencia LIKE ?2",
+        &[&CHAVE_RECORTE, &format!("{}%", referencia::PREFIXO_LINHAS)],
+    )?;
+    let mut metadados = vec![
+        vec!["cnpj_qtde".to_string(), db.count_rows("estabelecimento")?.to_string()],
+        vec![CHAVE_RECORTE.to_string(), format!("{} (origem: {})", filtro.descrever(), origem)],
+    ];
+    if copiadas.iter().any(|t| t == "socios") {
+        let orfaos: i64 = db.get_connection().query_row("SELECT COUNT(*) FROM socios WHERE cnpj IS NULL", [], |row| row.get(0))?;
+        metadados.push(vec!["socios_orfaos".to_string(), orfaos.to_string()]);
+    }
+    for tabela in database::TABELAS_PRINCIPAIS.iter().chain(database::TABELAS_CODIGO) {
+        if copiadas.iter().any(|t| t == tabela) {
+            metadados.push(vec![format!("{}{}", referencia::PREFIXO_LINHAS, tabela), db.count_rows(tabela)?.to_string()]);
+        }
+    }
+    db.insert_rows("_referencia", database::COLUNAS_REFERENCIA, &metadados)?;
+
+    db.execute("DETACH DATABASE origem")?;
+    db.optimize(false)?;
+    Ok(())
+}
+
+/// ZIPs da pasta de entrada, pelo tipo do arquivo que contêm
+#[derive(Default)]
+struct ZipsPorTipo {
+    /// Tabela de código e ZIP
+    codigos: Vec<(&'static str, PathBuf)>,
+    empresas: Vec<PathBuf>,
+    estabelecimentos: Vec<PathBuf>,
+    socios: Vec<PathBuf>,
+    simples: Vec<PathBuf>,
+}
+
+impl ZipsPorTipo {
+    fn classificar(zips: &[PathBuf]) -> Result<Self> {
+        let mut tipos = Self::default();
+        for zip in zips {
+            let arquivo = fs::File::open(zip).with_context(|| format!("Falha ao abrir {:?}", zip))?;
+            let mut archive = ZipArchive::new(arquivo).with_context(|| format!("ZIP inválido: {:?}", zip))?;
+            if archive.is_empty() {
+                continue;
+            }
+            let nome = archive.by_index(0)?.name().to_string();
+            if let Some((_, tabela)) = process::ARQUIVOS_CODIGO.iter().find(|(padrao, _)| nome.contains(padrao)) {
+                tipos.codigos.push((tabela, zip.clone()));
+                continue;
+            }
+            let Some(arquivos) = process::ARQUIVOS_TABELAS.iter().find(|a| nome.contains(a.padrao)) else {
+                ui::print_warning(&format!("Tipo de arquivo desconhecido, ignorado: {:?}", zip));
+                continue;
+            };
+            match arquivos.tabela {
+                "empresas" => tipos.empresas.push(zip.clone()),
+                "estabelecimento" => tipos.estabelecimentos.push(zip.clone()),
+                "simples" => tipos.simples.push(zip.clone()),
+                _ => tipos.socios.push(zip.clone()),
+            }
+        }
+        if tipos.estabelecimentos.is_empty() {
+            anyhow::bail!("Nenhum ZIP de estabelecimentos encontrado");
+        }
+        Ok(tipos)
+    }
+}
+
+fn recortar_zips(
+    pasta: &str,
+    destino: &str,
+    filtro: &FiltroRecorte,
+    informada: Option<&str>,
+    inicio: Instant,
+) -> Result<()> {
+    let zips = utils::get_files_by_extension(pasta, ".zip")?;
+    if zips.is_empty() {
+        anyhow::bail!("Nenhum arquivo ZIP encontrado em {}", pasta);
+    }
+    let referencia = referencia::detectar(pasta, informada, &zips)?;
+    ui::print_info(&format!("Mês de referência: {} ({})", referencia.mes, referencia.origem));
+    let tipos = ZipsPorTipo::classificar(&zips)?;
+    let mut etapas = ui::Etapas::new(4);
+
+    let mut db = Target::Sqlite(PathBuf::from(destino)).open()?;
+    db.create_tables()?;
+
+    etapas.iniciar(1, "Carregando tabelas de referência");
+    let mut municipios = Vec::new();
+    for (tabela, zip) in &tipos.codigos {
+        let linhas = ReceitaReader::<CodigoDescricao>::abrir_zip(zip, None)?
+            .map(|registro| registro.map(|r| (r.codigo, r.descricao)))
+            .collect::<Result<Vec<_>, _>>()?;
+        db.insert_codigo_descricao_batch(tabela, &linhas)?;
+        if *tabela == "municipio" {
+            municipios = linhas;
+        }
+    }
+    let criterios = Criterios::novo(filtro, &municipios)?;
+
+    etapas.iniciar(2, "Selecionando estabelecimentos");
+    let mut basicos = HashSet::new();
+    copiar_filtrados::<Estabelecimento>(db.as_mut(), &tipos.estabelecimentos, "estabelecimento", |e| {
+        let cnpj = e.cnpj();
+        let aceito = criterios.aceita(&CamposFiltro {
+            cnpj_basico: &e.cnpj_basico,
+            cnpj: &cnpj,
+            uf: &e.uf,
+            municipio: &e.municipio,
+            cnae_fiscal: &e.cnae_fiscal,
+            cnae_fiscal_secundaria: &e.cnae_fiscal_secundaria,
+            situacao_cadastral: &e.situacao_cadastral,
+        });
+        if aceito {
+            basicos.insert(e.cnpj_basico.clone());
+        }
+        aceito
+    })?;
+    ui::print_info(&format!("{} empresa(s) com estabelecimentos selecionados", basicos.len()));
+
+    etapas.iniciar(3, "Copiando empresas, sócios e Simples");
+    copiar_filtrados::<Empresa>(db.as_mut(), &tipos.empresas, "empresas", |e| basicos.contains(&e.cnpj_basico))?;
+    copiar_filtrados::<Socio>(db.as_mut(), &tipos.socios, "socios_original", |s| basicos.contains(&s.cnpj_basico))?;
+    copiar_filtrados::<Simples>(db.as_mut(), &tipos.simples, "simples", |s| basicos.contains(&s.cnpj_basico))?;
+
+    etapas.iniciar(4, "Criando índices e gravando metadados");
+    for tabela in database::TABELAS_CODIGO {
+        db.create_index(tabela, "codigo")?;
+    }
+    db.finalize_processing(&referencia.mes)?;
+    let mut contagens: Vec<(&str, i64)> = Vec::new();
+    for tabela in database::TABELAS_PRINCIPAIS.iter().chain(database::TABELAS_CODIGO) {
+        contagens.push((tabela, db.count_rows(tabela)?));
+    }
+    db.optimize(false)?;
+    let mut metadados = referencia::metadados(&referencia, &contagens, inicio.elapsed());
+    metadados.push(vec![CHAVE_RECORTE.to_string(), format!("{} (origem: {})", filtro.descrever(), pasta)]);
+    db.insert_rows("_referencia", database::COLUNAS_REFERENCIA, &metadados)?;
+    drop(db);
+    etapas.encerrar();
+
+    // Mesmo passo que se faria depois do `process`, para o recorte já sair completo
+    cnae_secundaria::create_cnae_secundaria_table(destino, false)
+}
+
+/// Grava em `tabela` os registros dos ZIPs aceitos por `aceita`, em lotes
+fn copiar_filtrados<T: RegistroReceita>(
+    db: &mut dyn DatabaseWriter,
+    zips: &[PathBuf],
+    tabela: &str,
+    mut aceita: impl FnMut(&T) -> bool,
+) -> Result<()> {
+    let pb = ui::progress_bar(zips.len() as u64);
+    let mut lote = Vec::with_capacity(TAMANHO_LOTE);
+    let (mut lidos, mut gravados) = (0u64, 0u64);
+    for zip in zips {
+        let leitor = ReceitaReader::<T>::abrir_zip(zip, None)?;
+        pb.set_message(leitor.origem().to_string());
+        for registro in leitor {
+            let registro = registro?;
+            lidos += 1;
+            if aceita(&registro) {
+                lote.push(registro.em_campos());
+                if lote.len() >= TAMANHO_LOTE {
+                    db.insert_rows(tabela, T::COLUNAS, &lote)?;
+                    gravados += lote.len() as u64;
+                    lote.clear();
+                }
+            }
+        }
+        pb.inc(1);
+    }
+    if !lote.is_empty() {
+        db.insert_rows(tabela, T::COLUNAS, &lote)?;
+        gravados += lote.len() as u64;
+    }
+    pb.finish_and_clear();
+    ui::print_verbose(&format!("{}: {} de {} registros", tabela, gravados, lidos));
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Local};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use zip::ZipArchive;
+use crate::download;
+use crate::migrations;
+use crate::ui;
+
+/// Mês de referência dos dados (AAAA-MM) e de onde ele foi obtido.
+/// A URL de origem só é conhecida quando os arquivos vieram do `download`
+#[derive(Debug, Clone)]
+pub struct Referencia {
+    pub mes: String,
+    pub origem: String,
+    pub url: Option<String>,
+}
+
+/// Normaliza um mês no formato ISO `AAAA-MM` (aceita a barra final das pastas do site)
+pub fn validar_iso(valor: &str) -> Option<String> {
+    let valor = valor.trim().trim_end_matches('/');
+    let (ano, mes) = valor.split_once('-')?;
+    if ano.len() != 4 || mes.len() != 2 {
+        return None;
+    }
+    let ano: u32 = ano.parse().ok()?;
+    let mes: u32 = mes.parse().ok()?;
+    if !(2000..=2999).contains(&ano) || !(1..=12).contains(&mes) {
+        return None;
+    }
+    Some(format!("{:04}-{:02}", ano, mes))
+}
+
+/// Extrai o mês de um nome no padrão da Receita (`K3241.K03200Y0.D40511.EMPRECSV`).
+/// O trecho `D40511` traz só o último dígito do ano, então a década é escolhida pelo
+/// ano mais recente, até `ano_base`, que termina nesse dígito
+fn referencia_de_nome(nome: &str, ano_base: i32) -> Option<String> {
+    nome.split('.').find_map(|parte| {
+        let digitos = parte.strip_prefix('D')?;
+        if digitos.len() != 5 || !digitos.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let digito_ano: i32 = digitos[0..1].parse().ok()?;
+        let mes: u32 = digitos[1..3].parse().ok()?;
+        if !(1..=12).contains(&mes) {
+            return None;
+        }
+        let ano = ano_base - (ano_base - digito_ano).rem_euclid(10);
+        Some(format!("{:04}-{:02}", ano, mes))
+    })
+}
+
+/// Referências encontradas nos nomes das entradas dos ZIPs, com a quantidade de cada uma
+fn referencias_dos_zips(zip_files: &[PathBuf]) -> Result<BTreeMap<String, usize>> {
+    let ano_atual = Local::now().year();
+    let mut encontradas = BTreeMap::new();
+    for zip_path in zip_files {
+        let file = fs::File::open(zip_path)
+            .with_context(|| format!("Falha ao abrir {:?}", zip_path))?;
+        let mut archive = ZipArchive::new(file)
+            .with_context(|| format!("ZIP inválido: {:?}", zip_path))?;
+        for i in 0..archive.len() {
+            let entrada = archive.by_index(i)?;
+            // A data gravada na entrada resolve a década; se for implausível, usa o ano atual
+            let ano_entrada = entrada.last_modified().year() as i32;
+            let ano_base = if (2000..=ano_atual).contains(&ano_entrada) { ano_entrada } else { ano_atual };
+            if let Some(mes) = referencia_de_nome(entrada.name(), ano_base) {
+                *encontradas.entry(mes).or_insert(0) += 1;
+            }
+        }
+    }
+    Ok(encontradas)
+}
+
+/// Determina o mês de referência, nesta ordem: valor informado, manifesto do download,
+/// nome da pasta de entrada, nomes das entradas dos ZIPs e data de modificação dos ZIPs.
+/// Falha se nenhuma fonte permitir determiná-lo
+pub fn detectar(input_dir: &str, informada: Option<&str>, zip_files: &[PathBuf]) -> Result<Referencia> {
+    if let Some(valor) = informada {
+        let mes = validar_iso(valor)
+            .with_context(|| format!("Referência inválida '{}': use o formato AAAA-MM", valor))?;
+        // A URL do manifesto só vale se ele for do mesmo mês informado
+        let url = download::ler_manifesto(input_dir)?
+            .filter(|m| validar_iso(&m.referencia).as_deref() == Some(mes.as_str()))
+            .map(|m| m.url);
+        return Ok(Referencia { mes, origem: "informada na linha de comando".to_string(), url });
+    }
+
+    if let Some(manifesto) = download::ler_manifesto(input_dir)? {
+        let mes = validar_iso(&manifesto.referencia).with_context(|| {
+            format!(
+                "O manifesto em {} tem uma referência inválida: '{}'",
+                input_dir, manifesto.referencia
+            )
+        })?;
+        return Ok(Referencia {
+            mes,
+            origem: format!("{} do download", download::ARQUIVO_MANIFESTO),
+            url: Some(manifesto.url),
+        });
+    }
+
+    let pasta = fs::canonicalize(input_dir).unwrap_or_else(|_| PathBuf::from(input_dir));
+    if let Some(mes) = pasta
+        .ancestors()
+        .filter_map(|p| p.file_name().and_then(|n| n.to_str()))
+        .find_map(validar_iso)
+    {
+        return Ok(Referencia { mes, origem: format!("nome da pasta {}", pasta.display()), url: None });
+    }
+
+    let encontradas = referencias_dos_zips(zip_files)?;
+    // Em caso de empate vence o mês mais recente (o último na ordem do BTreeMap)
+    if let Some((mes, _)) = encontradas.iter().max_by_key(|(_, qtde)| **qtde) {
+        if encontradas.len() > 1 {
+            ui::print_warning(&format!(
+                "Os arquivos têm referências diferentes ({}); usando a mais frequente",
+                encontradas.keys().cloned().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        return Ok(Referencia {
+            mes: mes.clone(),
+            origem: "nomes dos arquivos nos ZIPs".to_string(),
+            url: None,
+        });
+    }
+
+    let mais_recente = zip_files
+        .iter()
+        .filter_map(|f| fs::metadata(f).and_then(|m| m.modified()).ok())
+        .max();
+    if let Some(modificado) = mais_recente {
+        let mes = DateTime::<Local>::from(modificado).format("%Y-%m").to_string();
+        ui::print_warning(&format!(
+            "Referência estimada pela data de modificação dos arquivos ZIP ({}). \
+             Confira e, se necessário, informe com --referencia AAAA-MM",
+            mes
+        ));
+        return Ok(Referencia { mes, origem: "data de modificação dos ZIPs".to_string(), url: None });
+    }
+
+    anyhow::bail!(
+        "Não foi possível determinar o mês de referência dos arquivos em {}. \
+         Informe com --referencia AAAA-MM",
+        input_dir
+    )
+}
+
+/// Chaves de `_referencia` gravadas ao final do processamento, além de `CNPJ` (o mês),
+/// `cnpj_qtde` e `socios_orfaos`, que vêm de `finalize_processing`
+pub const CHAVE_URL_ORIGEM: &str = "url_origem";
+pub const CHAVE_GERADO_EM: &str = "gerado_em";
+pub const CHAVE_VERSAO_FERRAMENTA: &str = "versao_ferramenta";
+pub const CHAVE_VERSAO_ESQUEMA: &str = "versao_esquema";
+pub const CHAVE_DURACAO: &str = "duracao_segundos";
+/// Prefixo das contagens por tabela (`linhas_empresas`, `linhas_socios`, ...)
+pub const PREFIXO_LINHAS: &str = "linhas_";
+
+/// Linhas de `_referencia` com os metadados do processamento que acabou de terminar
+pub fn metadados(referencia: &Referencia, contagens: &[(&str, i64)], duracao: Duration) -> Vec<Vec<String>> {
+    let mut linhas = vec![
+        vec![CHAVE_GERADO_EM.to_string(), Local::now().to_rfc3339()],
+        vec![CHAVE_VERSAO_FERRAMENTA.to_string(), env!("CARGO_PKG_VERSION").to_string()],
+        vec![CHAVE_VERSAO_ESQUEMA.to_string(), migrations::SCHEMA_VERSION.to_string()],
+        vec![CHAVE_DURACAO.to_string(), format!("{:.1}", duracao.as_secs_f64())],
+    ];
+    if let Some(url) = &referencia.url {
+        linhas.push(vec![CHAVE_URL_ORIGEM.to_string(), url.clone()]);
+    }
+    for (tabela, qtde) in contagens {
+        linhas.push(vec![format!("{}{}", PREFIXO_LINHAS, tabela), qtde.to_string()]);
+    }
+    linhas
+}
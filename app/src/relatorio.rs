@@ -0,0 +1,505 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::cnpj;
+use crate::database;
+use crate::migrations;
+use crate::ui;
+use crate::utils;
+
+const MAX_EXEMPLOS: usize = 10;
+
+pub const ARQUIVO_JSON: &str = "relatorio-qualidade.json";
+pub const ARQUIVO_HTML: &str = "relatorio-qualidade.html";
+
+/// Colunas de código e a tabela de descrição onde o código deveria existir
+const CHAVES_CODIGO: &[(&str, &str, &str)] = &[
+    ("empresas", "natureza_juridica", "natureza_juridica"),
+    ("empresas", "qualificacao_responsavel", "qualificacao_socio"),
+    ("estabelecimento", "motivo_situacao_cadastral", "motivo"),
+    ("estabelecimento", "pais", "pais"),
+    ("estabelecimento", "cnae_fiscal", "cnae"),
+    ("estabelecimento", "municipio", "municipio"),
+    ("socios", "qualificacao_socio", "qualificacao_socio"),
+    ("socios", "pais", "pais"),
+    ("socios", "qualificacao_representante_legal", "qualificacao_socio"),
+];
+
+/// Colunas de data no formato AAAAMMDD
+const COLUNAS_DATA: &[(&str, &str)] = &[
+    ("estabelecimento", "data_situacao_cadastral"),
+    ("estabelecimento", "data_inicio_atividades"),
+    ("estabelecimento", "data_situacao_especial"),
+    ("socios", "data_entrada_sociedade"),
+    ("simples", "data_opcao_simples"),
+    ("simples", "data_exclusao_simples"),
+    ("simples", "data_opcao_mei"),
+    ("simples", "data_exclusao_mei"),
+];
+
+/// Campos que todo registro deveria ter preenchidos
+const CAMPOS_OBRIGATORIOS: &[(&str, &str)] = &[
+    ("empresas", "razao_social"),
+    ("empresas", "natureza_juridica"),
+    ("estabelecimento", "cnpj_ordem"),
+    ("estabelecimento", "cnpj_dv"),
+    ("estabelecimento", "matriz_filial"),
+    ("estabelecimento", "situacao_cadastral"),
+    ("estabelecimento", "cnae_fiscal"),
+    ("estabelecimento", "data_inicio_atividades"),
+    ("socios", "identificador_de_socio"),
+    ("socios", "nome_socio"),
+    ("simples", "opcao_simples"),
+];
+
+#[derive(Debug, Serialize)]
+pub struct RelatorioQualidade {
+    pub gerado_em: String,
+    pub banco: String,
+    pub data_referencia: Option<String>,
+    pub linhas_por_tabela: Vec<ContagemTabela>,
+    pub linhas_por_arquivo: Vec<ContagemArquivo>,
+    pub chaves_orfas: Vec<Ocorrencia>,
+    pub cnpjs_duplicados: Vec<Ocorrencia>,
+    pub digitos_verificadores_invalidos: Vec<Ocorrencia>,
+    pub datas_anomalas: Vec<Ocorrencia>,
+    pub campos_obrigatorios_vazios: Vec<Ocorrencia>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContagemTabela {
+    pub tabela: String,
+    pub linhas: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContagemArquivo {
+    pub arquivo: String,
+    pub tabela: String,
+    pub linhas: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Ocorrencia {
+    pub tabela: String,
+    pub coluna: String,
+    pub descricao: String,
+    pub quantidade: u64,
+    pub exemplos: Vec<String>,
+}
+
+/// Verificação feita linha a linha; as de uma mesma tabela rodam numa única varredura
+enum Regra {
+    CodigoAusente(&'static str),
+    DataZerada,
+    DataInvalida,
+    DataFutura,
+    Vazio,
+}
+
+struct Verificacao {
+    tabela: &'static str,
+    coluna: &'static str,
+    regra: Regra,
+}
+
+impl Verificacao {
+    /// Condição SQL sobre o alias `x` que identifica a linha com problema
+    fn condicao(&self, hoje: &str) -> String {
+        let c = format!("x.{}", self.coluna);
+        let data_invalida = format!(
+            "(length({c}) <> 8 OR {c} GLOB '*[^0-9]*' \
+             OR substr({c}, 5, 2) NOT BETWEEN '01' AND '12' \
+             OR substr({c}, 7, 2) NOT BETWEEN '01' AND '31')",
+            c = c
+        );
+        match self.regra {
+            Regra::CodigoAusente(tabela_codigo) => format!(
+                "({c} <> '' AND NOT EXISTS (SELECT 1 FROM {t} l WHERE l.codigo = {c}))",
+                c = c,
+                t = tabela_codigo
+            ),
+            Regra::DataZerada => format!("({} = '00000000')", c),
+            Regra::DataInvalida => format!("({c} <> '' AND {c} <> '00000000' AND {inv})", c = c, inv = data_invalida),
+            Regra::DataFutura => format!("({c} > '{h}' AND NOT {inv})", c = c, h = hoje, inv = data_invalida),
+            Regra::Vazio => format!("({c} IS NULL OR {c} = '')", c = c),
+        }
+    }
+
+    fn descricao(&self) -> String {
+        match self.regra {
+            Regra::CodigoAusente(tabela_codigo) => format!("Código ausente da tabela {}", tabela_codigo),
+            Regra::DataZerada => "Data zerada (00000000)".to_string(),
+            Regra::DataInvalida => "Data inválida".to_string(),
+            Regra::DataFutura => "Data no futuro".to_string(),
+            Regra::Vazio => "Campo obrigatório vazio".to_string(),
+        }
+    }
+
+    /// Para campos vazios o exemplo é o CNPJ básico da linha; nos demais, o próprio valor
+    fn coluna_exemplo(&self) -> &'static str {
+        match self.regra {
+            Regra::Vazio => "cnpj_basico",
+            _ => self.coluna,
+        }
+    }
+}
+
+fn verificacoes() -> Vec<Verificacao> {
+    let mut lista = Vec::new();
+    for (tabela, coluna, tabela_codigo) in CHAVES_CODIGO {
+        lista.push(Verificacao { tabela, coluna, regra: Regra::CodigoAusente(tabela_codigo) });
+    }
+    for (tabela, coluna) in COLUNAS_DATA {
+        lista.push(Verificacao { tabela, coluna, regra: Regra::DataZerada });
+        lista.push(Verificacao { tabela, coluna, regra: Regra::DataInvalida });
+        lista.push(Verificacao { tabela, coluna, regra: Regra::DataFutura });
+    }
+    for (tabela, coluna) in CAMPOS_OBRIGATORIOS {
+        lista.push(Verificacao { tabela, coluna, regra: Regra::Vazio });
+    }
+    lista
+}
+
+/// Gera o relatório de qualidade de um banco SQLite, grava o JSON na tabela `_qualidade`
+/// e salva `relatorio-qualidade.json` e `relatorio-qualidade.html` em `output_dir`
+/// (padrão: pasta do banco)
+pub fn gerar_relatorio(db_path: &str, output_dir: Option<&str>) -> Result<RelatorioQualidade> {
+    ui::print_header("🔎 Relatório de Qualidade dos Dados");
+
+    if !Path::new(db_path).exists() {
+        anyhow::bail!("Banco de dados não encontrado: {}", db_path);
+    }
+    let conn = Connection::open(db_path)
+        .with_context(|| format!("Falha ao abrir banco de dados: {}", db_path))?;
+
+    let relatorio = montar_relatorio(&conn, db_path)?;
+    let json = serde_json::to_string_pretty(&relatorio)?;
+
+    conn.execute_batch(database::SQL_TABELAS_CONTROLE)?;
+    conn.execute("DELETE FROM _qualidade", [])?;
+    conn.execute(
+        "INSERT INTO _qualidade (gerado_em, relatorio) VALUES (?1, ?2)",
+        params![relatorio.gerado_em, json],
+    )?;
+
+    let pasta = match output_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(db_path).parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    if let Some(dir) = pasta.to_str().filter(|d| !d.is_empty()) {
+        utils::ensure_dir(dir)?;
+    }
+    let caminho_json = pasta.join(ARQUIVO_JSON);
+    let caminho_html = pasta.join(ARQUIVO_HTML);
+    fs::write(&caminho_json, &json)
+        .with_context(|| format!("Falha ao gravar {:?}", caminho_json))?;
+    fs::write(&caminho_html, gerar_html(&relatorio))
+        .with_context(|| format!("Falha ao gravar {:?}", caminho_html))?;
+
+    let total = |ocorrencias: &[Ocorrencia]| ocorrencias.iter().map(|o| o.quantidade).sum::<u64>();
+    ui::print_statistics(&[
+        ("Chaves órfãs", total(&relatorio.chaves_orfas)),
+        ("CNPJs duplicados", total(&relatorio.cnpjs_duplicados)),
+        ("Dígitos verificadores inválidos", total(&relatorio.digitos_verificadores_invalidos)),
+        ("Datas anômalas", total(&relatorio.datas_anomalas)),
+        ("Campos obrigatórios vazios", total(&relatorio.campos_obrigatorios_vazios)),
+    ]);
+    ui::print_success(&format!(
+        "Relatório salvo em {} e {} (e na tabela _qualidade)",
+        caminho_json.display(),
+        caminho_html.display()
+    ));
+
+    Ok(relatorio)
+}
+
+fn montar_relatorio(conn: &Connection, db_path: &str) -> Result<RelatorioQualidade> {
+    let agora = Local::now();
+    let hoje = agora.format("%Y%m%d").to_string();
+
+    let data_referencia = if migrations::tabela_existe(conn, "_referencia")? {
+        conn.query_row(
+            "SELECT valor FROM _referencia WHERE referencia = 'CNPJ'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?
+    } else {
+        None
+    };
+
+    let mut relatorio = RelatorioQualidade {
+        gerado_em: agora.to_rfc3339(),
+        banco: db_path.to_string(),
+        data_referencia,
+        linhas_por_tabela: Vec::new(),
+        linhas_por_arquivo: Vec::new(),
+        chaves_orfas: Vec::new(),
+        cnpjs_duplicados: Vec::new(),
+        digitos_verificadores_invalidos: Vec::new(),
+        datas_anomalas: Vec::new(),
+        campos_obrigatorios_vazios: Vec::new(),
+    };
+
+    // Estabelecimentos sem empresa e sócios sem matriz
+    if migrations::tabela_existe(conn, "estabelecimento")? && migrations::tabela_existe(conn, "empresas")? {
+        relatorio.chaves_orfas.push(contar_ocorrencia(
+            conn,
+            "estabelecimento",
+            "cnpj_basico",
+            "Estabelecimento sem registro em empresas",
+            "SELECT {} FROM estabelecimento x \
+             WHERE NOT EXISTS (SELECT 1 FROM empresas e WHERE e.cnpj_basico = x.cnpj_basico)",
+            "x.cnpj",
+        )?);
+    }
+    if migrations::tabela_existe(conn, "socios")? {
+        relatorio.chaves_orfas.push(contar_ocorrencia(
+            conn,
+            "socios",
+            "cnpj",
+            "Sócio de empresa sem matriz na base",
+            "SELECT {} FROM socios x WHERE x.cnpj IS NULL OR x.cnpj = ''",
+            "DISTINCT x.cnpj_basico",
+        )?);
+    }
+
+    // Verificações por coluna, uma varredura por tabela
+    let todas = verificacoes();
+    for tabela in ["empresas", "estabelecimento", "socios", "simples"] {
+        if !migrations::tabela_existe(conn, tabela)? {
+            continue;
+        }
+        ui::print_info(&format!("Verificando {}...", tabela));
+
+        let da_tabela: Vec<&Verificacao> = todas.iter().filter(|v| v.tabela == tabela).collect();
+        let somas: Vec<String> = da_tabela
+            .iter()
+            .map(|v| format!("COALESCE(SUM(CASE WHEN {} THEN 1 ELSE 0 END), 0)", v.condicao(&hoje)))
+            .collect();
+        let sql = format!("SELECT COUNT(*), {} FROM {} x", somas.join(", "), tabela);
+        let contagens: Vec<i64> = conn.query_row(&sql, [], |row| {
+            (0..=da_tabela.len()).map(|i| row.get(i)).collect()
+        })?;
+
+        relatorio.linhas_por_tabela.push(ContagemTabela {
+            tabela: tabela.to_string(),
+            linhas: contagens[0] as u64,
+        });
+
+        for (verificacao, quantidade) in da_tabela.iter().zip(&contagens[1..]) {
+            let exemplos = if *quantidade > 0 {
+                exemplos(
+                    conn,
+                    &format!(
+                        "SELECT DISTINCT x.{} FROM {} x WHERE {} LIMIT {}",
+                        verificacao.coluna_exemplo(),
+                        tabela,
+                        verificacao.condicao(&hoje),
+                        MAX_EXEMPLOS
+                    ),
+                )?
+            } else {
+                Vec::new()
+            };
+            let ocorrencia = Ocorrencia {
+                tabela: tabela.to_string(),
+                coluna: verificacao.coluna.to_string(),
+                descricao: verificacao.descricao(),
+                quantidade: *quantidade as u64,
+                exemplos,
+            };
+            match verificacao.regra {
+                Regra::CodigoAusente(_) => relatorio.chaves_orfas.push(ocorrencia),
+                Regra::Vazio => relatorio.campos_obrigatorios_vazios.push(ocorrencia),
+                _ => relatorio.datas_anomalas.push(ocorrencia),
+            }
+        }
+    }
+
+    // Chaves duplicadas
+    if migrations::tabela_existe(conn, "estabelecimento")? {
+        relatorio.cnpjs_duplicados.push(contar_duplicados(conn, "estabelecimento", "cnpj")?);
+    }
+    if migrations::tabela_existe(conn, "empresas")? {
+        relatorio.cnpjs_duplicados.push(contar_duplicados(conn, "empresas", "cnpj_basico")?);
+    }
+
+    // Dígitos verificadores
+    if migrations::tabela_existe(conn, "estabelecimento")? {
+        ui::print_info("Conferindo dígitos verificadores...");
+        let mut stmt = conn.prepare("SELECT cnpj FROM estabelecimento")?;
+        let mut rows = stmt.query([])?;
+        let mut invalidos = Ocorrencia {
+            tabela: "estabelecimento".to_string(),
+            coluna: "cnpj".to_string(),
+            descricao: "Dígito verificador inválido".to_string(),
+            quantidade: 0,
+            exemplos: Vec::new(),
+        };
+        while let Some(row) = rows.next()? {
+            let cnpj: String = row.get::<_, Option<String>>(0)?.unwrap_or_default();
+            if !cnpj::dv_valido(&cnpj) {
+                invalidos.quantidade += 1;
+                if invalidos.exemplos.len() < MAX_EXEMPLOS {
+                    invalidos.exemplos.push(cnpj);
+                }
+            }
+        }
+        relatorio.digitos_verificadores_invalidos.push(invalidos);
+    }
+
+    // Linhas por arquivo de origem
+    if migrations::tabela_existe(conn, "_arquivos")? {
+        let mut stmt = conn.prepare("SELECT arquivo, tabela, linhas FROM _arquivos ORDER BY tabela, arquivo")?;
+        relatorio.linhas_por_arquivo = stmt
+            .query_map([], |row| {
+                Ok(ContagemArquivo {
+                    arquivo: row.get(0)?,
+                    tabela: row.get(1)?,
+                    linhas: row.get::<_, i64>(2)? as u64,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+    }
+
+    Ok(relatorio)
+}
+
+fn exemplos(conn: &Connection, sql: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(sql)?;
+    let valores = stmt
+        .query_map([], |row| Ok(row.get::<_, Option<String>>(0)?.unwrap_or_default()))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(valores)
+}
+
+/// `consulta` recebe `{}` no lugar da lista de colunas: `COUNT(*)` para contar e
+/// `coluna_exemplo` para buscar os exemplos
+fn contar_ocorrencia(
+    conn: &Connection,
+    tabela: &str,
+    coluna: &str,
+    descricao: &str,
+    consulta: &str,
+    coluna_exemplo: &str,
+) -> Result<Ocorrencia> {
+    let quantidade: i64 = conn.query_row(&consulta.replace("{}", "COUNT(*)"), [], |row| row.get(0))?;
+    let exemplos = if quantidade > 0 {
+        exemplos(conn, &format!("{} LIMIT {}", consulta.replace("{}", coluna_exemplo), MAX_EXEMPLOS))?
+    } else {
+        Vec::new()
+    };
+    Ok(Ocorrencia {
+        tabela: tabela.to_string(),
+        coluna: coluna.to_string(),
+        descricao: descricao.to_string(),
+        quantidade: quantidade as u64,
+        exemplos,
+    })
+}
+
+/// Conta os valores repetidos de uma coluna que deveria ser única
+fn contar_duplicados(conn: &Connection, tabela: &str, coluna: &str) -> Result<Ocorrencia> {
+    let repetidos = format!(
+        "SELECT {c} FROM {t} WHERE {c} IS NOT NULL GROUP BY {c} HAVING COUNT(*) > 1",
+        c = coluna,
+        t = tabela
+    );
+    let quantidade: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM ({})", repetidos), [], |row| row.get(0))?;
+    let exemplos = if quantidade > 0 {
+        exemplos(conn, &format!("{} LIMIT {}", repetidos, MAX_EXEMPLOS))?
+    } else {
+        Vec::new()
+    };
+    Ok(Ocorrencia {
+        tabela: tabela.to_string(),
+        coluna: coluna.to_string(),
+        descricao: format!("Valor de {} repetido", coluna),
+        quantidade: quantidade as u64,
+        exemplos,
+    })
+}
+
+fn escapar_html(texto: &str) -> String {
+    texto
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn secao_html(html: &mut String, titulo: &str, ocorrencias: &[Ocorrencia]) {
+    html.push_str(&format!("<h2>{}</h2>\n", escapar_html(titulo)));
+    html.push_str("<table>\n<tr><th>Tabela</th><th>Coluna</th><th>Verificação</th><th>Quantidade</th><th>Exemplos</th></tr>\n");
+    for o in ocorrencias {
+        let classe = if o.quantidade > 0 { " class=\"alerta\"" } else { "" };
+        html.push_str(&format!(
+            "<tr{}><td>{}</td><td>{}</td><td>{}</td><td class=\"num\">{}</td><td>{}</td></tr>\n",
+            classe,
+            escapar_html(&o.tabela),
+            escapar_html(&o.coluna),
+            escapar_html(&o.descricao),
+            o.quantidade,
+            escapar_html(&o.exemplos.join(", "))
+        ));
+    }
+    html.push_str("</table>\n");
+}
+
+fn gerar_html(relatorio: &RelatorioQualidade) -> String {
+    let mut html = String::new();
+    html.push_str(
+        "<!DOCTYPE html>\n<html lang=\"pt-BR\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Relatório de Qualidade - CNPJ</title>\n<style>\n\
+         body { font-family: sans-serif; margin: 2em; color: #222; }\n\
+         table { border-collapse: collapse; margin-bottom: 2em; }\n\
+         th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }\n\
+         th { background: #eef; }\n\
+         td.num { text-align: right; }\n\
+         tr.alerta td { background: #fff3cd; }\n\
+         </style>\n</head>\n<body>\n",
+    );
+    html.push_str("<h1>Relatório de Qualidade dos Dados</h1>\n<ul>\n");
+    html.push_str(&format!("<li>Banco: {}</li>\n", escapar_html(&relatorio.banco)));
+    html.push_str(&format!(
+        "<li>Data de referência: {}</li>\n",
+        escapar_html(relatorio.data_referencia.as_deref().unwrap_or("desconhecida"))
+    ));
+    html.push_str(&format!("<li>Gerado em: {}</li>\n</ul>\n", escapar_html(&relatorio.gerado_em)));
+
+    html.push_str("<h2>Linhas por tabela</h2>\n<table>\n<tr><th>Tabela</th><th>Linhas</th></tr>\n");
+    for t in &relatorio.linhas_por_tabela {
+        html.push_str(&format!("<tr><td>{}</td><td class=\"num\">{}</td></tr>\n", escapar_html(&t.tabela), t.linhas));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Linhas por arquivo</h2>\n");
+    if relatorio.linhas_por_arquivo.is_empty() {
+        html.push_str("<p>Contagem por arquivo não disponível para este banco.</p>\n");
+    } else {
+        html.push_str("<table>\n<tr><th>Arquivo</th><th>Tabela</th><th>Linhas</th></tr>\n");
+        for a in &relatorio.linhas_por_arquivo {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td class=\"num\">{}</td></tr>\n",
+                escapar_html(&a.arquivo),
+                escapar_html(&a.tabela),
+                a.linhas
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    secao_html(&mut html, "Chaves órfãs", &relatorio.chaves_orfas);
+    secao_html(&mut html, "CNPJs duplicados", &relatorio.cnpjs_duplicados);
+    secao_html(&mut html, "Dígitos verificadores inválidos", &relatorio.digitos_verificadores_invalidos);
+    secao_html(&mut html, "Datas anômalas", &relatorio.datas_anomalas);
+    secao_html(&mut html, "Campos obrigatórios vazios", &relatorio.campos_obrigatorios_vazios);
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
@@ -1,62 +1,237 @@
+//! Saída do programa: mensagens no terminal, eventos JSON e arquivo de log.
+//!
+//! As funções `print_*` emitem eventos do `tracing` com um campo `evento` que diz o tipo
+//! da mensagem. No formato texto, a camada `Terminal` desenha esses eventos com cores e
+//! símbolos; com `--log-format json`, cada evento vira uma linha JSON no stderr e as barras
+//! do indicatif ficam ocultas. `--log-file` grava os mesmos eventos em um arquivo.
+
+use anyhow::Context as _;
+use clap::ValueEnum;
 use colored::*;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::OpenOptions;
 use std::io::{self, Write};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Registry;
+use crate::progresso::Evento;
+
+/// Alvo dos eventos do programa; os das dependências não são mostrados
+const ALVO: &str = "cnpj_sqlite";
+
+/// Intervalo mínimo entre eventos `linhas` no nível info; os demais ficam no nível debug
+const INTERVALO_LINHAS: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum FormatoLog {
+    /// Mensagens coloridas e barras de progresso
+    #[default]
+    #[value(alias = "text")]
+    Texto,
+    /// Um objeto JSON por linha, no stderr
+    Json,
+}
+
+pub struct OpcoesLog {
+    pub quiet: bool,
+    pub verbose: bool,
+    pub formato: FormatoLog,
+    /// Arquivo onde as mensagens também são gravadas (acrescentadas ao final)
+    pub arquivo: Option<String>,
+}
 
-static mut QUIET: bool = false;
-static mut VERBOSE: bool = false;
+struct Estado {
+    quiet: bool,
+    formato: FormatoLog,
+}
 
-pub fn init(quiet: bool, verbose: bool) {
-    unsafe {
-        QUIET = quiet;
-        VERBOSE = verbose;
+static ESTADO: OnceLock<Estado> = OnceLock::new();
+
+/// Último evento `linhas` registrado no nível info
+static ULTIMAS_LINHAS: Mutex<Option<Instant>> = Mutex::new(None);
+
+pub fn init(opcoes: &OpcoesLog) -> anyhow::Result<()> {
+    let nivel_terminal = if opcoes.quiet {
+        LevelFilter::ERROR
+    } else if opcoes.verbose {
+        LevelFilter::DEBUG
+    } else {
+        LevelFilter::INFO
+    };
+    // O arquivo não é afetado por --quiet
+    let nivel_arquivo = if opcoes.verbose { LevelFilter::DEBUG } else { LevelFilter::INFO };
+
+    let mut camadas: Vec<Box<dyn Layer<Registry> + Send + Sync>> = Vec::new();
+    let terminal = match opcoes.formato {
+        FormatoLog::Texto => Terminal { verbose: opcoes.verbose }.boxed(),
+        FormatoLog::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(false)
+            .with_writer(io::stderr)
+            .boxed(),
+    };
+    camadas.push(terminal.with_filter(Targets::new().with_target(ALVO, nivel_terminal)).boxed());
+
+    if let Some(caminho) = &opcoes.arquivo {
+        let arquivo = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(caminho)
+            .with_context(|| format!("Falha ao abrir o arquivo de log {}", caminho))?;
+        let escritor = Mutex::new(arquivo);
+        let camada = match opcoes.formato {
+            FormatoLog::Texto => tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_target(false)
+                .with_writer(escritor)
+                .boxed(),
+            FormatoLog::Json => tracing_subscriber::fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_current_span(false)
+                .with_span_list(false)
+                .with_writer(escritor)
+                .boxed(),
+        };
+        camadas.push(camada.with_filter(Targets::new().with_target(ALVO, nivel_arquivo)).boxed());
     }
+
+    tracing_subscriber::registry()
+        .with(camadas)
+        .try_init()
+        .context("Falha ao iniciar o log")?;
+    let _ = ESTADO.set(Estado { quiet: opcoes.quiet, formato: opcoes.formato });
+    Ok(())
 }
 
 fn is_quiet() -> bool {
-    unsafe { QUIET }
+    ESTADO.get().is_some_and(|estado| estado.quiet)
 }
 
-fn is_verbose() -> bool {
-    unsafe { VERBOSE }
+fn is_json() -> bool {
+    ESTADO.get().is_some_and(|estado| estado.formato == FormatoLog::Json)
 }
 
 pub fn print_info(message: &str) {
-    if !is_quiet() {
-        println!("{} {}", "ℹ".blue(), message);
-    }
+    tracing::info!(evento = "info", "{}", message);
 }
 
 pub fn print_success(message: &str) {
-    if !is_quiet() {
-        println!("{} {}", "✓".green().bold(), message.green());
-    }
+    tracing::info!(evento = "sucesso", "{}", message);
 }
 
 pub fn print_warning(message: &str) {
-    if !is_quiet() {
-        println!("{} {}", "⚠".yellow().bold(), message.yellow());
-    }
+    tracing::warn!(evento = "aviso", "{}", message);
 }
 
 pub fn print_error(message: &str) {
-    eprintln!("{} {}", "✗".red().bold(), message.red());
+    tracing::error!(evento = "erro", "{}", message);
 }
 
-pub fn print_header(message: &str) {
-    if !is_quiet() {
-        println!("\n{}", message.bold().cyan());
-        println!("{}", "─".repeat(message.len()).cyan());
+/// Erro que encerra o programa, com a cadeia de contexto do anyhow. No formato texto
+/// aparece como antes, com as causas (e o backtrace, se ativado), mesmo com `--quiet`
+pub fn print_erro_fatal(erro: &anyhow::Error) {
+    if !is_json() {
+        eprintln!("Error: {:?}", erro);
     }
+    tracing::error!(evento = "erro_fatal", causas = %format!("{:#}", erro), "{}", erro);
+}
+
+pub fn print_header(message: &str) {
+    tracing::info!(evento = "cabecalho", "{}", message);
 }
 
 pub fn print_verbose(message: &str) {
-    if is_verbose() && !is_quiet() {
-        println!("  {}", message.dimmed());
+    tracing::debug!(evento = "detalhe", "{}", message);
+}
+
+/// Item de uma lista, logo abaixo de uma mensagem
+pub fn print_item(message: &str) {
+    tracing::info!(evento = "item", "{}", message);
+}
+
+/// Etapas numeradas de um comando. Cada `iniciar` encerra a etapa anterior; a última é
+/// encerrada quando o valor sai de escopo, inclusive quando o comando falha
+pub struct Etapas {
+    total: usize,
+    atual: Option<(usize, String, Instant)>,
+}
+
+impl Etapas {
+    pub fn new(total: usize) -> Self {
+        Self { total, atual: None }
+    }
+
+    pub fn iniciar(&mut self, passo: usize, descricao: &str) {
+        self.encerrar();
+        tracing::info!(evento = "etapa_inicio", passo, total = self.total, "{}", descricao);
+        self.atual = Some((passo, descricao.to_string(), Instant::now()));
+    }
+
+    pub fn encerrar(&mut self) {
+        if let Some((passo, descricao, inicio)) = self.atual.take() {
+            let duracao_s = inicio.elapsed().as_secs_f64();
+            tracing::info!(evento = "etapa_fim", passo, total = self.total, duracao_s, "{}", descricao);
+        }
+    }
+}
+
+impl Drop for Etapas {
+    fn drop(&mut self) {
+        self.encerrar();
     }
 }
 
-pub fn print_step(step: usize, total: usize, message: &str) {
-    if !is_quiet() {
-        println!("[{}/{}] {}", step, total, message.bold());
+/// Registra um evento de progresso. No terminal em texto eles aparecem só nas barras;
+/// as fases são registradas por `Etapas`
+pub fn registrar_progresso(evento: &Evento) {
+    match evento {
+        Evento::Fase { .. } => {}
+        Evento::Arquivo { nome, indice, total } => {
+            tracing::info!(evento = "arquivo", arquivo = %nome, indice, total, "{} ({}/{})", nome, indice, total);
+        }
+        Evento::Bytes { arquivo, atual, total } => {
+            tracing::debug!(evento = "bytes", arquivo = %arquivo, atual, total, "{}: {}/{} bytes", arquivo, atual, total);
+        }
+        Evento::Linhas { tabela, linhas, por_segundo } => {
+            let mut ultimo = ULTIMAS_LINHAS.lock().unwrap_or_else(|e| e.into_inner());
+            if ultimo.is_none_or(|instante| instante.elapsed() >= INTERVALO_LINHAS) {
+                *ultimo = Some(Instant::now());
+                tracing::info!(evento = "linhas", tabela = %tabela, linhas, por_segundo, "{}: {} registros", tabela, linhas);
+            } else {
+                tracing::debug!(evento = "linhas", tabela = %tabela, linhas, por_segundo, "{}: {} registros", tabela, linhas);
+            }
+        }
+        Evento::Mensagem { texto } => {
+            tracing::info!(evento = "mensagem", "{}", texto);
+        }
+    }
+}
+
+/// Conjunto de barras do indicatif, oculto com `--log-format json`
+pub fn multi_progress() -> MultiProgress {
+    if is_json() {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    }
+}
+
+/// Barra do indicatif, oculta com `--log-format json`
+pub fn progress_bar(total: u64) -> ProgressBar {
+    if is_json() {
+        ProgressBar::with_draw_target(Some(total), ProgressDrawTarget::hidden())
+    } else {
+        ProgressBar::new(total)
     }
 }
 
@@ -64,14 +239,14 @@ pub fn ask_confirmation(prompt: &str, default: bool) -> io::Result<bool> {
     if is_quiet() {
         return Ok(default);
     }
-    
+
     let default_str = if default { "Y/n" } else { "y/N" };
     print!("{} [{}]: ", prompt.bold(), default_str);
     io::stdout().flush()?;
-    
+
     let mut input = String::new();
     io::stdin().read_line(&mut input)?;
-    
+
     let trimmed = input.trim().to_lowercase();
     if trimmed.is_empty() {
         Ok(default)
@@ -89,19 +264,83 @@ pub fn ask_confirmation_no(prompt: &str) -> io::Result<bool> {
 }
 
 pub fn print_statistics(stats: &[(&str, u64)]) {
-    if is_quiet() {
-        return;
-    }
-    
-    println!("\n{}", "Estatísticas:".bold().cyan());
+    tracing::info!(evento = "estatisticas", "Estatísticas:");
     for (label, value) in stats {
-        println!("  {}: {}", label.bold(), value.to_string().green());
+        tracing::info!(evento = "estatistica", nome = %label, valor = value, "{}: {}", label, value);
     }
 }
 
 pub fn print_separator() {
-    if !is_quiet() {
-        println!("{}", "=".repeat(60).dimmed());
+    tracing::info!(evento = "separador", "");
+}
+
+/// Campos de um evento, lidos pela camada `Terminal`
+#[derive(Default)]
+struct Campos {
+    evento: String,
+    mensagem: String,
+    outros: HashMap<&'static str, String>,
+}
+
+impl Visit for Campos {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "evento" => self.evento = value.to_string(),
+            "message" => self.mensagem = value.to_string(),
+            nome => {
+                self.outros.insert(nome, value.to_string());
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        // `format_args!` e os campos com `%` chegam aqui, já formatados como Display
+        self.record_str(field, &format!("{:?}", value));
     }
 }
 
+impl Campos {
+    fn campo(&self, nome: &str) -> &str {
+        self.outros.get(nome).map(String::as_str).unwrap_or("")
+    }
+}
+
+/// Desenha os eventos no terminal, no formato texto
+struct Terminal {
+    verbose: bool,
+}
+
+impl<S: Subscriber> Layer<S> for Terminal {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut campos = Campos::default();
+        event.record(&mut campos);
+        let message = campos.mensagem.as_str();
+        match campos.evento.as_str() {
+            "info" => println!("{} {}", "ℹ".blue(), message),
+            "sucesso" => println!("{} {}", "✓".green().bold(), message.green()),
+            "aviso" => println!("{} {}", "⚠".yellow().bold(), message.yellow()),
+            "erro" => eprintln!("{} {}", "✗".red().bold(), message.red()),
+            "cabecalho" => {
+                println!("\n{}", message.bold().cyan());
+                println!("{}", "─".repeat(message.len()).cyan());
+            }
+            "detalhe" => println!("  {}", message.dimmed()),
+            "item" => println!("  {} {}", "•".cyan(), message),
+            "etapa_inicio" => {
+                println!("[{}/{}] {}", campos.campo("passo"), campos.campo("total"), message.bold())
+            }
+            "etapa_fim" if self.verbose => {
+                let duracao: f64 = campos.campo("duracao_s").parse().unwrap_or_default();
+                let texto = format!("fim da etapa {}/{} ({:.1}s)", campos.campo("passo"), campos.campo("total"), duracao);
+                println!("  {}", texto.dimmed());
+            }
+            "estatisticas" => println!("\n{}", message.bold().cyan()),
+            "estatistica" => {
+                println!("  {}: {}", campos.campo("nome").bold(), campos.campo("valor").green())
+            }
+            "separador" => println!("{}", "=".repeat(60).dimmed()),
+            // Progresso: no terminal, fica por conta das barras
+            _ => {}
+        }
+    }
+}
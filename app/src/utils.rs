@@ -1,9 +1,9 @@
 use anyhow::{Context, Result};
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
-use encoding_rs;
 use encoding_rs_io::DecodeReaderBytesBuilder;
-use glob;
+use sha2::{Digest, Sha256};
 
 pub fn ensure_dir(path: &str) -> Result<()> {
     if !Path::new(path).exists() {
@@ -27,33 +27,28 @@ pub fn get_files_by_extension(dir: &str, ext: &str) -> Result<Vec<PathBuf>> {
     Ok(files)
 }
 
-pub fn create_latin1_reader(file_path: &Path) -> Result<Box<dyn std::io::Read>> {
+pub fn create_latin1_reader(file_path: &Path) -> Result<Box<dyn Read + Send>> {
     let file = fs::File::open(file_path)
         .with_context(|| format!("Falha ao abrir arquivo: {:?}", file_path))?;
     
+    Ok(latin1_reader(file))
+}
+
+/// Converte para UTF-8 um conteúdo em Latin-1, a codificação dos arquivos da Receita
+pub fn latin1_reader<R: Read + Send + 'static>(reader: R) -> Box<dyn Read + Send> {
     let reader = DecodeReaderBytesBuilder::new()
         .encoding(Some(encoding_rs::WINDOWS_1252)) // Latin1 equivalente
-        .build(file);
+        .build(reader);
     
-    Ok(Box::new(reader))
-}
-
-pub fn parse_date_from_filename(filename: &str) -> Option<String> {
-    // Formato: arquivo.D30610.EMPRECSV -> D30610 -> 10/06/2023
-    if let Some(start) = filename.find('D') {
-        let date_part = &filename[start..start.min(filename.len()) + 6];
-        if date_part.len() == 6 && date_part.starts_with('D') {
-            let year = &date_part[1..2];
-            let month = &date_part[2..4];
-            let day = &date_part[4..6];
-            return Some(format!("{}/{}/202{}", day, month, year));
-        }
-    }
-    None
+    Box::new(reader)
 }
 
-pub fn format_progress(current: usize, total: usize) -> String {
-    let percent = (current as f64 / total as f64) * 100.0;
-    format!("{:.1}% ({}/{})", percent, current, total)
+/// SHA-256 do conteúdo de um arquivo, em hexadecimal
+pub fn sha256_arquivo(file_path: &Path) -> Result<String> {
+    let mut file = fs::File::open(file_path)
+        .with_context(|| format!("Falha ao abrir arquivo: {:?}", file_path))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
 }
 
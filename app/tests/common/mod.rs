@@ -0,0 +1,20 @@
+//! Auxiliares compartilhados pelos testes de integração.
+
+use std::path::Path;
+use std::process::Command;
+
+/// Executa o binário com `args` e falha o teste se ele terminar com erro
+pub fn executar(pasta: &Path, args: &[&str]) {
+    let saida = Command::new(env!("CARGO_BIN_EXE_cnpj-sqlite"))
+        .current_dir(pasta)
+        .args(["--quiet", "--yes"])
+        .args(args)
+        .output()
+        .expect("binário não executou");
+    assert!(
+        saida.status.success(),
+        "cnpj-sqlite {:?} falhou:\n{}",
+        args,
+        String::from_utf8_lossy(&saida.stderr)
+    );
+}
@@ -0,0 +1,66 @@
+//! `comparar` entre um banco processado a partir da amostra sintética e uma cópia
+//! alterada dele, que faz o papel do mês anterior.
+
+mod common;
+
+use common::executar;
+use rusqlite::Connection;
+
+#[test]
+fn comparar_encontra_as_alteracoes_entre_dois_meses() {
+    let pasta = tempfile::tempdir().unwrap();
+    executar(pasta.path(), &["gerar-amostra", "-o", "zips", "-n", "200", "--partes", "2", "-r", "2024-05"]);
+    executar(pasta.path(), &["process", "-i", "zips", "-o", "saida"]);
+    std::fs::copy(pasta.path().join("saida/cnpj.db"), pasta.path().join("antigo.db")).unwrap();
+
+    // O "mês anterior": sem 3 filiais, com um CNPJ que depois sumiu, 2 estabelecimentos
+    // ainda ativos, um endereço diferente, um sócio a menos e uma empresa fora do Simples
+    let antigo = Connection::open(pasta.path().join("antigo.db")).unwrap();
+    antigo
+        .execute_batch(
+            "DELETE FROM estabelecimento WHERE rowid IN
+                (SELECT rowid FROM estabelecimento WHERE matriz_filial = '2' LIMIT 3);
+             INSERT INTO estabelecimento (cnpj, cnpj_basico, cnpj_ordem, cnpj_dv, matriz_filial, uf, cnae_fiscal, situacao_cadastral)
+                VALUES ('11222333000181', '11222333', '0001', '81', '1', 'SP', '4711302', '02');
+             UPDATE estabelecimento SET situacao_cadastral = '02' WHERE rowid IN
+                (SELECT rowid FROM estabelecimento WHERE situacao_cadastral = '08' LIMIT 2);
+             UPDATE estabelecimento SET numero = 'S/N-ANTIGO' WHERE rowid IN
+                (SELECT rowid FROM estabelecimento WHERE situacao_cadastral = '04' LIMIT 1);
+             DELETE FROM socios WHERE rowid IN (SELECT rowid FROM socios WHERE cnpj IS NOT NULL LIMIT 1);
+             UPDATE simples SET opcao_simples = 'N' WHERE rowid IN
+                (SELECT rowid FROM simples WHERE opcao_simples = 'S' LIMIT 1);
+             UPDATE _referencia SET valor = '2024-04' WHERE referencia = 'CNPJ';",
+        )
+        .unwrap();
+    drop(antigo);
+
+    executar(pasta.path(), &["comparar", "antigo.db", "saida/cnpj.db", "-o", "alteracoes.json"]);
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(pasta.path().join("alteracoes.json")).unwrap()).unwrap();
+    let resumo = &json["resumo"];
+    assert_eq!(resumo["referencia_antiga"], "2024-04");
+    assert_eq!(resumo["totais"]["novo"], 3);
+    assert_eq!(resumo["totais"]["removido"], 1);
+    assert_eq!(resumo["totais"]["situacao"], 2);
+    assert_eq!(resumo["transicoes_situacao"]["ativa → baixada"], 2);
+    assert_eq!(resumo["encerrados"], 2);
+    assert_eq!(resumo["totais"]["endereco"], 1);
+    assert_eq!(resumo["totais"]["entrada_socio"], 1);
+    assert_eq!(resumo["totais"]["saida_socio"], 0);
+    assert_eq!(resumo["totais"]["opcao_simples"], 1);
+    assert_eq!(resumo["por_uf"]["SP"]["removido"], 1);
+
+    let alteracoes = json["alteracoes"].as_array().unwrap();
+    let total: u64 = resumo["totais"].as_object().unwrap().values().map(|v| v.as_u64().unwrap()).sum();
+    assert_eq!(alteracoes.len() as u64, total);
+    let removido = alteracoes.iter().find(|a| a["tipo"] == "removido").unwrap();
+    assert_eq!(removido["cnpj"], "11222333000181");
+    assert_eq!(removido["antes"], "ativa");
+
+    // Em CSV, as mesmas alterações e um segundo arquivo com as contagens
+    executar(pasta.path(), &["comparar", "antigo.db", "saida/cnpj.db", "-o", "alteracoes.csv"]);
+    let csv = std::fs::read_to_string(pasta.path().join("alteracoes.csv")).unwrap();
+    assert_eq!(csv.lines().count(), alteracoes.len() + 1);
+    let resumo_csv = std::fs::read_to_string(pasta.path().join("alteracoes-resumo.csv")).unwrap();
+    assert!(resumo_csv.lines().any(|linha| linha == "uf,SP,removido,1"));
+}
@@ -0,0 +1,120 @@
+//! Prioridade das opções: arquivo de configuração → variáveis de ambiente (inclusive os
+//! nomes antigos `API_HOST` e `API_PORT`) → linha de comando.
+
+mod common;
+
+use common::executar;
+use std::fs;
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const REFERENCIA: &str = "2024-07";
+
+const CONFIGURACAO: &str = r#"
+[process]
+input = "zips"
+output = "saida"
+
+[server]
+host = "127.0.0.3"
+port = {porta}
+chaves = "acesso.db"
+"#;
+
+fn porta_livre() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+fn comando(pasta: &Path) -> Command {
+    let mut comando = Command::new(env!("CARGO_BIN_EXE_cnpj-sqlite"));
+    comando.current_dir(pasta).arg("--quiet");
+    comando
+}
+
+fn preparar(pasta: &Path, porta_arquivo: u16) {
+    fs::write(
+        pasta.join("cnpj-sqlite.toml"),
+        CONFIGURACAO.replace("{porta}", &porta_arquivo.to_string()),
+    )
+    .unwrap();
+}
+
+#[test]
+fn ambiente_vale_sobre_o_arquivo() {
+    let pasta = tempfile::tempdir().unwrap();
+    preparar(pasta.path(), 7001);
+
+    let saida = comando(pasta.path())
+        .args(["config", "show"])
+        .env("API_HOST", "127.0.0.2")
+        .env("API_PORT", "7002")
+        .env("CNPJ_SQLITE_SERVER_PORT", "7003")
+        .output()
+        .unwrap();
+    assert!(saida.status.success(), "{}", String::from_utf8_lossy(&saida.stderr));
+    let texto = String::from_utf8(saida.stdout).unwrap();
+    let linha = |chave: &str| {
+        texto
+            .lines()
+            .find(|l| l.starts_with(&format!("{} = ", chave)))
+            .unwrap_or_else(|| panic!("{} ausente em:\n{}", chave, texto))
+            .to_string()
+    };
+
+    assert!(linha("input").contains("\"zips\"") && linha("input").contains("# arquivo"));
+    assert!(linha("host").contains("\"127.0.0.2\"") && linha("host").contains("variável API_HOST"));
+    // O nome novo tem prioridade sobre o antigo
+    assert!(linha("port").starts_with("port = 7003") && linha("port").contains("variável CNPJ_SQLITE_SERVER_PORT"));
+    assert!(linha("chaves").contains("\"acesso.db\"") && linha("chaves").contains("# arquivo"));
+}
+
+#[test]
+fn linha_de_comando_vale_sobre_ambiente_e_arquivo() {
+    let pasta = tempfile::tempdir().unwrap();
+    let (porta_arquivo, porta_ambiente, porta_cli) = (porta_livre(), porta_livre(), porta_livre());
+    preparar(pasta.path(), porta_arquivo);
+    executar(pasta.path(), &["gerar-amostra", "-o", "zips", "-n", "50", "--partes", "1", "-r", REFERENCIA]);
+    // Entrada e saída vêm do arquivo
+    executar(pasta.path(), &["process"]);
+    assert!(pasta.path().join("saida/cnpj.db").exists());
+
+    // `chaves` sem --arquivo usa server.chaves
+    executar(pasta.path(), &["chaves", "criar", "--nome", "teste"]);
+    assert!(pasta.path().join("acesso.db").exists());
+    assert!(!pasta.path().join("chaves.db").exists());
+
+    // Host do ambiente, porta da linha de comando e, sem --database, o banco gerado por `process`
+    let mut processo = comando(pasta.path())
+        .args(["server", "-p", &porta_cli.to_string()])
+        .env("API_HOST", "127.0.0.2")
+        .env("API_PORT", porta_ambiente.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let base = format!("http://127.0.0.2:{}", porta_cli);
+    let limite = Instant::now() + Duration::from_secs(30);
+    let resposta = loop {
+        match reqwest::blocking::get(format!("{}/health", base)) {
+            Ok(resposta) => break resposta,
+            Err(_) if Instant::now() < limite => thread::sleep(Duration::from_millis(100)),
+            Err(e) => {
+                let _ = processo.kill();
+                panic!("servidor não respondeu em {}: {}", base, e);
+            }
+        }
+    };
+    let status = resposta.status();
+    let ignoradas = [("127.0.0.3", porta_arquivo), ("127.0.0.2", porta_ambiente)]
+        .map(|endereco| TcpStream::connect(endereco).is_err());
+    let _ = processo.kill();
+    let _ = processo.wait();
+
+    assert_eq!(status, 200);
+    assert_eq!(ignoradas, [true, true]);
+    assert!(!pasta.path().join("dados-publicos").exists());
+}
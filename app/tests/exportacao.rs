@@ -0,0 +1,70 @@
+//! `export` do banco processado a partir da amostra sintética, particionado por UF, nos
+//! três formatos.
+
+mod common;
+
+use common::executar;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+/// Linhas de cada partição `uf=XX`, lidas dos arquivos gerados
+fn linhas_por_particao(pasta: &Path, arquivo: &str) -> BTreeMap<String, u64> {
+    let mut linhas = BTreeMap::new();
+    for entrada in std::fs::read_dir(pasta).unwrap() {
+        let particao = entrada.unwrap().path();
+        let nome = particao.file_name().unwrap().to_string_lossy().into_owned();
+        let caminho = particao.join(arquivo);
+        let total = match caminho.extension().unwrap().to_str().unwrap() {
+            "parquet" => {
+                let leitor = SerializedFileReader::new(File::open(&caminho).unwrap()).unwrap();
+                leitor.metadata().file_metadata().num_rows() as u64
+            }
+            // Cabeçalho no CSV, uma linha por registro no NDJSON
+            "csv" => std::fs::read_to_string(&caminho).unwrap().lines().count() as u64 - 1,
+            _ => std::fs::read_to_string(&caminho).unwrap().lines().count() as u64,
+        };
+        linhas.insert(nome, total);
+    }
+    linhas
+}
+
+#[test]
+fn export_particionado_por_uf_em_todos_os_formatos() {
+    let pasta = tempfile::tempdir().unwrap();
+    executar(pasta.path(), &["gerar-amostra", "-o", "zips", "-n", "200", "-r", "2024-05"]);
+    executar(pasta.path(), &["process", "-i", "zips", "-o", "saida"]);
+
+    // Estabelecimentos sem UF vão para a partição padrão do Hive
+    let conn = Connection::open(pasta.path().join("saida/cnpj.db")).unwrap();
+    conn.execute("UPDATE estabelecimento SET uf = '' WHERE rowid IN (SELECT rowid FROM estabelecimento LIMIT 2)", [])
+        .unwrap();
+    let mut esperado = BTreeMap::new();
+    let mut stmt = conn.prepare("SELECT uf, COUNT(*) FROM estabelecimento GROUP BY uf").unwrap();
+    for linha in stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))).unwrap() {
+        let (uf, total) = linha.unwrap();
+        let particao = if uf.is_empty() { "__HIVE_DEFAULT_PARTITION__".to_string() } else { uf };
+        esperado.insert(format!("uf={}", particao), total as u64);
+    }
+    drop(stmt);
+    drop(conn);
+    assert!(esperado.len() > 2);
+
+    for formato in ["parquet", "csv", "ndjson"] {
+        let destino = format!("export-{}", formato);
+        executar(
+            pasta.path(),
+            &[
+                "export", "-d", "saida/cnpj.db", "-f", formato, "-o", &destino,
+                "--partition-by-uf", "--row-group-size", "50",
+            ],
+        );
+        let linhas = linhas_por_particao(
+            &pasta.path().join(&destino),
+            &format!("estabelecimento_completo.{}", formato),
+        );
+        assert_eq!(linhas, esperado, "partições do {}", formato);
+    }
+}
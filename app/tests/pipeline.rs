@@ -0,0 +1,227 @@
+//! Testes de ponta a ponta sobre uma amostra sintética: `gerar-amostra` → `process` →
+//! `cnae-secundaria` → consultas à API, usando o binário compilado.
+
+mod common;
+
+use common::executar;
+use cnpj_sqlite::amostra::{self, OpcoesAmostra};
+use cnpj_sqlite::cnpj;
+use cnpj_sqlite::consultas::CnpjResponse;
+use cnpj_sqlite::leitor::ReceitaReader;
+use cnpj_sqlite::models::{Empresa, Estabelecimento, Simples, Socio};
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+use std::net::TcpListener;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const EMPRESAS: usize = 300;
+const REFERENCIA: &str = "2024-05";
+
+/// Todos os registros de um tipo, lidos dos ZIPs gerados
+fn ler_zips<T: cnpj_sqlite::leitor::RegistroReceita>(pasta: &Path, prefixo: &str) -> Vec<T> {
+    let mut zips: Vec<_> = std::fs::read_dir(pasta)
+        .unwrap()
+        .map(|entrada| entrada.unwrap().path())
+        .filter(|caminho| caminho.file_name().unwrap().to_string_lossy().starts_with(prefixo))
+        .collect();
+    zips.sort();
+    zips.iter()
+        .flat_map(|zip| ReceitaReader::<T>::abrir_zip(zip, None).unwrap())
+        .map(|registro| registro.unwrap())
+        .collect()
+}
+
+/// Servidor da API em uma porta livre, encerrado quando sai de escopo
+struct Servidor {
+    processo: Child,
+    base: String,
+}
+
+impl Servidor {
+    fn iniciar(pasta: &Path, banco: &str) -> Self {
+        let porta = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let processo = Command::new(env!("CARGO_BIN_EXE_cnpj-sqlite"))
+            .current_dir(pasta)
+            .args(["--quiet", "server", "-d", banco, "-p", &porta.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("servidor não iniciou");
+        let servidor = Self { processo, base: format!("http://127.0.0.1:{}", porta) };
+
+        let limite = Instant::now() + Duration::from_secs(30);
+        while servidor.get("/health").map(|r| r.status() != 200).unwrap_or(true) {
+            assert!(Instant::now() < limite, "servidor não respondeu a /health");
+            thread::sleep(Duration::from_millis(100));
+        }
+        servidor
+    }
+
+    fn get(&self, caminho: &str) -> reqwest::Result<reqwest::blocking::Response> {
+        reqwest::blocking::get(format!("{}{}", self.base, caminho))
+    }
+
+    fn json(&self, caminho: &str) -> serde_json::Value {
+        serde_json::from_str(&self.get(caminho).unwrap().text().unwrap()).unwrap()
+    }
+
+    fn post(&self, caminho: &str, corpo: &str) -> reqwest::blocking::Response {
+        reqwest::blocking::Client::new()
+            .post(format!("{}{}", self.base, caminho))
+            .body(corpo.to_string())
+            .send()
+            .unwrap()
+    }
+}
+
+impl Drop for Servidor {
+    fn drop(&mut self) {
+        let _ = self.processo.kill();
+        let _ = self.processo.wait();
+    }
+}
+
+#[test]
+fn amostra_processada_e_consultada_pela_api() {
+    let pasta = tempfile::tempdir().unwrap();
+    let zips = pasta.path().join("zips");
+    executar(
+        pasta.path(),
+        &["gerar-amostra", "-o", "zips", "-n", &EMPRESAS.to_string(), "--partes", "3", "-r", REFERENCIA],
+    );
+    executar(pasta.path(), &["process", "-i", "zips", "-o", "saida"]);
+    executar(pasta.path(), &["cnae-secundaria", "-d", "saida/cnpj.db"]);
+
+    let empresas: Vec<Empresa> = ler_zips(&zips, "Empresas");
+    let estabelecimentos: Vec<Estabelecimento> = ler_zips(&zips, "Estabelecimentos");
+    let socios: Vec<Socio> = ler_zips(&zips, "Socios");
+    let simples: Vec<Simples> = ler_zips(&zips, "Simples");
+    assert_eq!(empresas.len(), EMPRESAS);
+    assert!(estabelecimentos.iter().all(|e| cnpj::dv_valido(&e.cnpj())));
+
+    // O banco tem tudo o que foi gerado
+    let banco = Connection::open(pasta.path().join("saida/cnpj.db")).unwrap();
+    let contar = |sql: &str| banco.query_row(sql, [], |linha| linha.get::<_, i64>(0)).unwrap() as usize;
+    assert_eq!(contar("SELECT COUNT(*) FROM empresas"), empresas.len());
+    assert_eq!(contar("SELECT COUNT(*) FROM estabelecimento"), estabelecimentos.len());
+    assert_eq!(contar("SELECT COUNT(*) FROM socios"), socios.len());
+    assert_eq!(contar("SELECT COUNT(*) FROM simples"), simples.len());
+    assert_eq!(contar("SELECT COUNT(*) FROM cnae"), contar("SELECT COUNT(DISTINCT codigo) FROM cnae"));
+
+    // Uma linha em cnae_secundaria para cada CNAE secundário de cada estabelecimento
+    let secundarias: usize = estabelecimentos
+        .iter()
+        .map(|e| e.cnae_fiscal_secundaria.split(',').filter(|c| !c.is_empty()).count())
+        .sum();
+    assert!(secundarias > 0);
+    assert_eq!(contar("SELECT COUNT(*) FROM cnae_secundaria"), secundarias);
+
+    // Matriz com sócios e Simples, para conferir a resposta completa
+    let mut socios_por_empresa: HashMap<&str, usize> = HashMap::new();
+    for socio in &socios {
+        *socios_por_empresa.entry(socio.cnpj_basico.as_str()).or_default() += 1;
+    }
+    let com_simples: HashSet<&str> = simples.iter().map(|s| s.cnpj_basico.as_str()).collect();
+    let escolhido = estabelecimentos
+        .iter()
+        .find(|e| {
+            e.matriz_filial == "1"
+                && socios_por_empresa.contains_key(e.cnpj_basico.as_str())
+                && com_simples.contains(e.cnpj_basico.as_str())
+        })
+        .expect("amostra sem matriz com sócios e Simples");
+    let empresa = empresas.iter().find(|e| e.cnpj_basico == escolhido.cnpj_basico).unwrap();
+
+    // `consulta` abre o banco só para leitura: não muda o modo do journal
+    let modo = |banco: &Connection| banco.query_row("PRAGMA journal_mode", [], |l| l.get::<_, String>(0)).unwrap();
+    let _: String = banco.query_row("PRAGMA journal_mode = DELETE", [], |l| l.get(0)).unwrap();
+    executar(pasta.path(), &["consulta", &escolhido.cnpj(), "-d", "saida/cnpj.db", "-f", "json"]);
+    assert_eq!(modo(&banco), "delete");
+
+    let servidor = Servidor::iniciar(pasta.path(), "saida/cnpj.db");
+
+    let resposta = servidor.get(&format!("/cnpj/{}", escolhido.cnpj())).unwrap();
+    assert_eq!(resposta.status(), 200);
+    let dados: CnpjResponse = serde_json::from_str(&resposta.text().unwrap()).unwrap();
+    assert_eq!(dados.cnpj, escolhido.cnpj());
+    assert_eq!(dados.estabelecimento.uf, escolhido.uf);
+    assert_eq!(dados.estabelecimento.logradouro, escolhido.logradouro);
+    assert!(dados.estabelecimento.cnae_fiscal_desc.is_some());
+    assert!(dados.estabelecimento.municipio_desc.is_some());
+    assert_eq!(dados.empresa.map(|e| e.razao_social), Some(empresa.razao_social.clone()));
+    assert_eq!(dados.socios.len(), socios_por_empresa[escolhido.cnpj_basico.as_str()]);
+    assert!(dados.simples.is_some());
+
+    // Pontuação é aceita; CNPJ malformado e CNPJ ausente da base têm respostas próprias
+    let formatado = cnpj::formatar(&escolhido.cnpj()).replace('/', "");
+    assert_eq!(servidor.get(&format!("/cnpj/{}", formatado)).unwrap().status(), 200);
+    assert_eq!(servidor.get("/cnpj/123").unwrap().status(), 400);
+    let existentes: HashSet<String> = estabelecimentos.iter().map(|e| e.cnpj()).collect();
+    let ausente = (0u64..)
+        .map(|n| format!("{:012}", 999_000_000_000 + n))
+        .map(|base| format!("{}{}", base, cnpj::calcular_dv(&base).unwrap()))
+        .find(|c| !existentes.contains(c))
+        .unwrap();
+    assert_eq!(servidor.get(&format!("/cnpj/{}", ausente)).unwrap().status(), 404);
+
+    let status: serde_json::Value =
+        serde_json::from_str(&servidor.get("/api/database/status").unwrap().text().unwrap()).unwrap();
+    assert_eq!(status["referencia"], REFERENCIA);
+    assert_eq!(status["tabelas"]["empresas"], EMPRESAS as i64);
+}
+
+#[test]
+fn processamento_pela_api_substitui_o_banco_servido() {
+    // Configuração padrão: os ZIPs em dados-publicos-zip e o banco servido é o mesmo
+    // dados-publicos/cnpj.db que o processamento gera
+    let pasta = tempfile::tempdir().unwrap();
+    executar(pasta.path(), &["gerar-amostra", "-o", "dados-publicos-zip", "-n", "100", "-r", REFERENCIA]);
+    executar(pasta.path(), &["process"]);
+    let servidor = Servidor::iniciar(pasta.path(), "dados-publicos/cnpj.db");
+    assert_eq!(servidor.json("/api/database/status")["referencia"], REFERENCIA);
+
+    let resposta = servidor.post("/api/process/start", r#"{"referencia": "2024-06"}"#);
+    assert_eq!(resposta.status(), 202);
+    let limite = Instant::now() + Duration::from_secs(120);
+    let job = loop {
+        let job = servidor.json("/api/progress")["process"].clone();
+        if job["situacao"] != "executando" {
+            break job;
+        }
+        assert!(Instant::now() < limite, "processamento não terminou");
+        thread::sleep(Duration::from_millis(200));
+    };
+    assert_eq!(job["situacao"], "concluido", "{}", job);
+
+    // O banco novo já está em uso, no mesmo caminho, sem arquivos temporários ao lado
+    let status = servidor.json("/api/database/status");
+    assert_eq!(status["referencia"], "2024-06");
+    assert_eq!(status["banco"], "dados-publicos/cnpj.db");
+    for sobra in ["cnpj.db.novo", "cnpj.db.pronto", "cnpj.db.anterior"] {
+        assert!(!pasta.path().join("dados-publicos").join(sobra).exists(), "{} ficou para trás", sobra);
+    }
+}
+
+#[test]
+fn mesma_semente_gera_a_mesma_amostra() {
+    let gerar = |semente: u64| {
+        let pasta = tempfile::tempdir().unwrap();
+        let opcoes = OpcoesAmostra {
+            output_dir: pasta.path().to_string_lossy().into_owned(),
+            empresas: 50,
+            partes: 2,
+            referencia: REFERENCIA.to_string(),
+            semente,
+        };
+        let resumo = amostra::gerar(&opcoes).unwrap();
+        assert_eq!(resumo.empresas, 50);
+        let estabelecimentos: Vec<Estabelecimento> = ler_zips(pasta.path(), "Estabelecimentos");
+        assert_eq!(estabelecimentos.len() as u64, resumo.estabelecimentos);
+        estabelecimentos.into_iter().map(|e| (e.cnpj(), e.nome_fantasia, e.logradouro)).collect::<Vec<_>>()
+    };
+    assert_eq!(gerar(7), gerar(7));
+    assert_ne!(gerar(7), gerar(8));
+}
@@ -0,0 +1,114 @@
+//! `recortar` sobre um banco processado a partir da amostra sintética: por UF e por CNAE,
+//! o recorte tem só os estabelecimentos do filtro e é completo em si (sem sócios, empresas
+//! ou Simples de fora, com as tabelas de código inteiras).
+
+mod common;
+
+use common::executar;
+use rusqlite::Connection;
+use std::path::Path;
+
+const TABELAS_CODIGO: &[&str] = &["cnae", "motivo", "municipio", "natureza_juridica", "pais", "qualificacao_socio"];
+
+fn contar(banco: &Connection, sql: &str) -> i64 {
+    banco.query_row(sql, [], |linha| linha.get(0)).unwrap()
+}
+
+/// Confere que nenhuma linha do recorte aponta para um estabelecimento ou empresa de fora
+/// e que as tabelas de código vieram inteiras
+fn conferir_completo(recorte: &Path, origem: &Path) {
+    let banco = Connection::open(recorte).unwrap();
+    banco.execute("ATTACH DATABASE ?1 AS origem", [origem.to_str().unwrap()]).unwrap();
+    assert!(contar(&banco, "SELECT COUNT(*) FROM estabelecimento") > 0);
+    for sql in [
+        "SELECT COUNT(*) FROM empresas WHERE cnpj_basico NOT IN (SELECT cnpj_basico FROM estabelecimento)",
+        "SELECT COUNT(*) FROM socios WHERE cnpj_basico NOT IN (SELECT cnpj_basico FROM estabelecimento)",
+        "SELECT COUNT(*) FROM simples WHERE cnpj_basico NOT IN (SELECT cnpj_basico FROM estabelecimento)",
+        "SELECT COUNT(*) FROM cnae_secundaria WHERE cnpj NOT IN (SELECT cnpj FROM estabelecimento)",
+    ] {
+        assert_eq!(contar(&banco, sql), 0, "{}", sql);
+    }
+    // Empresas, sócios e Simples das empresas selecionadas vêm todos
+    for tabela in ["empresas", "socios", "simples"] {
+        let esperado = contar(&banco, &format!(
+            "SELECT COUNT(*) FROM origem.{} WHERE cnpj_basico IN (SELECT cnpj_basico FROM main.estabelecimento)",
+            tabela
+        ));
+        assert_eq!(contar(&banco, &format!("SELECT COUNT(*) FROM main.{}", tabela)), esperado, "{}", tabela);
+    }
+    for tabela in TABELAS_CODIGO {
+        let quantidade = contar(&banco, &format!("SELECT COUNT(*) FROM main.{}", tabela));
+        assert!(quantidade > 0, "{} vazia", tabela);
+        assert_eq!(quantidade, contar(&banco, &format!("SELECT COUNT(*) FROM origem.{}", tabela)), "{}", tabela);
+    }
+}
+
+#[test]
+fn recortes_por_uf_e_por_cnae_sao_completos() {
+    let pasta = tempfile::tempdir().unwrap();
+    executar(pasta.path(), &["gerar-amostra", "-o", "zips", "-n", "300", "--partes", "2", "-r", "2024-05"]);
+    executar(pasta.path(), &["process", "-i", "zips", "-o", "saida"]);
+    executar(pasta.path(), &["cnae-secundaria", "-d", "saida/cnpj.db"]);
+    let origem = Connection::open(pasta.path().join("saida/cnpj.db")).unwrap();
+
+    // Por UF: a mais frequente da amostra
+    let uf: String = origem
+        .query_row(
+            "SELECT uf FROM estabelecimento WHERE uf <> '' GROUP BY uf ORDER BY COUNT(*) DESC, uf LIMIT 1",
+            [],
+            |l| l.get(0),
+        )
+        .unwrap();
+    executar(pasta.path(), &["recortar", "-d", "saida/cnpj.db", "-o", "uf.db", "--uf", &uf]);
+    let recorte = Connection::open(pasta.path().join("uf.db")).unwrap();
+    assert_eq!(
+        contar(&recorte, "SELECT COUNT(*) FROM estabelecimento"),
+        contar(&origem, &format!("SELECT COUNT(*) FROM estabelecimento WHERE uf = '{}'", uf)),
+    );
+    assert_eq!(contar(&recorte, &format!("SELECT COUNT(*) FROM estabelecimento WHERE uf <> '{}'", uf)), 0);
+    conferir_completo(&pasta.path().join("uf.db"), &pasta.path().join("saida/cnpj.db"));
+
+    // Por CNAE: a divisão (dois primeiros dígitos) mais frequente, principal ou secundária
+    let divisao: String = origem
+        .query_row(
+            "SELECT substr(cnae_fiscal, 1, 2) FROM estabelecimento GROUP BY 1 ORDER BY COUNT(*) DESC, 1 LIMIT 1",
+            [],
+            |l| l.get(0),
+        )
+        .unwrap();
+    executar(pasta.path(), &["recortar", "-d", "saida/cnpj.db", "-o", "cnae.db", "--cnae", &divisao]);
+    let recorte = Connection::open(pasta.path().join("cnae.db")).unwrap();
+    let filtro = format!(
+        "cnae_fiscal LIKE '{0}%' OR cnpj IN (SELECT cnpj FROM cnae_secundaria WHERE cnae_fiscal_secundaria LIKE '{0}%')",
+        divisao
+    );
+    assert_eq!(
+        contar(&recorte, "SELECT COUNT(*) FROM estabelecimento"),
+        contar(&origem, &format!("SELECT COUNT(*) FROM estabelecimento WHERE {}", filtro)),
+    );
+    assert_eq!(contar(&recorte, &format!("SELECT COUNT(*) FROM estabelecimento WHERE NOT ({})", filtro)), 0);
+    conferir_completo(&pasta.path().join("cnae.db"), &pasta.path().join("saida/cnpj.db"));
+}
+
+#[test]
+fn recorte_que_falha_nao_deixa_arquivos() {
+    let pasta = tempfile::tempdir().unwrap();
+    executar(pasta.path(), &["gerar-amostra", "-o", "zips", "-n", "50", "--partes", "1", "-r", "2024-05"]);
+    executar(pasta.path(), &["process", "-i", "zips", "-o", "saida"]);
+    // Sem a tabela de municípios, o recorte falha depois de criar o banco de destino
+    Connection::open(pasta.path().join("saida/cnpj.db"))
+        .unwrap()
+        .execute_batch("DROP TABLE municipio")
+        .unwrap();
+
+    let saida = std::process::Command::new(env!("CARGO_BIN_EXE_cnpj-sqlite"))
+        .current_dir(pasta.path())
+        .args(["--quiet", "--yes", "recortar", "-d", "saida/cnpj.db", "-o", "recorte.db", "--uf", "SP"])
+        .output()
+        .unwrap();
+    assert!(!saida.status.success());
+    assert!(String::from_utf8_lossy(&saida.stderr).contains("municipio"));
+    for arquivo in ["recorte.db", "recorte.db-wal", "recorte.db-shm", "recorte.db-journal"] {
+        assert!(!pasta.path().join(arquivo).exists(), "{} ficou para trás", arquivo);
+    }
+}
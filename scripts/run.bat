@@ -1,6 +1,6 @@
 @echo off
 REM Script de inicialização do processo completo de CNPJ-SQLite
-REM Executa as 3 partes: Download, Processamento e CNAE Secundário
+REM Executa o subcomando atualizar: Download, Verificação, Processamento, CNAE Secundário e Otimização
 REM Execute este script na raiz do projeto
 
 setlocal enabledelayedexpansion
@@ -48,97 +48,15 @@ if not exist "%BINARY_PATH%" (
     echo.
 )
 
-REM Parte 1: Download
-echo [INFO] ═══════════════════════════════════════════════════════════
-echo [INFO] PARTE 1/3: Download dos arquivos ZIP
-echo [INFO] ═══════════════════════════════════════════════════════════
+REM Download, verificação, processamento, CNAE secundário e otimização.
+REM Argumentos extras vão para o subcomando (ex: --pular download, --forcar processamento)
+echo [INFO] Atualizando o banco de dados (download, verificação, processamento, CNAE secundário e otimização)...
 echo.
-
-set "DOWNLOAD_NEW=0"
-if exist "%ZIP_DIR%" (
-    dir /b "%ZIP_DIR%\*.zip" >nul 2>&1
-    if not errorlevel 1 (
-        echo [WARNING] Diretório %ZIP_DIR% já contém arquivos ZIP.
-        set /p "REPLY=Deseja baixar novamente? (s/N): "
-        echo.
-        if /i "!REPLY!"=="s" (
-            set "DOWNLOAD_NEW=1"
-        ) else (
-            echo [INFO] Pulando download. Usando arquivos existentes.
-        )
-    ) else (
-        set "DOWNLOAD_NEW=1"
-    )
-) else (
-    set "DOWNLOAD_NEW=1"
-)
-
-if "!DOWNLOAD_NEW!"=="1" (
-    echo [INFO] Iniciando download dos arquivos...
-    "%BINARY_PATH%" download --output "%ZIP_DIR%"
-    if errorlevel 1 (
-        echo [ERROR] Falha no download.
-        exit /b 1
-    )
-    echo [SUCCESS] Download concluído!
-)
-
-echo.
-
-REM Parte 2: Processamento
-echo [INFO] ═══════════════════════════════════════════════════════════
-echo [INFO] PARTE 2/3: Processamento dos arquivos CSV para SQLite
-echo [INFO] ═══════════════════════════════════════════════════════════
-echo.
-
-set "PROCESS_NEW=0"
-if exist "%DB_PATH%" (
-    echo [WARNING] Banco de dados %DB_PATH% já existe.
-    set /p "REPLY=Deseja reprocessar? Isso apagará o banco existente. (s/N): "
-    echo.
-    if /i "!REPLY!"=="s" (
-        set "PROCESS_NEW=1"
-        echo [INFO] Removendo banco existente...
-        if exist "%DB_PATH%" del /f /q "%DB_PATH%"
-        if exist "%DB_PATH%-shm" del /f /q "%DB_PATH%-shm"
-        if exist "%DB_PATH%-wal" del /f /q "%DB_PATH%-wal"
-    ) else (
-        echo [INFO] Pulando processamento. Usando banco existente.
-    )
-) else (
-    set "PROCESS_NEW=1"
-)
-
-if "!PROCESS_NEW!"=="1" (
-    echo [INFO] Iniciando processamento...
-    "%BINARY_PATH%" process --input "%ZIP_DIR%" --output "%DB_DIR%" --cleanup true
-    if errorlevel 1 (
-        echo [ERROR] Falha no processamento.
-        exit /b 1
-    )
-    echo [SUCCESS] Processamento concluído!
-)
-
-echo.
-
-REM Parte 3: CNAE Secundário
-echo [INFO] ═══════════════════════════════════════════════════════════
-echo [INFO] PARTE 3/3: Criação da tabela de CNAE Secundário
-echo [INFO] ═══════════════════════════════════════════════════════════
-echo.
-
-if not exist "%DB_PATH%" (
-    echo [ERROR] Banco de dados não encontrado. Execute a parte 2 primeiro.
-    exit /b 1
-)
-
-echo [INFO] Criando tabela de CNAE Secundário...
-"%BINARY_PATH%" cnae-secundaria --database "%DB_PATH%"
+"%BINARY_PATH%" atualizar --input "%ZIP_DIR%" --output "%DB_DIR%" %*
 if errorlevel 1 (
-    echo [ERROR] Falha na criação da tabela de CNAE Secundário.
+    echo [ERROR] Falha na atualização.
     exit /b 1
 )
-echo [SUCCESS] Tabela de CNAE Secundário criada!
 
 echo.
 echo [INFO] ═══════════════════════════════════════════════════════════
@@ -1,7 +1,7 @@
 #!/bin/bash
 
 # Script de inicialização do processo completo de CNPJ-SQLite
-# Executa as 3 partes: Download, Processamento e CNAE Secundário
+# Executa o subcomando atualizar: Download, Verificação, Processamento, CNAE Secundário e Otimização
 # Execute este script na raiz do projeto
 
 set -e  # Para na primeira ocorrência de erro
@@ -70,71 +70,11 @@ else
     echo ""
 fi
 
-# Parte 1: Download
-print_info "═══════════════════════════════════════════════════════════"
-print_info "PARTE 1/3: Download dos arquivos ZIP"
-print_info "═══════════════════════════════════════════════════════════"
-echo ""
-
-if [ -d "$ZIP_DIR" ] && [ "$(ls -A $ZIP_DIR/*.zip 2>/dev/null)" ]; then
-    print_warning "Diretório $ZIP_DIR já contém arquivos ZIP."
-    read -p "Deseja baixar novamente? (s/N): " -n 1 -r
-    echo ""
-    if [[ ! $REPLY =~ ^[Ss]$ ]]; then
-        print_info "Pulando download. Usando arquivos existentes."
-    else
-        "$BINARY_PATH" download --output "$ZIP_DIR"
-        print_success "Download concluído!"
-    fi
-else
-    print_info "Iniciando download dos arquivos..."
-    "$BINARY_PATH" download --output "$ZIP_DIR"
-    print_success "Download concluído!"
-fi
-
-echo ""
-
-# Parte 2: Processamento
-print_info "═══════════════════════════════════════════════════════════"
-print_info "PARTE 2/3: Processamento dos arquivos CSV para SQLite"
-print_info "═══════════════════════════════════════════════════════════"
-echo ""
-
-if [ -f "$DB_PATH" ]; then
-    print_warning "Banco de dados $DB_PATH já existe."
-    read -p "Deseja reprocessar? Isso apagará o banco existente. (s/N): " -n 1 -r
-    echo ""
-    if [[ ! $REPLY =~ ^[Ss]$ ]]; then
-        print_info "Pulando processamento. Usando banco existente."
-    else
-        print_info "Removendo banco existente..."
-        rm -f "$DB_PATH" "$DB_PATH-shm" "$DB_PATH-wal"
-        print_info "Iniciando processamento..."
-        "$BINARY_PATH" process --input "$ZIP_DIR" --output "$DB_DIR" --cleanup true
-        print_success "Processamento concluído!"
-    fi
-else
-    print_info "Iniciando processamento..."
-    "$BINARY_PATH" process --input "$ZIP_DIR" --output "$DB_DIR" --cleanup true
-    print_success "Processamento concluído!"
-fi
-
+# Download → verificação → processamento → CNAE secundário → otimização.
+# Argumentos extras vão para o subcomando (ex: --pular download, --forcar processamento)
+print_info "Atualizando o banco de dados (download, verificação, processamento, CNAE secundário e otimização)..."
 echo ""
-
-# Parte 3: CNAE Secundário
-print_info "═══════════════════════════════════════════════════════════"
-print_info "PARTE 3/3: Criação da tabela de CNAE Secundário"
-print_info "═══════════════════════════════════════════════════════════"
-echo ""
-
-if [ ! -f "$DB_PATH" ]; then
-    print_error "Banco de dados não encontrado. Execute a parte 2 primeiro."
-    exit 1
-fi
-
-print_info "Criando tabela de CNAE Secundário..."
-"$BINARY_PATH" cnae-secundaria --database "$DB_PATH"
-print_success "Tabela de CNAE Secundário criada!"
+"$BINARY_PATH" atualizar --input "$ZIP_DIR" --output "$DB_DIR" "$@"
 
 echo ""
 print_info "═══════════════════════════════════════════════════════════"
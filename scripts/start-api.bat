@@ -132,12 +132,19 @@ echo.
 
 echo [INFO] Endpoints disponíveis:
 echo   📋 GET /cnpj/{cnpj}  - Consulta dados completos de um CNPJ
-echo   ❤️  GET /health       - Verifica status do servidor
+echo   ❤️  GET /health       - Verifica o servidor e a conexão com o banco
+echo   🗄️  GET /api/database/status - Metadados do banco (referência, versões, contagens)
+echo   ⚙️  POST /api/download/start, /api/process/start - Inicia download ou processamento
+echo   📈 GET /api/progress - Progresso dos trabalhos
+echo   🔄 POST /api/admin/reload - Troca o banco sem reiniciar
+echo   📈 GET /metrics - Métricas no formato do Prometheus
+echo   📘 GET /docs/ - Documentação interativa (OpenAPI em /openapi.json)
 echo.
 
 echo [INFO] Exemplo de uso:
 echo   curl http://%HOST%:%PORT%/cnpj/00000000000191
 echo   curl http://%HOST%:%PORT%/health
+echo   curl http://%HOST%:%PORT%/api/database/status
 echo.
 
 echo [WARNING] Pressione Ctrl+C para parar o servidor